@@ -20,6 +20,13 @@ struct Args {
 
     #[clap(long, default_value = "10")]
     recv_timeout: u64,
+    /// directory to persist backend data in. Backends keep everything in
+    /// memory when this is not set.
+    #[clap(long)]
+    data_dir: Option<String>,
+    /// serve Prometheus metrics on http://<addr>/metrics, e.g. 0.0.0.0:9100
+    #[clap(long)]
+    metrics_addr: Option<String>,
 }
 
 #[tokio::main]
@@ -32,6 +39,8 @@ async fn main() -> TribResult<()> {
         args.cfg,
         args.ready_addrs,
         args.recv_timeout,
+        args.data_dir,
+        args.metrics_addr,
     )
     .await
 }
@@ -20,6 +20,13 @@ struct Args {
 
     #[clap(long, default_value = "10")]
     recv_timeout: u64,
+    /// keep each keeper's raft state in a sub-directory of this directory,
+    /// so that it survives a restart
+    #[clap(long)]
+    data_dir: Option<String>,
+    /// serve Prometheus metrics on http://<addr>/metrics, e.g. 0.0.0.0:9100
+    #[clap(long)]
+    metrics_addr: Option<String>,
 }
 
 #[tokio::main]
@@ -32,6 +39,8 @@ async fn main() -> TribResult<()> {
         args.config,
         args.ready_addrs,
         args.recv_timeout,
+        args.data_dir,
+        args.metrics_addr,
     )
     .await
 }
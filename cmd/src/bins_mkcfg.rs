@@ -1,10 +1,13 @@
+use std::path::Path;
 use std::process;
 
 use clap::Parser;
 use tribbler::{
     addr,
-    config::{self, DEFAULT_CONFIG_LOCATION},
+    config::{self, RateLimit, DEFAULT_CONFIG_LOCATION},
     err::TribResult,
+    storage::{EvictionPolicy, MemoryLimit},
+    tls,
 };
 
 /// generates a [config::Config] based on the command arguments. The config
@@ -28,6 +31,42 @@ struct Options {
     /// whether or not to used fixed versus random port numbers
     #[clap(short, long)]
     fix: bool,
+    /// number of backends holding a copy of every bin
+    #[clap(short, long, default_value_t = config::DEFAULT_REPLICATION)]
+    replication: usize,
+    /// replicas that must answer a read
+    #[clap(long, default_value = "1")]
+    read_quorum: usize,
+    /// replicas that must accept a write; all of them by default
+    #[clap(long)]
+    write_quorum: Option<usize>,
+    /// points every backend is placed at on the hash ring
+    #[clap(long, default_value_t = config::DEFAULT_VNODES)]
+    vnodes: usize,
+    /// most bytes of keys, values and lists every backend may hold
+    #[clap(long)]
+    memory_limit: Option<u64>,
+    /// evict the least recently used keys when a backend is full, instead
+    /// of refusing the write
+    #[clap(long, requires = "memory_limit")]
+    evict: bool,
+    /// issue a new certificate authority and certificates for every backend
+    /// and keeper into this directory, and only talk TLS with them
+    #[clap(long)]
+    tls_dir: Option<String>,
+    /// posts every user may make a minute
+    #[clap(long)]
+    post_rate: Option<u32>,
+    /// follows every user may make a minute
+    #[clap(long)]
+    follow_rate: Option<u32>,
+    /// sign-ups all users together may make a minute
+    #[clap(long)]
+    sign_up_rate: Option<u32>,
+    /// calls that may be made at once before the rates apply; defaults to
+    /// a minute's worth
+    #[clap(long)]
+    rate_burst: Option<u32>,
 }
 
 fn main() -> TribResult<()> {
@@ -42,6 +81,21 @@ fn main() -> TribResult<()> {
         process::exit(1)
     }
 
+    if args.replication == 0 || args.replication > args.backs {
+        eprintln!(
+            "bad replication: {}. must be between 1 and {}",
+            args.replication, args.backs
+        );
+        process::exit(1)
+    }
+    let write_quorum = args.write_quorum.unwrap_or(args.replication);
+    let replication =
+        config::ReplicationConfig::new(args.replication, args.read_quorum, write_quorum);
+    if let Err(e) = replication.check() {
+        eprintln!("bad quorums: {}", e);
+        process::exit(1)
+    }
+
     let mut p = 3000;
     if !args.fix {
         p = addr::rand::rand_port();
@@ -59,7 +113,41 @@ fn main() -> TribResult<()> {
         p += 1;
     }
 
-    let cfg = config::Config { backs, keepers };
+    let tls = match &args.tls_dir {
+        Some(dir) => {
+            let nodes = [&backs[..], &keepers[..]].concat();
+            Some(tls::generate(Path::new(dir), &nodes)?)
+        }
+        None => None,
+    };
+
+    let rate = |per_minute: Option<u32>| {
+        per_minute.map(|per_minute| RateLimit {
+            per_minute,
+            burst: args.rate_burst.unwrap_or(per_minute),
+        })
+    };
+    let cfg = config::Config {
+        backs,
+        keepers,
+        replication: args.replication,
+        read_quorum: args.read_quorum,
+        write_quorum: args.write_quorum,
+        vnodes: args.vnodes.max(1),
+        memory_limit: args.memory_limit.map(|bytes| MemoryLimit {
+            bytes,
+            policy: match args.evict {
+                true => EvictionPolicy::EvictLru,
+                false => EvictionPolicy::Reject,
+            },
+        }),
+        tls,
+        rate_limits: config::RateLimits {
+            post: rate(args.post_rate),
+            follow: rate(args.follow_rate),
+            sign_up: rate(args.sign_up_rate),
+        },
+    };
 
     cfg.write(Some(&args.file))
 }
@@ -1,4 +1,5 @@
 use std::{
+    path::Path,
     process,
     sync::{
         mpsc::{self, Sender},
@@ -10,7 +11,15 @@ use std::{
 use lab::{lab1, lab2};
 use log::{error, info, warn, LevelFilter};
 use tokio::join;
-use tribbler::{addr, config::Config, err::TribResult, storage::MemStorage};
+use tribbler::{
+    addr,
+    config::Config,
+    disk::DiskStorage,
+    err::TribResult,
+    metrics,
+    sharded::ShardedStorage,
+    storage::Storage,
+};
 
 #[derive(Debug, Clone)]
 pub enum ProcessType {
@@ -24,12 +33,17 @@ pub async fn main(
     cfg: String,
     _ready_addrs: Vec<String>,
     recv_timeout: u64,
+    data_dir: Option<String>,
+    metrics_addr: Option<String>,
 ) -> TribResult<()> {
     env_logger::builder()
         .default_format()
         .filter_level(log_level)
         .init();
     let config = Arc::new(Config::read(Some(&cfg))?);
+    if let Some(addr) = metrics_addr {
+        spawn_metrics(addr);
+    }
 
     println!("{:?}", config);
     let (tx, rdy) = mpsc::channel();
@@ -46,6 +60,7 @@ pub async fn main(
                 i,
                 config.clone(),
                 Some(tx.clone()),
+                data_dir.clone(),
             )));
         }
     }
@@ -82,16 +97,59 @@ pub async fn main(
     Ok(())
 }
 
+/// Serves the metrics of this process on `http://<addr>/metrics`, logging
+/// when the listener fails.
+pub fn spawn_metrics(addr: String) {
+    info!("serving metrics on http://{}/metrics", addr);
+    tokio::spawn(async move {
+        if let Err(e) = metrics::serve(addr).await {
+            error!("metrics listener failed: {}", e);
+        }
+    });
+}
+
 #[allow(unused_must_use)]
-async fn run_srv(t: ProcessType, idx: usize, config: Arc<Config>, tx: Option<Sender<bool>>) {
+async fn run_srv(
+    t: ProcessType,
+    idx: usize,
+    config: Arc<Config>,
+    tx: Option<Sender<bool>>,
+    data_dir: Option<String>,
+) {
     match t {
         ProcessType::Back => {
-            let cfg = config.back_config(idx, Box::new(MemStorage::default()), tx, None);
+            // each backend on this host keeps its files in its own
+            // sub-directory of the data dir
+            let store: Box<dyn Storage> = match data_dir {
+                Some(dir) => {
+                    let dir = Path::new(&dir).join(format!("back-{}", idx));
+                    match DiskStorage::open(&dir) {
+                        Ok(s) => Box::new(s),
+                        Err(e) => {
+                            error!("failed to open storage in {}: {}", dir.display(), e);
+                            if let Some(tx) = tx {
+                                let _ = tx.send(false);
+                            }
+                            return;
+                        }
+                    }
+                }
+                None => Box::new(ShardedStorage::new()),
+            };
+            let cfg = config.back_config(idx, store, tx, None);
             info!("starting backend on {}", cfg.addr);
             lab1::serve_back(cfg).await;
         }
         ProcessType::Keep => {
-            let cfg = config.keeper_config(idx, tx, None).unwrap();
+            let mut cfg = config.keeper_config(idx, tx, None).unwrap();
+            // likewise each keeper keeps its raft state in its own
+            // sub-directory
+            cfg.data_dir = data_dir.map(|dir| {
+                Path::new(&dir)
+                    .join(format!("keep-{}", idx))
+                    .to_string_lossy()
+                    .into_owned()
+            });
             info!("starting keeper on {}", cfg.addr());
             lab2::serve_keeper(cfg).await;
         }
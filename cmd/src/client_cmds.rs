@@ -8,12 +8,17 @@ use tribbler::{
     storage::{KeyValue, Pattern, Storage},
 };
 
-pub fn app_commands() -> [Command<'static>; 9] {
+pub fn app_commands() -> [Command<'static>; 10] {
     let k = &[Arg::new("key").required(true)];
     let kv = &[
         Arg::new("key").required(true),
         Arg::new("value").required(true),
     ];
+    let cas = &[
+        Arg::new("key").required(true),
+        Arg::new("expected").required(true),
+        Arg::new("value").required(true),
+    ];
     let patt = &[
         Arg::new("prefix").required(false).default_value(""),
         Arg::new("suffix").required(false).default_value(""),
@@ -22,6 +27,7 @@ pub fn app_commands() -> [Command<'static>; 9] {
     [
         Command::new("get").args(k),
         Command::new("set").args(kv),
+        Command::new("cas").args(cas),
         Command::new("keys").args(patt),
         Command::new("list-get").args(k),
         Command::new("list-append").args(kv),
@@ -71,6 +77,18 @@ pub async fn match_storage_cmds(client: &dyn Storage, subcmd: Option<(&str, &Arg
             let kv = get_kv(v);
             print_result(client.set(&kv).await);
         }
+        Some(("cas", v)) => {
+            let expected = v.value_of("expected").unwrap().to_string();
+            print_result(
+                client
+                    .cas(
+                        v.value_of("key").unwrap(),
+                        Some(expected),
+                        v.value_of("value").unwrap(),
+                    )
+                    .await,
+            );
+        }
         Some(("keys", v)) => {
             let pattern = get_pattern(v);
             print_result(client.keys(&pattern).await);
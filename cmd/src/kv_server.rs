@@ -3,7 +3,10 @@
 use clap::Parser;
 use lab::lab1::serve_back;
 use log::{info, LevelFilter};
-use tribbler::{config::BackConfig, err::TribResult};
+use tribbler::{
+    config::BackConfig, disk::DiskStorage, err::TribResult, sharded::ShardedStorage,
+    storage::Storage,
+};
 
 #[derive(Parser, Debug)]
 #[clap(name = "kv-server")]
@@ -13,6 +16,10 @@ struct Options {
 
     #[clap(short, long, default_value = "INFO")]
     log_level: LevelFilter,
+
+    /// directory to persist data in. Data is kept in memory when not set.
+    #[clap(long)]
+    data_dir: Option<String>,
 }
 
 #[tokio::main]
@@ -22,13 +29,18 @@ async fn main() -> TribResult<()> {
         .default_format()
         .filter_level(options.log_level)
         .init();
-    let memstorage = Box::new(tribbler::storage::MemStorage::new());
+    let storage: Box<dyn Storage> = match &options.data_dir {
+        Some(dir) => Box::new(DiskStorage::open(dir)?),
+        None => Box::new(ShardedStorage::new()),
+    };
     let addr = options.address.clone();
     let config = BackConfig {
         addr: options.address,
-        storage: memstorage,
+        storage,
         ready: None,
         shutdown: None,
+        memory_limit: None,
+        tls: None,
     };
     let x = serve_back(config);
     info!("============================================");
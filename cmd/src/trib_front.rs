@@ -3,12 +3,15 @@ use std::str::FromStr;
 use actix_files::Files;
 use actix_web::{web, App, HttpServer};
 use clap::Parser;
+use cmd::bins_run::spawn_metrics;
 use lab::lab2;
 use log::{info, warn, LevelFilter};
+use tribbler::auth::{Accounts, ACCOUNTS_BIN};
 use tribbler::config::Config;
 use tribbler::config::DEFAULT_CONFIG_LOCATION;
 use tribbler::err::{TribResult, TribblerError};
 use tribbler::ref_impl::RefServer;
+use tribbler::storage::MemStorage;
 use tribbler::trib::Server;
 
 type Srv = Box<dyn Server + Send + Sync>;
@@ -56,6 +59,16 @@ struct Cfg {
     /// the host port to bind
     #[clap(long, default_value = "8080")]
     port: u16,
+
+    /// serve Prometheus metrics on http://<addr>/metrics, e.g. 0.0.0.0:9100
+    #[clap(long)]
+    metrics_addr: Option<String>,
+
+    /// a file of `<user> <password>` lines; every user listed who has no
+    /// password yet, such as one made before there were passwords, is given
+    /// that one at startup
+    #[clap(long)]
+    passwords: Option<String>,
 }
 
 #[tokio::main]
@@ -66,25 +79,48 @@ async fn main() -> TribResult<()> {
         .default_format()
         .filter_level(args.log_level)
         .init();
-    let srv_impl: Srv = match args.server_type {
-        ServerType::Ref => Box::new(RefServer::new()),
+    let (srv_impl, accounts): (Srv, Accounts) = match args.server_type {
+        ServerType::Ref => (
+            Box::new(RefServer::new()),
+            Accounts::new(Box::new(MemStorage::new())),
+        ),
         ServerType::Lab => {
             let cfg = Config::read(Some(&args.config))?;
-            let bc = lab2::new_bin_client(cfg.backs).await?;
-            lab2::new_front(bc).await?
+            let bc = lab2::new_replicated_bin_client(
+                cfg.backs.clone(),
+                cfg.replication_config(),
+                cfg.tls.as_ref(),
+            )
+            .await?;
+            let accounts = Accounts::new(bc.bin(ACCOUNTS_BIN).await?);
+            (
+                lab2::new_limited_front(bc, cfg.rate_limits).await?,
+                accounts,
+            )
         }
     };
+    if let Some(addr) = args.metrics_addr {
+        spawn_metrics(addr);
+    }
     let server: web::Data<Srv> = web::Data::new(srv_impl);
+    let accounts = web::Data::new(accounts);
     match populate(&server).await {
         Ok(_) => info!("Pre-populated test-server successfully"),
         Err(e) => warn!("Failed to pre-populate test server: {}", e),
     }
+    if let Some(file) = &args.passwords {
+        set_passwords(&accounts, file).await?;
+    }
     let srv = HttpServer::new(move || {
         App::new()
             .app_data(server.clone())
+            .app_data(accounts.clone())
             .service(
                 web::scope("/api")
+                    .wrap_fn(api::metered)
                     .service(api::add_user)
+                    .service(api::login)
+                    .service(api::logout)
                     .service(api::list_users)
                     .service(api::list_tribs)
                     .service(api::list_home)
@@ -122,16 +158,45 @@ async fn populate(server: &web::Data<Box<dyn Server + Send + Sync>>) -> TribResu
     Ok(())
 }
 
+/// Gives the users listed in `file` their passwords, skipping (with a
+/// warning) any who already have one.
+async fn set_passwords(accounts: &Accounts, file: &str) -> TribResult<()> {
+    for line in std::fs::read_to_string(file)?.lines() {
+        let (user, password) = match line.trim().split_once(' ') {
+            Some((user, password)) => (user, password.trim()),
+            None if line.trim().is_empty() => continue,
+            None => return Err(format!("no password for {} in {}", line.trim(), file).into()),
+        };
+        match accounts.register(user, password).await {
+            Ok(()) => info!("Set the password of {}", user),
+            Err(e) => warn!("Did not set the password of {}: {}", user, e),
+        }
+    }
+    Ok(())
+}
+
 /// this module contains the REST API functions used by the front-end
 mod api {
     use std::error::Error;
+    use std::future::Future;
+    use std::time::Instant;
     use std::{collections::HashMap, sync::Arc};
 
-    use actix_web::{get, http::header::ContentType, post, web, HttpResponse, Responder};
+    use actix_web::cookie::{time, Cookie, SameSite};
+    use actix_web::dev::{Service, ServiceRequest, ServiceResponse};
+    use actix_web::{
+        get, http::header::ContentType, post, web, HttpRequest, HttpResponse, Responder,
+    };
     use log::debug;
+    use tribbler::auth::{Accounts, SESSION_TTL};
+    use tribbler::err::{TribResult, TribblerError};
+    use tribbler::metrics;
 
     use crate::Srv;
 
+    /// the cookie which carries the session token of the logged in user
+    const SESSION_COOKIE: &str = "session";
+
     fn build_resp<T: Serialize>(d: &T) -> HttpResponse {
         HttpResponse::Ok()
             .content_type(ContentType::plaintext())
@@ -139,18 +204,81 @@ mod api {
     }
 
     fn err_response(err: Box<dyn Error>) -> HttpResponse {
-        HttpResponse::InternalServerError().body(err.to_string())
+        match err.downcast_ref::<TribblerError>() {
+            Some(TribblerError::Unauthenticated(_)) => {
+                HttpResponse::Unauthorized().body(err.to_string())
+            }
+            Some(TribblerError::RateLimited(_)) => {
+                HttpResponse::TooManyRequests().body(err.to_string())
+            }
+            _ => HttpResponse::InternalServerError().body(err.to_string()),
+        }
+    }
+
+    /// Calls `srv` with `req`, counting and timing the call by endpoint in
+    /// the front-end [metrics].
+    pub fn metered<S, B>(
+        req: ServiceRequest,
+        srv: &S,
+    ) -> impl Future<Output = Result<ServiceResponse<B>, actix_web::Error>>
+    where
+        S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error>,
+    {
+        // the route pattern, so that a path cannot make up new endpoints
+        let endpoint = req
+            .match_pattern()
+            .unwrap_or_else(|| "unmatched".to_string());
+        let started = Instant::now();
+        let answer = srv.call(req);
+        async move {
+            let resp = answer.await;
+            let status = match &resp {
+                Ok(r) => r.status().as_u16().to_string(),
+                Err(e) => e.as_response_error().status_code().as_u16().to_string(),
+            };
+            metrics::FRONT_SECONDS
+                .with_label_values(&[&endpoint])
+                .observe(started.elapsed().as_secs_f64());
+            metrics::FRONT_REQUESTS
+                .with_label_values(&[&endpoint, &status])
+                .inc();
+            resp
+        }
+    }
+
+    fn session(req: &HttpRequest) -> Option<String> {
+        req.cookie(SESSION_COOKIE).map(|c| c.value().to_string())
+    }
+
+    /// The user logged in by `req`, who must be `who`, the user the request
+    /// acts as.
+    async fn authorize(accounts: &Accounts, req: &HttpRequest, who: &str) -> TribResult<()> {
+        let user = accounts.authorize(session(req).as_deref()).await?;
+        if user != who {
+            return Err(Box::new(TribblerError::Unauthenticated(format!(
+                "logged in as {}, not {}",
+                user, who
+            ))));
+        }
+        Ok(())
     }
 
-    /// signs up a new user
+    /// signs up a new user with a password, or gives an existing user
+    /// without one a password
     #[post("/add-user")]
     pub async fn add_user(
         data: web::Data<Srv>,
+        accounts: web::Data<Accounts>,
         form: web::Form<HashMap<String, String>>,
     ) -> impl Responder {
         let s = form.0;
-        debug!("add-user: {:?}", &s);
-        match data.sign_up(s.keys().next().unwrap()).await {
+        let raw = s.keys().next().unwrap();
+        let l = match serde_json::from_str::<Login>(raw) {
+            Ok(l) => l,
+            Err(e) => return err_response(Box::new(e)),
+        };
+        debug!("add-user: {}", &l.user);
+        match accounts.sign_up(&***data, &l.user, &l.password).await {
             Ok(_) => build_resp(&UserList {
                 users: data.list_users().await.unwrap(),
                 err: "".to_string(),
@@ -159,6 +287,54 @@ mod api {
         }
     }
 
+    /// checks a user's password and starts a session for them
+    #[post("/login")]
+    pub async fn login(
+        accounts: web::Data<Accounts>,
+        form: web::Form<HashMap<String, String>>,
+    ) -> impl Responder {
+        let s = form.0;
+        let raw = s.keys().next().unwrap();
+        let l = match serde_json::from_str::<Login>(raw) {
+            Ok(l) => l,
+            Err(e) => return err_response(Box::new(e)),
+        };
+        match accounts.login(&l.user, &l.password).await {
+            Ok(token) => {
+                let cookie = Cookie::build(SESSION_COOKIE, token)
+                    .path("/")
+                    .http_only(true)
+                    .same_site(SameSite::Strict)
+                    .max_age(time::Duration::seconds(SESSION_TTL.as_secs() as i64))
+                    .finish();
+                let mut resp = build_resp(&Bool {
+                    v: true,
+                    err: "".to_string(),
+                });
+                resp.add_cookie(&cookie).unwrap();
+                resp
+            }
+            Err(e) => err_response(e),
+        }
+    }
+
+    /// ends the session of the logged in user
+    #[post("/logout")]
+    pub async fn logout(accounts: web::Data<Accounts>, req: HttpRequest) -> impl Responder {
+        if let Some(token) = session(&req) {
+            if let Err(e) = accounts.logout(&token).await {
+                return err_response(e);
+            }
+        }
+        let mut resp = build_resp(&Bool {
+            v: true,
+            err: "".to_string(),
+        });
+        resp.add_removal_cookie(&Cookie::build(SESSION_COOKIE, "").path("/").finish())
+            .unwrap();
+        resp
+    }
+
     /// lists all the users registered
     #[get("list-users")]
     pub async fn list_users(data: web::Data<Srv>) -> impl Responder {
@@ -233,15 +409,20 @@ mod api {
         }
     }
 
-    /// makes a user follow another user
+    /// makes the logged in user follow another user
     #[post("follow")]
     pub async fn follow(
         data: web::Data<Srv>,
+        accounts: web::Data<Accounts>,
+        req: HttpRequest,
         form: web::Form<HashMap<String, String>>,
     ) -> impl Responder {
         let s = form.0;
         let raw = s.keys().next().unwrap();
         let t = serde_json::from_str::<WhoWhom>(raw).unwrap();
+        if let Err(e) = authorize(&accounts, &req, &t.who).await {
+            return err_response(e);
+        }
         match data.follow(&t.who, &t.whom).await {
             Ok(_) => {
                 let ul = Bool {
@@ -254,15 +435,20 @@ mod api {
         }
     }
 
-    /// makes a user unfollow another user
+    /// makes the logged in user unfollow another user
     #[post("unfollow")]
     pub async fn unfollow(
         data: web::Data<Srv>,
+        accounts: web::Data<Accounts>,
+        req: HttpRequest,
         form: web::Form<HashMap<String, String>>,
     ) -> impl Responder {
         let s = form.0;
         let raw = s.keys().next().unwrap();
         let t = serde_json::from_str::<WhoWhom>(raw).unwrap();
+        if let Err(e) = authorize(&accounts, &req, &t.who).await {
+            return err_response(e);
+        }
         match data.unfollow(&t.who, &t.whom).await {
             Ok(_) => {
                 let ul = Bool {
@@ -294,24 +480,32 @@ mod api {
         }
     }
 
-    /// adds a post for a particular user
+    /// adds a post for the logged in user
     #[post("post")]
     pub async fn post(
         data: web::Data<Srv>,
+        accounts: web::Data<Accounts>,
+        req: HttpRequest,
         form: web::Form<HashMap<String, String>>,
     ) -> impl Responder {
         let s = form.0;
         let raw = s.keys().next().unwrap();
         match serde_json::from_str::<Post>(raw) {
             Ok(p) => {
+                if let Err(e) = authorize(&accounts, &req, &p.who).await {
+                    return err_response(e);
+                }
                 let x = match data.post(&p.who, &p.message, p.clock).await {
                     Ok(_) => Bool {
                         v: true,
                         err: "".to_string(),
                     },
-                    Err(e) => Bool {
-                        v: false,
-                        err: e.to_string(),
+                    Err(e) => match e.downcast_ref::<TribblerError>() {
+                        Some(TribblerError::RateLimited(_)) => return err_response(e),
+                        _ => Bool {
+                            v: false,
+                            err: e.to_string(),
+                        },
                     },
                 };
                 build_resp(&x)
@@ -359,4 +553,10 @@ mod api {
         message: String,
         clock: u64,
     }
+
+    #[derive(Deserialize)]
+    struct Login {
+        user: String,
+        password: String,
+    }
 }
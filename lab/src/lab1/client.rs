@@ -1,67 +1,150 @@
+use super::pool::ChannelPool;
 use async_trait::async_trait;
+use std::time::Duration;
+use tokio::sync::mpsc;
 use tribbler::err::TribResult;
-use tribbler::rpc::trib_storage_client::TribStorageClient;
-use tribbler::rpc::{Clock, Key, KeyValue as rpcKeyValue, Pattern as rpcPattern};
-use tribbler::storage::{KeyList, KeyString, KeyValue, List, Pattern, Storage};
+use tribbler::err::TribblerError;
+use tribbler::hlc::Hlc;
+use tribbler::rpc::precondition::Check;
+use tribbler::rpc::txn_op::Op;
+use tribbler::rpc::{
+    BytesKey, BytesKeyValue, CasRequest, Change as rpcChange, ChangeKind as rpcChangeKind, Clock,
+    ExpiringKeyValue, Key, KeyValue as rpcKeyValue, KeyValues, Keys, KeysPage, KeysPageRequest,
+    ListLenCheck, ListRangeRequest, ListTrimRequest, MultiListRangeRequest, Pattern as rpcPattern,
+    Precondition as rpcPrecondition, StatsRequest, TxnOp as rpcTxnOp, TxnRequest, WatchRequest,
+};
+use tribbler::storage::{
+    ByteStorage, Change, ChangeKind, ChangeStream, KeyBytes, KeyList, KeyPage, KeyString, KeyValue,
+    List, ListBytes, Pattern, Precondition, Storage, StorageStats, Txn, TxnOp, TxnResult, Watch,
+    WATCH_HISTORY,
+};
+
+/// `ttl` in whole milliseconds, rounded up, so that a short but positive
+/// TTL does not turn into the 0 a backend refuses.
+pub(crate) fn ttl_millis(ttl: Duration) -> u64 {
+    let ms = ttl.as_nanos().div_ceil(1_000_000);
+    ms.clamp(1, u64::MAX as u128) as u64
+}
 
 pub struct StorageClient {
     pub addr: String,
+    pub pool: ChannelPool,
     // pub clock: RwLock<u64>,
 }
 
+impl StorageClient {
+    /// creates a client for the backend at `addr` which shares its
+    /// connections with every other user of `pool`
+    pub fn new(addr: &str, pool: ChannelPool) -> StorageClient {
+        StorageClient {
+            addr: addr.to_string(),
+            pool,
+        }
+    }
+}
+
 #[async_trait]
 impl KeyString for StorageClient {
     async fn get(&self, key: &str) -> TribResult<Option<String>> {
-        let mut client = TribStorageClient::connect(self.addr.clone()).await?;
+        let mut client = self.pool.client(&self.addr).await?;
         let r = client
             .get(Key {
                 key: key.to_string(),
             })
-            .await;
-        match r {
-            Ok(value) => Ok(Some(value.into_inner().value)),
-            Err(e) => Ok(None),
-        }
+            .await
+            .map_err(|e| self.pool.check(&self.addr, e))?;
+        Ok(r.into_inner().value)
     }
 
     async fn set(&self, kv: &KeyValue) -> TribResult<bool> {
-        let mut client = TribStorageClient::connect(self.addr.clone()).await?;
+        let mut client = self.pool.client(&self.addr).await?;
         let r = client
             .set(rpcKeyValue {
                 key: kv.key.to_string(),
                 value: kv.value.to_string(),
             })
-            .await?;
+            .await
+            .map_err(|e| self.pool.check(&self.addr, e))?;
 
         match r.into_inner().value {
             value => Ok(value),
         }
     }
 
+    async fn set_with_ttl(&self, kv: &KeyValue, ttl: Duration) -> TribResult<bool> {
+        let mut client = self.pool.client(&self.addr).await?;
+        let r = client
+            .set_with_ttl(ExpiringKeyValue {
+                key: kv.key.to_string(),
+                value: kv.value.to_string(),
+                ttl_ms: ttl_millis(ttl),
+            })
+            .await
+            .map_err(|e| self.pool.check(&self.addr, e))?;
+        Ok(r.into_inner().value)
+    }
+
+    async fn cas(&self, key: &str, expected: Option<String>, new: &str) -> TribResult<bool> {
+        let mut client = self.pool.client(&self.addr).await?;
+        let r = client
+            .cas(CasRequest {
+                key: key.to_string(),
+                expected,
+                value: new.to_string(),
+            })
+            .await
+            .map_err(|e| self.pool.check(&self.addr, e))?;
+
+        Ok(r.into_inner().value)
+    }
+
     async fn keys(&self, p: &Pattern) -> TribResult<List> {
-        let mut client = TribStorageClient::connect(self.addr.clone()).await?;
+        let mut client = self.pool.client(&self.addr).await?;
         let r = client
             .keys(rpcPattern {
                 prefix: p.prefix.to_string(),
                 suffix: p.suffix.to_string(),
             })
-            .await?;
+            .await
+            .map_err(|e| self.pool.check(&self.addr, e))?;
 
         match r.into_inner().list {
             value => Ok(List(value)),
         }
     }
+
+    async fn keys_page(&self, p: &Pattern, start_after: &str, limit: usize) -> TribResult<KeyPage> {
+        let mut client = self.pool.client(&self.addr).await?;
+        let r = client
+            .keys_page(page_request(p, start_after, limit))
+            .await
+            .map_err(|e| self.pool.check(&self.addr, e))?;
+        Ok(key_page(r.into_inner()))
+    }
+
+    async fn multi_get(&self, keys: &[String]) -> TribResult<Vec<Option<String>>> {
+        let mut client = self.pool.client(&self.addr).await?;
+        let r = client
+            .multi_get(Keys {
+                keys: keys.to_vec(),
+            })
+            .await
+            .map_err(|e| self.pool.check(&self.addr, e))?;
+
+        Ok(r.into_inner().values.into_iter().map(|v| v.value).collect())
+    }
 }
 
 #[async_trait]
 impl KeyList for StorageClient {
     async fn list_get(&self, key: &str) -> TribResult<List> {
-        let mut client = TribStorageClient::connect(self.addr.clone()).await?;
+        let mut client = self.pool.client(&self.addr).await?;
         let r = client
             .list_get(Key {
                 key: key.to_string(),
             })
-            .await?;
+            .await
+            .map_err(|e| self.pool.check(&self.addr, e))?;
 
         match r.into_inner().list {
             value => Ok(List(value)),
@@ -69,60 +152,407 @@ impl KeyList for StorageClient {
     }
 
     async fn list_append(&self, kv: &KeyValue) -> TribResult<bool> {
-        let mut client = TribStorageClient::connect(self.addr.clone()).await?;
+        let mut client = self.pool.client(&self.addr).await?;
         let r = client
             .list_append(rpcKeyValue {
                 key: kv.key.to_string(),
                 value: kv.value.to_string(),
             })
-            .await?;
+            .await
+            .map_err(|e| self.pool.check(&self.addr, e))?;
 
         match r.into_inner().value {
             value => Ok(value),
         }
     }
 
+    async fn list_append_with_ttl(&self, kv: &KeyValue, ttl: Duration) -> TribResult<bool> {
+        let mut client = self.pool.client(&self.addr).await?;
+        let r = client
+            .list_append_with_ttl(ExpiringKeyValue {
+                key: kv.key.to_string(),
+                value: kv.value.to_string(),
+                ttl_ms: ttl_millis(ttl),
+            })
+            .await
+            .map_err(|e| self.pool.check(&self.addr, e))?;
+        Ok(r.into_inner().value)
+    }
+
     async fn list_remove(&self, kv: &KeyValue) -> TribResult<u32> {
-        let mut client = TribStorageClient::connect(self.addr.clone()).await?;
+        let mut client = self.pool.client(&self.addr).await?;
         let r = client
             .list_remove(rpcKeyValue {
                 key: kv.key.to_string(),
                 value: kv.value.to_string(),
             })
-            .await?;
+            .await
+            .map_err(|e| self.pool.check(&self.addr, e))?;
 
         match r.into_inner().removed {
             value => Ok(value),
         }
     }
 
+    async fn list_trim(&self, key: &str, keep_last: u64) -> TribResult<u32> {
+        let mut client = self.pool.client(&self.addr).await?;
+        let r = client
+            .list_trim(ListTrimRequest {
+                key: key.to_string(),
+                keep_last,
+            })
+            .await
+            .map_err(|e| self.pool.check(&self.addr, e))?;
+        Ok(r.into_inner().removed)
+    }
+
+    async fn list_len(&self, key: &str) -> TribResult<u64> {
+        let mut client = self.pool.client(&self.addr).await?;
+        let r = client
+            .list_len(Key {
+                key: key.to_string(),
+            })
+            .await
+            .map_err(|e| self.pool.check(&self.addr, e))?;
+        Ok(r.into_inner().len)
+    }
+
+    async fn list_range(&self, key: &str, start: i64, end: i64) -> TribResult<List> {
+        let mut client = self.pool.client(&self.addr).await?;
+        let r = client
+            .list_range(ListRangeRequest {
+                key: key.to_string(),
+                start,
+                end,
+            })
+            .await
+            .map_err(|e| self.pool.check(&self.addr, e))?;
+        Ok(List(r.into_inner().list))
+    }
+
+    async fn multi_list_range(
+        &self,
+        keys: &[String],
+        start: i64,
+        end: i64,
+    ) -> TribResult<Vec<List>> {
+        let mut client = self.pool.client(&self.addr).await?;
+        let r = client
+            .multi_list_range(MultiListRangeRequest {
+                keys: keys.to_vec(),
+                start,
+                end,
+            })
+            .await
+            .map_err(|e| self.pool.check(&self.addr, e))?;
+
+        Ok(r.into_inner()
+            .lists
+            .into_iter()
+            .map(|l| List(l.list))
+            .collect())
+    }
+
     async fn list_keys(&self, p: &Pattern) -> TribResult<List> {
-        let mut client = TribStorageClient::connect(self.addr.clone()).await?;
+        let mut client = self.pool.client(&self.addr).await?;
         let r = client
             .list_keys(rpcPattern {
                 prefix: p.prefix.to_string(),
                 suffix: p.suffix.to_string(),
             })
-            .await?;
+            .await
+            .map_err(|e| self.pool.check(&self.addr, e))?;
 
         match r.into_inner().list {
             value => Ok(List(value)),
         }
     }
+
+    async fn list_keys_page(
+        &self,
+        p: &Pattern,
+        start_after: &str,
+        limit: usize,
+    ) -> TribResult<KeyPage> {
+        let mut client = self.pool.client(&self.addr).await?;
+        let r = client
+            .list_keys_page(page_request(p, start_after, limit))
+            .await
+            .map_err(|e| self.pool.check(&self.addr, e))?;
+        Ok(key_page(r.into_inner()))
+    }
+
+    async fn multi_list_get(&self, keys: &[String]) -> TribResult<Vec<List>> {
+        let mut client = self.pool.client(&self.addr).await?;
+        let r = client
+            .multi_list_get(Keys {
+                keys: keys.to_vec(),
+            })
+            .await
+            .map_err(|e| self.pool.check(&self.addr, e))?;
+
+        Ok(r.into_inner()
+            .lists
+            .into_iter()
+            .map(|l| List(l.list))
+            .collect())
+    }
+
+    async fn list_append_many(&self, key: &str, values: &[String]) -> TribResult<bool> {
+        let mut client = self.pool.client(&self.addr).await?;
+        let r = client
+            .list_append_many(KeyValues {
+                key: key.to_string(),
+                values: values.to_vec(),
+            })
+            .await
+            .map_err(|e| self.pool.check(&self.addr, e))?;
+
+        Ok(r.into_inner().value)
+    }
 }
 
 #[async_trait]
 impl Storage for StorageClient {
     async fn clock(&self, at_least: u64) -> TribResult<u64> {
-        let mut client = TribStorageClient::connect(self.addr.clone()).await?;
+        let mut client = self.pool.client(&self.addr).await?;
+        let r = client
+            .clock(Clock::from(Hlc::from_u64(at_least)))
+            .await
+            .map_err(|e| self.pool.check(&self.addr, e))?;
+
+        Ok(Hlc::from(r.into_inner()).to_u64())
+    }
+
+    async fn transaction(&self, txn: &Txn) -> TribResult<TxnResult> {
+        let mut client = self.pool.client(&self.addr).await?;
         let r = client
-            .clock(Clock {
-                timestamp: at_least,
+            .txn(txn_request(txn))
+            .await
+            .map_err(|e| self.pool.check(&self.addr, e))?
+            .into_inner();
+        Ok(TxnResult {
+            committed: r.committed,
+            values: r.values.into_iter().map(|v| v.value).collect(),
+        })
+    }
+
+    fn watcher(&self) -> Option<&dyn Watch> {
+        Some(self)
+    }
+
+    /// The backend answers the bytes calls with an unimplemented status when
+    /// its storage cannot hold bytes.
+    fn bytes(&self) -> Option<&dyn ByteStorage> {
+        Some(self)
+    }
+
+    async fn stats(&self) -> TribResult<StorageStats> {
+        let mut client = self.pool.client(&self.addr).await?;
+        let r = client
+            .stats(StatsRequest {})
+            .await
+            .map_err(|e| self.pool.check(&self.addr, e))?
+            .into_inner();
+        Ok(StorageStats {
+            used_bytes: r.used_bytes,
+            limit_bytes: Some(r.limit_bytes).filter(|b| *b > 0),
+            keys: r.keys,
+            lists: r.lists,
+            evicted: r.evicted,
+            rejected: r.rejected,
+        })
+    }
+}
+
+#[async_trait]
+impl KeyBytes for StorageClient {
+    async fn get_bytes(&self, key: &[u8]) -> TribResult<Option<Vec<u8>>> {
+        let mut client = self.pool.client(&self.addr).await?;
+        let r = client
+            .get_bytes(BytesKey { key: key.to_vec() })
+            .await
+            .map_err(|e| self.pool.check(&self.addr, e))?;
+        Ok(r.into_inner().value)
+    }
+
+    async fn set_bytes(&self, key: &[u8], value: &[u8]) -> TribResult<bool> {
+        let mut client = self.pool.client(&self.addr).await?;
+        let r = client
+            .set_bytes(BytesKeyValue {
+                key: key.to_vec(),
+                value: value.to_vec(),
             })
-            .await?;
+            .await
+            .map_err(|e| self.pool.check(&self.addr, e))?;
+        Ok(r.into_inner().value)
+    }
 
-        match r.into_inner().timestamp {
-            value => Ok(value),
-        }
+    async fn keys_bytes(&self, prefix: &[u8]) -> TribResult<Vec<Vec<u8>>> {
+        let mut client = self.pool.client(&self.addr).await?;
+        let r = client
+            .keys_bytes(BytesKey {
+                key: prefix.to_vec(),
+            })
+            .await
+            .map_err(|e| self.pool.check(&self.addr, e))?;
+        Ok(r.into_inner().list)
+    }
+}
+
+#[async_trait]
+impl ListBytes for StorageClient {
+    async fn list_get_bytes(&self, key: &[u8]) -> TribResult<Vec<Vec<u8>>> {
+        let mut client = self.pool.client(&self.addr).await?;
+        let r = client
+            .list_get_bytes(BytesKey { key: key.to_vec() })
+            .await
+            .map_err(|e| self.pool.check(&self.addr, e))?;
+        Ok(r.into_inner().list)
+    }
+
+    async fn list_append_bytes(&self, key: &[u8], value: &[u8]) -> TribResult<bool> {
+        let mut client = self.pool.client(&self.addr).await?;
+        let r = client
+            .list_append_bytes(BytesKeyValue {
+                key: key.to_vec(),
+                value: value.to_vec(),
+            })
+            .await
+            .map_err(|e| self.pool.check(&self.addr, e))?;
+        Ok(r.into_inner().value)
+    }
+
+    async fn list_remove_bytes(&self, key: &[u8], value: &[u8]) -> TribResult<u32> {
+        let mut client = self.pool.client(&self.addr).await?;
+        let r = client
+            .list_remove_bytes(BytesKeyValue {
+                key: key.to_vec(),
+                value: value.to_vec(),
+            })
+            .await
+            .map_err(|e| self.pool.check(&self.addr, e))?;
+        Ok(r.into_inner().removed)
+    }
+
+    async fn list_keys_bytes(&self, prefix: &[u8]) -> TribResult<Vec<Vec<u8>>> {
+        let mut client = self.pool.client(&self.addr).await?;
+        let r = client
+            .list_keys_bytes(BytesKey {
+                key: prefix.to_vec(),
+            })
+            .await
+            .map_err(|e| self.pool.check(&self.addr, e))?;
+        Ok(r.into_inner().list)
     }
 }
+
+#[async_trait]
+impl Watch for StorageClient {
+    async fn watch(&self, p: &Pattern, since: u64) -> TribResult<ChangeStream> {
+        let mut client = self.pool.client(&self.addr).await?;
+        let mut stream = client
+            .watch(WatchRequest {
+                pattern: Some(rpcPattern {
+                    prefix: p.prefix.to_string(),
+                    suffix: p.suffix.to_string(),
+                }),
+                since,
+            })
+            .await
+            .map_err(|e| self.pool.check(&self.addr, e))?
+            .into_inner();
+
+        let (tx, rx) = mpsc::channel(WATCH_HISTORY);
+        tokio::spawn(async move {
+            loop {
+                let change = tokio::select! {
+                    _ = tx.closed() => return,
+                    change = stream.message() => change,
+                };
+                let item = match change {
+                    Ok(Some(change)) => Ok(change_from(change)),
+                    Ok(None) => return,
+                    Err(e) => Err(TribblerError::from(e).into()),
+                };
+                let last = item.is_err();
+                if tx.send(item).await.is_err() || last {
+                    return;
+                }
+            }
+        });
+        Ok(rx)
+    }
+}
+
+fn page_request(p: &Pattern, start_after: &str, limit: usize) -> KeysPageRequest {
+    KeysPageRequest {
+        pattern: Some(rpcPattern {
+            prefix: p.prefix.to_string(),
+            suffix: p.suffix.to_string(),
+        }),
+        start_after: start_after.to_string(),
+        limit: limit.min(u32::MAX as usize) as u32,
+    }
+}
+
+fn key_page(page: KeysPage) -> KeyPage {
+    KeyPage {
+        keys: List(page.keys),
+        next: page.next,
+    }
+}
+
+fn change_from(change: rpcChange) -> Change {
+    let kind = match rpcChangeKind::from_i32(change.kind) {
+        Some(rpcChangeKind::ListAppend) => ChangeKind::ListAppend,
+        Some(rpcChangeKind::ListRemove) => ChangeKind::ListRemove,
+        Some(rpcChangeKind::ListTrim) => ChangeKind::ListTrim,
+        Some(rpcChangeKind::Expire) => ChangeKind::Expire,
+        Some(rpcChangeKind::ListExpire) => ChangeKind::ListExpire,
+        Some(rpcChangeKind::Evict) => ChangeKind::Evict,
+        Some(rpcChangeKind::Set) | None => ChangeKind::Set,
+    };
+    Change {
+        seq: change.seq,
+        kind,
+        key: change.key,
+        value: change.value,
+    }
+}
+
+fn txn_request(txn: &Txn) -> TxnRequest {
+    let kv = |kv: &KeyValue| rpcKeyValue {
+        key: kv.key.to_string(),
+        value: kv.value.to_string(),
+    };
+    let checks = txn
+        .checks
+        .iter()
+        .map(|c| rpcPrecondition {
+            check: Some(match c {
+                Precondition::Value { key, value } => Check::Value(rpcKeyValue {
+                    key: key.to_string(),
+                    value: value.to_string(),
+                }),
+                Precondition::ListLen { key, len } => Check::ListLen(ListLenCheck {
+                    key: key.to_string(),
+                    len: *len,
+                }),
+            }),
+        })
+        .collect();
+    let ops = txn
+        .ops
+        .iter()
+        .map(|op| rpcTxnOp {
+            op: Some(match op {
+                TxnOp::Get(key) => Op::Get(key.to_string()),
+                TxnOp::Set(v) => Op::Set(kv(v)),
+                TxnOp::ListAppend(v) => Op::ListAppend(kv(v)),
+                TxnOp::ListRemove(v) => Op::ListRemove(kv(v)),
+            }),
+        })
+        .collect();
+    TxnRequest { checks, ops }
+}
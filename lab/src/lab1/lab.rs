@@ -1,9 +1,12 @@
 use crate::lab1::client::StorageClient;
+use crate::lab1::pool::ChannelPool;
 use crate::lab1::server::StorageServer;
 use std::net::ToSocketAddrs;
+use std::sync::Arc;
 use tokio::sync::mpsc::Receiver;
 use tonic::transport::Server;
 use tribbler::err::TribblerError;
+use tribbler::metrics::RpcMetrics;
 use tribbler::rpc::trib_storage_server::TribStorageServer;
 use tribbler::{config::BackConfig, err::TribResult, storage::Storage};
 
@@ -26,6 +29,27 @@ pub async fn serve_back(config: BackConfig) -> TribResult<()> {
         },
     }
 
+    // the limit has to be in force before the first request comes in
+    if let Some(limit) = config.memory_limit {
+        if let Err(e) = config.storage.set_memory_limit(limit) {
+            if let Some(channel) = config.ready {
+                let _ = channel.send(false);
+            }
+            return Err(e);
+        }
+    }
+
+    // a broken certificate fails the start, rather than every connection
+    let mut builder = match server_builder(&config) {
+        Ok(builder) => builder,
+        Err(e) => {
+            if let Some(channel) = config.ready {
+                let _ = channel.send(false);
+            }
+            return Err(e);
+        }
+    };
+
     // ready is a channel for notifying the other parts in the program that the server is ready to accept RPC calls from the network (indicated by the server sending the value true) or if the setup failed (indicated by sending false).
     // ready might be None, which means the caller does not care about when the server is ready.
     match config.ready {
@@ -38,9 +62,9 @@ pub async fn serve_back(config: BackConfig) -> TribResult<()> {
 
     let next_addr = config.addr.to_socket_addrs().unwrap().next().unwrap();
     let trib_storage_server = TribStorageServer::new(StorageServer {
-        storage: config.storage,
+        storage: Arc::from(config.storage),
     });
-    let storage_server = Server::builder().add_service(trib_storage_server);
+    let storage_server = builder.add_service(trib_storage_server);
 
     // shutdown is another type of channel for receiving a shutdown notification.
     // when a message is received on this channel, the server should shut down.
@@ -63,11 +87,19 @@ pub async fn serve_back(config: BackConfig) -> TribResult<()> {
     // should block indefinitely unless there is errors or the server is sent a shutdown signal. It is async, you should be able to call .await on futures within it.
 }
 
+/// A server builder recording the [RpcMetrics] of every call, and speaking
+/// TLS when `config` asks for it.
+fn server_builder(config: &BackConfig) -> TribResult<Server<RpcMetrics>> {
+    let mut builder = Server::builder().layer(RpcMetrics::new(&config.addr));
+    if let Some(tls) = &config.tls {
+        builder = builder.tls_config(tls.server(&config.addr)?)?;
+    }
+    Ok(builder)
+}
+
 /// This function should create a new client which implements the [Storage]
 /// trait. It should communicate with the backend that is started in the
 /// [serve_back] function.
 pub async fn new_client(addr: &str) -> TribResult<Box<dyn Storage>> {
-    Ok(Box::new(StorageClient {
-        addr: addr.to_string(),
-    }))
+    Ok(Box::new(StorageClient::new(addr, ChannelPool::new())))
 }
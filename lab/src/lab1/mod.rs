@@ -232,6 +232,9 @@
 //! notification. when a message is received on this channel, the server should
 //! shut down. **Hint**: take a look at
 //! [serve_with_shutdown](tonic::transport::server::Router)
+//! - `memory_limit`, when set, is put on the storage with
+//!   [set_memory_limit](tribbler::storage::Storage::set_memory_limit) before
+//!   the server reports ready; a storage refusing it fails the setup.
 //!
 //! This function should block indefinitely unless there is errors or the server
 //! is sent a shutdown signal. It is `async`, you should be able to call
@@ -409,9 +412,8 @@
 //!         let r = client.get(Key {
 //!             key: key.to_string(),
 //!         }).await?;
-//!         match r.into_inner().value {
-//!             value => Ok(Some(value)),
-//!         }
+//!         // the value is unset when the key does not exist
+//!         Ok(r.into_inner().value)
 //!      }
 //!   ...
 //! }
@@ -565,6 +567,7 @@
 //!
 pub mod client;
 mod lab;
+pub mod pool;
 mod server;
 pub use crate::lab1::lab::new_client;
 pub use crate::lab1::lab::serve_back;
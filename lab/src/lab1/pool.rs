@@ -0,0 +1,81 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tonic::transport::{Channel, ClientTlsConfig, Endpoint};
+use tonic::{Code, Status};
+use tribbler::config::TlsConfig;
+use tribbler::err::{TribResult, TribblerError};
+use tribbler::rpc::trib_storage_client::TribStorageClient;
+
+/// How long opening a connection to a backend may take before giving up.
+pub const CONNECT_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// A shared set of gRPC channels keyed by backend address (`http://<host>:<port>`).
+///
+/// A channel is only opened the first time an address is asked for and is then
+/// reused by every clone of the pool. When an RPC on a channel fails at the
+/// transport level the caller should [invalidate](ChannelPool::invalidate) the
+/// address so that the next call reconnects.
+#[derive(Clone, Default)]
+pub struct ChannelPool {
+    channels: Arc<Mutex<HashMap<String, Channel>>>,
+    tls: Option<ClientTlsConfig>,
+}
+
+impl ChannelPool {
+    pub fn new() -> ChannelPool {
+        ChannelPool::default()
+    }
+
+    /// A pool whose channels all go over TLS set up by `tls`, when it is
+    /// not [None].
+    pub fn with_tls(tls: Option<&TlsConfig>) -> TribResult<ChannelPool> {
+        Ok(ChannelPool {
+            tls: tls.map(TlsConfig::client).transpose()?,
+            ..ChannelPool::default()
+        })
+    }
+
+    /// Returns the channel for `addr`, connecting to it if there is none yet.
+    pub async fn channel(&self, addr: &str) -> TribResult<Channel> {
+        if let Some(c) = self.channels.lock().map_err(|e| e.to_string())?.get(addr) {
+            return Ok(c.clone());
+        }
+        // the lock is not held while connecting, so two callers may race to
+        // connect the same address; the first one to finish wins
+        let mut endpoint = Endpoint::from_shared(addr.to_string())?;
+        if let Some(tls) = &self.tls {
+            endpoint = endpoint.tls_config(tls.clone())?;
+        }
+        let c = endpoint.connect_timeout(CONNECT_TIMEOUT).connect().await?;
+        Ok(self
+            .channels
+            .lock()
+            .map_err(|e| e.to_string())?
+            .entry(addr.to_string())
+            .or_insert(c)
+            .clone())
+    }
+
+    /// Returns a storage client that talks over the pooled channel for `addr`.
+    pub async fn client(&self, addr: &str) -> TribResult<TribStorageClient<Channel>> {
+        Ok(TribStorageClient::new(self.channel(addr).await?))
+    }
+
+    /// Drops the channel for `addr`, so the next call opens a fresh one.
+    pub fn invalidate(&self, addr: &str) {
+        if let Ok(mut channels) = self.channels.lock() {
+            channels.remove(addr);
+        }
+    }
+
+    /// Invalidates `addr` if `status` says the connection itself failed, then
+    /// hands the status back as a [TribblerError] so it can be propagated
+    /// with `?`.
+    pub fn check(&self, addr: &str, status: Status) -> TribblerError {
+        if matches!(status.code(), Code::Unavailable | Code::Unknown) {
+            self.invalidate(addr);
+        }
+        status.into()
+    }
+}
@@ -1,14 +1,40 @@
+use super::client::ttl_millis;
 use async_trait::async_trait;
-use tonic::{Request, Response, Status};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc::{self, Sender};
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{Request, Response, Status, Streaming};
+use tribbler::err::{to_status, TribResult};
+use tribbler::hlc::Hlc;
+use tribbler::rpc::precondition::Check;
+use tribbler::rpc::record::Entry;
 use tribbler::rpc::trib_storage_server::TribStorage;
+use tribbler::rpc::txn_op::Op;
 use tribbler::rpc::{
-    Bool, Clock, Key, KeyValue as rpcKeyValue, ListRemoveResponse, Pattern as rpcPattern,
-    StringList, Value,
+    Bool, BytesKey, BytesKeyValue, BytesList, BytesValue, CasRequest, Change as rpcChange,
+    ChangeKind as rpcChangeKind, Clock, ExpiringKeyValue, HealthCheck, HealthStatus,
+    IngestResponse, Key, KeyValue as rpcKeyValue, KeyValues, Keys, KeysPage, KeysPageRequest,
+    ListLength, ListRangeRequest, ListRemoveResponse, ListTrimRequest, MaybeValue,
+    MultiListRangeRequest, Pattern as rpcPattern, Record, Stats, StatsRequest, StringList,
+    StringLists, TxnRequest, TxnResponse, Value, Values, WatchRequest,
 };
-use tribbler::storage::{KeyValue, Pattern, Storage};
+use tribbler::storage::{
+    Change, ChangeKind, KeyPage, KeyValue, Pattern, Precondition, Storage, Txn, TxnOp,
+    WATCH_HISTORY,
+};
+
+/// How many records [StorageServer::scan] reads ahead of a slow receiver,
+/// and how many keys it reads from the storage at a time.
+const SCAN_PAGE: usize = 256;
+
+/// Why a TTL write with `ttl_ms == 0` is refused: a [Record] reads 0 as
+/// "never expires", so an expiring write has to say how long it lives.
+const NO_TTL: &str = "ttl_ms must be at least 1";
 
 pub struct StorageServer {
-    pub storage: Box<dyn Storage>,
+    pub storage: Arc<dyn Storage>,
 }
 
 #[async_trait]
@@ -16,11 +42,8 @@ impl TribStorage for StorageServer {
     async fn get(&self, request: Request<Key>) -> Result<Response<Value>, Status> {
         let result = self.storage.get(&request.into_inner().key).await;
         match result {
-            Ok(value) => match value {
-                Some(v) => Ok(Response::new(Value { value: v })),
-                None => Err(Status::invalid_argument("No key provided")),
-            },
-            Err(e) => Err(Status::invalid_argument("Server get() failed")),
+            Ok(value) => Ok(Response::new(Value { value })),
+            Err(e) => Err(to_status(&*e)),
         }
     }
 
@@ -35,7 +58,47 @@ impl TribStorage for StorageServer {
             .await;
         match result {
             Ok(value) => Ok(Response::new(Bool { value: value })),
-            Err(e) => Err(Status::invalid_argument("Server set() failed")),
+            Err(e) => Err(to_status(&*e)),
+        }
+    }
+
+    async fn set_with_ttl(
+        &self,
+        request: Request<ExpiringKeyValue>,
+    ) -> Result<Response<Bool>, Status> {
+        let request_inner = request.into_inner();
+        if request_inner.ttl_ms == 0 {
+            return Err(Status::invalid_argument(NO_TTL));
+        }
+        let result = self
+            .storage
+            .set_with_ttl(
+                &KeyValue {
+                    key: request_inner.key,
+                    value: request_inner.value,
+                },
+                Duration::from_millis(request_inner.ttl_ms),
+            )
+            .await;
+        match result {
+            Ok(value) => Ok(Response::new(Bool { value })),
+            Err(e) => Err(to_status(&*e)),
+        }
+    }
+
+    async fn cas(&self, request: Request<CasRequest>) -> Result<Response<Bool>, Status> {
+        let request_inner = request.into_inner();
+        let result = self
+            .storage
+            .cas(
+                &request_inner.key,
+                request_inner.expected,
+                &request_inner.value,
+            )
+            .await;
+        match result {
+            Ok(value) => Ok(Response::new(Bool { value })),
+            Err(e) => Err(to_status(&*e)),
         }
     }
 
@@ -50,7 +113,31 @@ impl TribStorage for StorageServer {
             .await;
         match result {
             Ok(value) => Ok(Response::new(StringList { list: value.0 })),
-            Err(e) => Err(Status::invalid_argument("Server keys() failed")),
+            Err(e) => Err(to_status(&*e)),
+        }
+    }
+
+    async fn keys_page(
+        &self,
+        request: Request<KeysPageRequest>,
+    ) -> Result<Response<KeysPage>, Status> {
+        let (p, start_after, limit) = page_request(request.into_inner());
+        match self.storage.keys_page(&p, &start_after, limit).await {
+            Ok(page) => Ok(Response::new(page_response(page))),
+            Err(e) => Err(to_status(&*e)),
+        }
+    }
+
+    async fn multi_get(&self, request: Request<Keys>) -> Result<Response<Values>, Status> {
+        let result = self.storage.multi_get(&request.into_inner().keys).await;
+        match result {
+            Ok(values) => Ok(Response::new(Values {
+                values: values
+                    .into_iter()
+                    .map(|value| MaybeValue { value })
+                    .collect(),
+            })),
+            Err(e) => Err(to_status(&*e)),
         }
     }
 
@@ -58,7 +145,7 @@ impl TribStorage for StorageServer {
         let result = self.storage.list_get(&request.into_inner().key).await;
         match result {
             Ok(value) => Ok(Response::new(StringList { list: value.0 })),
-            Err(e) => Err(Status::invalid_argument("Server list_get() failed")),
+            Err(e) => Err(to_status(&*e)),
         }
     }
 
@@ -73,7 +160,31 @@ impl TribStorage for StorageServer {
             .await;
         match result {
             Ok(value) => Ok(Response::new(Bool { value: value })),
-            Err(e) => Err(Status::invalid_argument("Server list_append() failed")),
+            Err(e) => Err(to_status(&*e)),
+        }
+    }
+
+    async fn list_append_with_ttl(
+        &self,
+        request: Request<ExpiringKeyValue>,
+    ) -> Result<Response<Bool>, Status> {
+        let request_inner = request.into_inner();
+        if request_inner.ttl_ms == 0 {
+            return Err(Status::invalid_argument(NO_TTL));
+        }
+        let result = self
+            .storage
+            .list_append_with_ttl(
+                &KeyValue {
+                    key: request_inner.key,
+                    value: request_inner.value,
+                },
+                Duration::from_millis(request_inner.ttl_ms),
+            )
+            .await;
+        match result {
+            Ok(value) => Ok(Response::new(Bool { value })),
+            Err(e) => Err(to_status(&*e)),
         }
     }
 
@@ -91,7 +202,64 @@ impl TribStorage for StorageServer {
             .await;
         match result {
             Ok(value) => Ok(Response::new(ListRemoveResponse { removed: value })),
-            Err(e) => Err(Status::invalid_argument("Server list_remove() failed")),
+            Err(e) => Err(to_status(&*e)),
+        }
+    }
+
+    async fn list_trim(
+        &self,
+        request: Request<ListTrimRequest>,
+    ) -> Result<Response<ListRemoveResponse>, Status> {
+        let request_inner = request.into_inner();
+        let result = self
+            .storage
+            .list_trim(&request_inner.key, request_inner.keep_last)
+            .await;
+        match result {
+            Ok(removed) => Ok(Response::new(ListRemoveResponse { removed })),
+            Err(e) => Err(to_status(&*e)),
+        }
+    }
+
+    async fn list_len(&self, request: Request<Key>) -> Result<Response<ListLength>, Status> {
+        match self.storage.list_len(&request.into_inner().key).await {
+            Ok(len) => Ok(Response::new(ListLength { len })),
+            Err(e) => Err(to_status(&*e)),
+        }
+    }
+
+    async fn list_range(
+        &self,
+        request: Request<ListRangeRequest>,
+    ) -> Result<Response<StringList>, Status> {
+        let request_inner = request.into_inner();
+        let result = self
+            .storage
+            .list_range(&request_inner.key, request_inner.start, request_inner.end)
+            .await;
+        match result {
+            Ok(value) => Ok(Response::new(StringList { list: value.0 })),
+            Err(e) => Err(to_status(&*e)),
+        }
+    }
+
+    async fn multi_list_range(
+        &self,
+        request: Request<MultiListRangeRequest>,
+    ) -> Result<Response<StringLists>, Status> {
+        let request_inner = request.into_inner();
+        let result = self
+            .storage
+            .multi_list_range(&request_inner.keys, request_inner.start, request_inner.end)
+            .await;
+        match result {
+            Ok(lists) => Ok(Response::new(StringLists {
+                lists: lists
+                    .into_iter()
+                    .map(|l| StringList { list: l.0 })
+                    .collect(),
+            })),
+            Err(e) => Err(to_status(&*e)),
         }
     }
 
@@ -109,16 +277,478 @@ impl TribStorage for StorageServer {
             .await;
         match result {
             Ok(value) => Ok(Response::new(StringList { list: value.0 })),
-            Err(e) => Err(Status::invalid_argument("server list_keys() failed")),
+            Err(e) => Err(to_status(&*e)),
+        }
+    }
+
+    async fn list_keys_page(
+        &self,
+        request: Request<KeysPageRequest>,
+    ) -> Result<Response<KeysPage>, Status> {
+        let (p, start_after, limit) = page_request(request.into_inner());
+        match self.storage.list_keys_page(&p, &start_after, limit).await {
+            Ok(page) => Ok(Response::new(page_response(page))),
+            Err(e) => Err(to_status(&*e)),
+        }
+    }
+
+    async fn multi_list_get(
+        &self,
+        request: Request<Keys>,
+    ) -> Result<Response<StringLists>, Status> {
+        let result = self
+            .storage
+            .multi_list_get(&request.into_inner().keys)
+            .await;
+        match result {
+            Ok(lists) => Ok(Response::new(StringLists {
+                lists: lists
+                    .into_iter()
+                    .map(|list| StringList { list: list.0 })
+                    .collect(),
+            })),
+            Err(e) => Err(to_status(&*e)),
+        }
+    }
+
+    async fn list_append_many(
+        &self,
+        request: Request<KeyValues>,
+    ) -> Result<Response<Bool>, Status> {
+        let request_inner = request.into_inner();
+        let result = self
+            .storage
+            .list_append_many(&request_inner.key, &request_inner.values)
+            .await;
+        match result {
+            Ok(value) => Ok(Response::new(Bool { value })),
+            Err(e) => Err(to_status(&*e)),
         }
     }
 
     async fn clock(&self, request: Request<Clock>) -> Result<Response<Clock>, Status> {
-        // //println!("-- clock funtion-- Received request from: {:?}", request);
-        let result = self.storage.clock(request.into_inner().timestamp).await;
+        let at_least = Hlc::from(request.into_inner()).to_u64();
+        let result = self.storage.clock(at_least).await;
         match result {
-            Ok(value) => Ok(Response::new(Clock { timestamp: value })),
-            Err(e) => Err(Status::invalid_argument("server clock() failed")),
+            Ok(value) => Ok(Response::new(Hlc::from_u64(value).into())),
+            Err(e) => Err(to_status(&*e)),
+        }
+    }
+
+    async fn txn(&self, request: Request<TxnRequest>) -> Result<Response<TxnResponse>, Status> {
+        let txn = txn_request(request.into_inner())?;
+        match self.storage.transaction(&txn).await {
+            Ok(r) => Ok(Response::new(TxnResponse {
+                committed: r.committed,
+                values: r
+                    .values
+                    .into_iter()
+                    .map(|value| MaybeValue { value })
+                    .collect(),
+            })),
+            Err(e) => Err(to_status(&*e)),
+        }
+    }
+
+    async fn health(
+        &self,
+        _request: Request<HealthCheck>,
+    ) -> Result<Response<HealthStatus>, Status> {
+        // answering at all is the signal; the storage is not touched so the
+        // probe does not move the clock or write to disk
+        Ok(Response::new(HealthStatus { serving: true }))
+    }
+
+    async fn stats(&self, _request: Request<StatsRequest>) -> Result<Response<Stats>, Status> {
+        match self.storage.stats().await {
+            Ok(s) => Ok(Response::new(Stats {
+                used_bytes: s.used_bytes,
+                limit_bytes: s.limit_bytes.unwrap_or(0),
+                keys: s.keys,
+                lists: s.lists,
+                evicted: s.evicted,
+                rejected: s.rejected,
+            })),
+            Err(e) => Err(to_status(&*e)),
         }
     }
+
+    async fn get_bytes(&self, request: Request<BytesKey>) -> Result<Response<BytesValue>, Status> {
+        let result = self
+            .storage
+            .bytes()
+            .ok_or_else(no_bytes)?
+            .get_bytes(&request.into_inner().key)
+            .await;
+        match result {
+            Ok(value) => Ok(Response::new(BytesValue { value })),
+            Err(e) => Err(to_status(&*e)),
+        }
+    }
+
+    async fn set_bytes(&self, request: Request<BytesKeyValue>) -> Result<Response<Bool>, Status> {
+        let request_inner = request.into_inner();
+        let result = self
+            .storage
+            .bytes()
+            .ok_or_else(no_bytes)?
+            .set_bytes(&request_inner.key, &request_inner.value)
+            .await;
+        match result {
+            Ok(value) => Ok(Response::new(Bool { value })),
+            Err(e) => Err(to_status(&*e)),
+        }
+    }
+
+    async fn keys_bytes(&self, request: Request<BytesKey>) -> Result<Response<BytesList>, Status> {
+        let result = self
+            .storage
+            .bytes()
+            .ok_or_else(no_bytes)?
+            .keys_bytes(&request.into_inner().key)
+            .await;
+        match result {
+            Ok(list) => Ok(Response::new(BytesList { list })),
+            Err(e) => Err(to_status(&*e)),
+        }
+    }
+
+    async fn list_get_bytes(
+        &self,
+        request: Request<BytesKey>,
+    ) -> Result<Response<BytesList>, Status> {
+        let result = self
+            .storage
+            .bytes()
+            .ok_or_else(no_bytes)?
+            .list_get_bytes(&request.into_inner().key)
+            .await;
+        match result {
+            Ok(list) => Ok(Response::new(BytesList { list })),
+            Err(e) => Err(to_status(&*e)),
+        }
+    }
+
+    async fn list_append_bytes(
+        &self,
+        request: Request<BytesKeyValue>,
+    ) -> Result<Response<Bool>, Status> {
+        let request_inner = request.into_inner();
+        let result = self
+            .storage
+            .bytes()
+            .ok_or_else(no_bytes)?
+            .list_append_bytes(&request_inner.key, &request_inner.value)
+            .await;
+        match result {
+            Ok(value) => Ok(Response::new(Bool { value })),
+            Err(e) => Err(to_status(&*e)),
+        }
+    }
+
+    async fn list_remove_bytes(
+        &self,
+        request: Request<BytesKeyValue>,
+    ) -> Result<Response<ListRemoveResponse>, Status> {
+        let request_inner = request.into_inner();
+        let result = self
+            .storage
+            .bytes()
+            .ok_or_else(no_bytes)?
+            .list_remove_bytes(&request_inner.key, &request_inner.value)
+            .await;
+        match result {
+            Ok(removed) => Ok(Response::new(ListRemoveResponse { removed })),
+            Err(e) => Err(to_status(&*e)),
+        }
+    }
+
+    async fn list_keys_bytes(
+        &self,
+        request: Request<BytesKey>,
+    ) -> Result<Response<BytesList>, Status> {
+        let result = self
+            .storage
+            .bytes()
+            .ok_or_else(no_bytes)?
+            .list_keys_bytes(&request.into_inner().key)
+            .await;
+        match result {
+            Ok(list) => Ok(Response::new(BytesList { list })),
+            Err(e) => Err(to_status(&*e)),
+        }
+    }
+
+    type ScanStream = ReceiverStream<Result<Record, Status>>;
+
+    async fn scan(
+        &self,
+        request: Request<rpcPattern>,
+    ) -> Result<Response<Self::ScanStream>, Status> {
+        let request_inner = request.into_inner();
+        let pattern = Pattern {
+            prefix: request_inner.prefix,
+            suffix: request_inner.suffix,
+        };
+        let storage = self.storage.clone();
+        let (tx, rx) = mpsc::channel(SCAN_PAGE);
+        tokio::spawn(async move {
+            if let Err(e) = scan_records(&*storage, &pattern, &tx).await {
+                let _ = tx.send(Err(to_status(&*e))).await;
+            }
+        });
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+
+    async fn ingest(
+        &self,
+        request: Request<Streaming<Record>>,
+    ) -> Result<Response<IngestResponse>, Status> {
+        let mut stream = request.into_inner();
+        let mut ingested = 0;
+        while let Some(record) = stream.message().await? {
+            match ingest_record(&*self.storage, record).await {
+                Ok(true) => ingested += 1,
+                Ok(false) => (),
+                Err(e) => return Err(to_status(&*e)),
+            }
+        }
+        Ok(Response::new(IngestResponse { ingested }))
+    }
+
+    type WatchStream = ReceiverStream<Result<rpcChange, Status>>;
+
+    async fn watch(
+        &self,
+        request: Request<WatchRequest>,
+    ) -> Result<Response<Self::WatchStream>, Status> {
+        let request_inner = request.into_inner();
+        let p = request_inner.pattern.unwrap_or_default();
+        let pattern = Pattern {
+            prefix: p.prefix,
+            suffix: p.suffix,
+        };
+        let watcher = match self.storage.watcher() {
+            Some(watcher) => watcher,
+            None => return Err(Status::unimplemented("storage cannot be watched")),
+        };
+        let mut changes = watcher
+            .watch(&pattern, request_inner.since)
+            .await
+            .map_err(|e| to_status(&*e))?;
+
+        let (tx, rx) = mpsc::channel(WATCH_HISTORY);
+        tokio::spawn(async move {
+            loop {
+                let change = tokio::select! {
+                    // the client went away
+                    _ = tx.closed() => return,
+                    change = changes.recv() => change,
+                };
+                let item = match change {
+                    Some(Ok(change)) => Ok(change_response(change)),
+                    Some(Err(e)) => Err(Status::aborted(e.to_string())),
+                    None => return,
+                };
+                if tx.send(item).await.is_err() {
+                    return;
+                }
+            }
+        });
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+}
+
+/// Sends every key and list of `storage` matching `pattern` down `tx`, a page
+/// at a time, until they run out or the receiver goes away.
+async fn scan_records(
+    storage: &dyn Storage,
+    pattern: &Pattern,
+    tx: &Sender<Result<Record, Status>>,
+) -> TribResult<()> {
+    let mut after = String::new();
+    loop {
+        let page = storage.keys_page(pattern, &after, SCAN_PAGE).await?;
+        let values = storage.multi_get(&page.keys.0).await?;
+        for (key, value) in page.keys.0.into_iter().zip(values) {
+            // the key may have been cleared since it was listed
+            let value = match value {
+                Some(value) => value,
+                None => continue,
+            };
+            let record = Record {
+                ttl_ms: ttl_ms(storage.ttl(&key).await?),
+                key,
+                entry: Some(Entry::Value(value)),
+            };
+            if tx.send(Ok(record)).await.is_err() {
+                return Ok(());
+            }
+        }
+        match page.next {
+            Some(next) => after = next,
+            None => break,
+        }
+    }
+    let mut after = String::new();
+    loop {
+        let page = storage.list_keys_page(pattern, &after, SCAN_PAGE).await?;
+        let lists = storage.multi_list_get(&page.keys.0).await?;
+        for (key, list) in page.keys.0.into_iter().zip(lists) {
+            if list.0.is_empty() {
+                continue;
+            }
+            let record = Record {
+                ttl_ms: ttl_ms(storage.list_ttl(&key).await?),
+                key,
+                entry: Some(Entry::List(StringList { list: list.0 })),
+            };
+            if tx.send(Ok(record)).await.is_err() {
+                return Ok(());
+            }
+        }
+        match page.next {
+            Some(next) => after = next,
+            None => return Ok(()),
+        }
+    }
+}
+
+/// Writes `record` into `storage`, keeping its expiry, and returns whether
+/// it held anything.
+async fn ingest_record(storage: &dyn Storage, record: Record) -> TribResult<bool> {
+    let ttl = match record.ttl_ms {
+        0 => None,
+        ms => Some(Duration::from_millis(ms)),
+    };
+    match record.entry {
+        Some(Entry::Value(value)) => {
+            let kv = KeyValue {
+                key: record.key,
+                value,
+            };
+            match ttl {
+                Some(ttl) => storage.set_with_ttl(&kv, ttl).await?,
+                None => storage.set(&kv).await?,
+            };
+        }
+        Some(Entry::List(list)) => {
+            // only the entries the storage lacks are added, all at once, so
+            // an earlier, interrupted copy is not repeated and whatever was
+            // appended here in the meantime stays
+            let missing = missing_entries(list.list, storage.list_get(&record.key).await?.0);
+            if !missing.is_empty() {
+                match ttl {
+                    Some(ttl) => {
+                        storage
+                            .list_append_many_with_ttl(&record.key, &missing, ttl)
+                            .await?
+                    }
+                    None => storage.list_append_many(&record.key, &missing).await?,
+                };
+            }
+        }
+        None => return Ok(false),
+    }
+    Ok(true)
+}
+
+/// The entries of `incoming` that `existing` does not hold, counting
+/// repeated entries one by one.
+fn missing_entries(incoming: Vec<String>, existing: Vec<String>) -> Vec<String> {
+    let mut held = HashMap::new();
+    for entry in existing {
+        *held.entry(entry).or_insert(0) += 1;
+    }
+    incoming
+        .into_iter()
+        .filter(|entry| match held.get_mut(entry) {
+            Some(n) if *n > 0 => {
+                *n -= 1;
+                false
+            }
+            _ => true,
+        })
+        .collect()
+}
+
+/// A [Record::ttl_ms] for `ttl`.
+fn ttl_ms(ttl: Option<Duration>) -> u64 {
+    ttl.map_or(0, ttl_millis)
+}
+
+/// The answer to the bytes calls when the storage cannot hold bytes.
+fn no_bytes() -> Status {
+    Status::unimplemented("storage cannot hold bytes")
+}
+
+fn page_request(r: KeysPageRequest) -> (Pattern, String, usize) {
+    let p = r.pattern.unwrap_or_default();
+    let p = Pattern {
+        prefix: p.prefix,
+        suffix: p.suffix,
+    };
+    (p, r.start_after, r.limit as usize)
+}
+
+fn page_response(page: KeyPage) -> KeysPage {
+    KeysPage {
+        keys: page.keys.0,
+        next: page.next,
+    }
+}
+
+fn change_response(change: Change) -> rpcChange {
+    let kind = match change.kind {
+        ChangeKind::Set => rpcChangeKind::Set,
+        ChangeKind::ListAppend => rpcChangeKind::ListAppend,
+        ChangeKind::ListRemove => rpcChangeKind::ListRemove,
+        ChangeKind::ListTrim => rpcChangeKind::ListTrim,
+        ChangeKind::Expire => rpcChangeKind::Expire,
+        ChangeKind::ListExpire => rpcChangeKind::ListExpire,
+        ChangeKind::Evict => rpcChangeKind::Evict,
+    };
+    rpcChange {
+        seq: change.seq,
+        kind: kind as i32,
+        key: change.key,
+        value: change.value,
+    }
+}
+
+/// The [Txn] a request asks for. A check or a step that says nothing is
+/// refused rather than skipped, so a guarded write never runs unguarded.
+#[allow(clippy::result_large_err)]
+fn txn_request(r: TxnRequest) -> Result<Txn, Status> {
+    let kv = |kv: rpcKeyValue| KeyValue {
+        key: kv.key,
+        value: kv.value,
+    };
+    let checks = r
+        .checks
+        .into_iter()
+        .map(|c| match c.check {
+            Some(Check::Value(v)) => Ok(Precondition::Value {
+                key: v.key,
+                value: v.value,
+            }),
+            Some(Check::ListLen(l)) => Ok(Precondition::ListLen {
+                key: l.key,
+                len: l.len,
+            }),
+            None => Err(Status::invalid_argument("empty precondition")),
+        })
+        .collect::<Result<_, _>>()?;
+    let ops = r
+        .ops
+        .into_iter()
+        .map(|op| match op.op {
+            Some(Op::Get(key)) => Ok(TxnOp::Get(key)),
+            Some(Op::Set(v)) => Ok(TxnOp::Set(kv(v))),
+            Some(Op::ListAppend(v)) => Ok(TxnOp::ListAppend(kv(v))),
+            Some(Op::ListRemove(v)) => Ok(TxnOp::ListRemove(kv(v))),
+            None => Err(Status::invalid_argument("empty transaction step")),
+        })
+        .collect::<Result<_, _>>()?;
+    Ok(Txn { checks, ops })
 }
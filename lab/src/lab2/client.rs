@@ -1,25 +1,34 @@
+use super::membership::Membership;
 use super::utils::StatusTableEntry;
 use crate::lab1::client::StorageClient;
-use crate::lab2::wrapper::StorageClientWrapper;
+use crate::lab1::pool::ChannelPool;
+use crate::lab2::wrapper::{
+    check_quorum, merge_multi_lists, merge_multi_ranges, replica_range, StorageClientWrapper,
+};
 use async_trait::async_trait;
-use std::collections::hash_map::DefaultHasher;
-use std::hash::Hasher;
-use std::sync::Arc;
-use std::time::Instant;
-use tokio::sync::Mutex;
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::time;
+use tribbler::colon::BinKey;
+use tribbler::config::ReplicationConfig;
 use tribbler::err::TribResult;
-use tribbler::rpc::trib_storage_client::TribStorageClient;
-use tribbler::rpc::Key;
-use tribbler::storage::{BinStorage, Storage};
+use tribbler::rpc::HealthCheck;
+use tribbler::storage::{BinStorage, KeyList, List, Storage};
 
-pub async fn scan_server(backs: Vec<String>) -> Vec<StatusTableEntry> {
+/// how long a backend may take to answer the health check in [scan_server]
+const SCAN_TIMEOUT: Duration = Duration::from_secs(1);
+
+pub async fn scan_server(backs: Vec<String>, pool: &ChannelPool) -> Vec<StatusTableEntry> {
     // scan all servers and establish the table
     // multi-processor
     //let timer2 = Instant::now();
     let mut handles = Vec::with_capacity(backs.len());
     let mut status_table_multi: Vec<StatusTableEntry> = Vec::new();
     for i in backs.iter() {
-        handles.push(tokio::spawn(scan_single_server(i.to_string())));
+        handles.push(tokio::spawn(scan_single_server(
+            i.to_string(),
+            pool.clone(),
+        )));
     }
     for handle in handles {
         let entry = handle.await;
@@ -36,61 +45,130 @@ pub async fn scan_server(backs: Vec<String>) -> Vec<StatusTableEntry> {
     // println!("multi process timing:{:?} ms", timer2.elapsed().as_millis());
     return status_table_multi;
 }
-pub async fn scan_single_server(addr: String) -> StatusTableEntry {
+pub async fn scan_single_server(addr: String, pool: ChannelPool) -> StatusTableEntry {
     let mut addr_http = "http://".to_string();
     addr_http.push_str(&addr);
-    let client = TribStorageClient::connect(addr_http.clone()).await;
-    match client {
-        Ok(_) => StatusTableEntry {
-            addr: addr.clone(),
-            status: true,
-        },
-        Err(_) => StatusTableEntry {
-            addr: addr.clone(),
-            status: false,
-        },
-    }
-}
-pub async fn hash_name_ip(name: &str, table: Vec<StatusTableEntry>) -> (String, String) {
-    let mut hasher = DefaultHasher::new();
-    hasher.write(name.as_bytes());
-    let index = hasher.finish() as usize % table.len();
-
-    // choose 2 ip
-    let mut tmp_addr_primary = "http://".to_string();
-    let mut tmp_addr_backup = "http://".to_string();
-    let mut cnt = 0;
-    let mut curridx = index;
-    while cnt < 2 {
-        if table[curridx].status == true {
-            if cnt == 0 {
-                tmp_addr_primary.push_str(&table[curridx].addr);
-            } else {
-                tmp_addr_backup.push_str(&table[curridx].addr);
-            }
-            cnt += 1;
+    // a pooled channel may outlive its backend, so ask the service itself
+    // instead of trusting that the channel is connected
+    let probe = async {
+        let mut client = pool.client(&addr_http).await?;
+        let status = client.health(HealthCheck {}).await?;
+        TribResult::Ok(status.into_inner().serving)
+    };
+    let status = match time::timeout(SCAN_TIMEOUT, probe).await {
+        Ok(Ok(serving)) => serving,
+        _ => {
+            pool.invalidate(&addr_http);
+            false
         }
-        curridx = (curridx + 1) % table.len();
+    };
+    StatusTableEntry {
+        addr: addr.clone(),
+        status,
     }
-    (tmp_addr_primary, tmp_addr_backup)
 }
 pub struct BinStorageClient {
-    pub backs: Vec<String>,
+    pub pool: ChannelPool,
+    pub replication: ReplicationConfig,
+    pub membership: Membership,
 }
 // bin() which takes a bin name and returns a Storage
 #[async_trait]
 impl BinStorage for BinStorageClient {
     async fn bin(&self, name: &str) -> TribResult<Box<dyn Storage>> {
-        let time = Instant::now();
-        let table = scan_server(self.backs.clone()).await;
-        let (tmp_addr_primary, tmp_addr_backup) = hash_name_ip(name, table.clone()).await;
         Ok(Box::new(StorageClientWrapper {
-            backs: self.backs.clone(),
-            status_table: Arc::new(Mutex::new(table.clone())),
+            pool: self.pool.clone(),
+            replication: self.replication,
+            membership: self.membership.clone(),
             name: name.to_string(),
-            timestamp: Arc::new(Mutex::new(time)),
-            addr_primary: Arc::new(Mutex::new(tmp_addr_primary)),
-            addr_backup: Arc::new(Mutex::new(tmp_addr_backup)),
         }))
     }
+
+    async fn multi_bin_list_get(&self, names: &[String], key: &str) -> TribResult<Vec<List>> {
+        let mut lists = vec![List(vec![]); names.len()];
+        for (addrs, idx, key_names) in self.group_bins(names, key).await {
+            let mut results = Vec::with_capacity(addrs.len());
+            for addr in addrs.iter() {
+                results.push(
+                    StorageClient::new(addr, self.pool.clone())
+                        .multi_list_get(&key_names)
+                        .await,
+                );
+            }
+            if results.iter().any(|r| r.is_err()) {
+                self.membership.report_failure();
+            }
+            let answers = check_quorum(results, &self.replication, self.replication.read_quorum)?;
+            for (i, list) in idx
+                .into_iter()
+                .zip(merge_multi_lists(answers, key_names.len()))
+            {
+                lists[i] = list;
+            }
+        }
+        Ok(lists)
+    }
+
+    async fn multi_bin_list_range(
+        &self,
+        names: &[String],
+        key: &str,
+        start: i64,
+        end: i64,
+    ) -> TribResult<Vec<List>> {
+        let (s, e) = replica_range(start, end);
+        let mut lists = vec![List(vec![]); names.len()];
+        for (addrs, idx, key_names) in self.group_bins(names, key).await {
+            let mut results = Vec::with_capacity(addrs.len());
+            for addr in addrs.iter() {
+                results.push(
+                    StorageClient::new(addr, self.pool.clone())
+                        .multi_list_range(&key_names, s, e)
+                        .await,
+                );
+            }
+            if results.iter().any(|r| r.is_err()) {
+                self.membership.report_failure();
+            }
+            let answers = check_quorum(results, &self.replication, self.replication.read_quorum)?;
+            for (i, list) in
+                idx.into_iter()
+                    .zip(merge_multi_ranges(answers, key_names.len(), start, end))
+            {
+                lists[i] = list;
+            }
+        }
+        Ok(lists)
+    }
+}
+
+impl BinStorageClient {
+    /// Groups the bins in `names` by the set of backends holding them, so
+    /// that each set is asked once for all of its lists. Every group comes
+    /// with the indices of its bins in `names` and the physical names of
+    /// their lists `key`.
+    async fn group_bins(
+        &self,
+        names: &[String],
+        key: &str,
+    ) -> Vec<(Vec<String>, Vec<usize>, Vec<String>)> {
+        let mut groups: HashMap<Vec<String>, Vec<usize>> = HashMap::new();
+        for (i, name) in names.iter().enumerate() {
+            let addrs = self
+                .membership
+                .replica_addrs(name, self.replication.factor)
+                .await;
+            groups.entry(addrs).or_default().push(i);
+        }
+        groups
+            .into_iter()
+            .map(|(addrs, idx)| {
+                let key_names = idx
+                    .iter()
+                    .map(|&i| BinKey::new(&names[i], key).encode())
+                    .collect::<Vec<String>>();
+                (addrs, idx, key_names)
+            })
+            .collect()
+    }
 }
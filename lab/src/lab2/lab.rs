@@ -1,33 +1,56 @@
-use super::client::scan_server;
+use super::client::{scan_server, scan_single_server};
 use std::net::ToSocketAddrs;
 
 #[allow(unused_variables)]
 use super::server::FrontServer;
-use crate::keeper::keeper_work_client::KeeperWorkClient;
 use crate::keeper::keeper_work_server::KeeperWorkServer;
-use crate::keeper::{Index, Leader};
+use crate::lab1::pool::ChannelPool;
 use crate::lab2::client::BinStorageClient;
-use crate::lab2::utils::{node_join, node_leave, write_twice, StatusTableEntry};
-use std::collections::hash_map::DefaultHasher;
-use std::hash::Hasher;
+use crate::lab2::membership::Membership;
+use crate::lab2::utils::{node_join, node_leave, publish_epoch, read_epoch, MembershipEpoch};
 use tokio::{select, time};
 use tonic::transport::Server;
 use tribbler::err::TribblerError;
-use tribbler::rpc::{Clock, Key, Pattern};
 use tribbler::{
-    config::KeeperConfig, err::TribResult, rpc::trib_storage_client::TribStorageClient,
+    config::{KeeperConfig, RateLimits, ReplicationConfig, TlsConfig},
+    err::TribResult,
+    metrics::{self, RpcMetrics},
+    disk::DiskStorage,
     storage::BinStorage,
 };
 
-use crate::lab3::myKeeper::{Keeper, KeeperClient, KeeperServer};
-use tribbler::storage::MemStorage;
+use crate::lab3::myKeeper::{GrpcTransport, KeeperServer};
+use crate::lab3::raft::{Raft, Role, HEARTBEAT_INTERVAL};
+use std::sync::Arc;
+
+/// How often the leading keeper checks on the backends; a backend which
+/// fails or comes back is noticed within this long.
+const SWEEP_INTERVAL: time::Duration = time::Duration::from_secs(1);
 
 /// This function accepts a list of backend addresses, and returns a
 /// type which should implement the [BinStorage] trait to access the
 /// underlying storage system.
 #[allow(unused_variables)]
 pub async fn new_bin_client(backs: Vec<String>) -> TribResult<Box<dyn BinStorage>> {
-    Ok(Box::new(BinStorageClient { backs }))
+    new_replicated_bin_client(backs, ReplicationConfig::default(), None).await
+}
+
+/// Like [new_bin_client], but keeps `replication.factor` copies of every bin
+/// and waits for the configured quorums, and talks to the back-ends over TLS
+/// when `tls` is set.
+pub async fn new_replicated_bin_client(
+    backs: Vec<String>,
+    replication: ReplicationConfig,
+    tls: Option<&TlsConfig>,
+) -> TribResult<Box<dyn BinStorage>> {
+    replication.check()?;
+    let pool = ChannelPool::with_tls(tls)?;
+    let membership = Membership::start(backs, pool.clone(), replication).await;
+    Ok(Box::new(BinStorageClient {
+        pool,
+        replication,
+        membership,
+    }))
 }
 
 /// this async function accepts a [KeeperConfig] that should be used to start
@@ -38,6 +61,29 @@ pub async fn new_bin_client(backs: Vec<String>) -> TribResult<Box<dyn BinStorage
 /// started.
 #[allow(unused_variables)]
 pub async fn serve_keeper(kc: KeeperConfig) -> TribResult<()> {
+    // every backend connection made by this keeper goes through one pool
+    let setup = || -> TribResult<(ChannelPool, Server<RpcMetrics>, Option<DiskStorage>)> {
+        let pool = ChannelPool::with_tls(kc.tls.as_ref())?;
+        let mut builder = Server::builder().layer(RpcMetrics::new(kc.addr()));
+        if let Some(tls) = &kc.tls {
+            builder = builder.tls_config(tls.server(kc.addr())?)?;
+        }
+        let durable = kc.data_dir.as_ref().map(DiskStorage::open).transpose()?;
+        Ok((pool, builder, durable))
+    };
+    let (pool, mut builder, durable) = match setup() {
+        Ok(setup) => setup,
+        Err(e) => {
+            if let Some(channel) = kc.ready {
+                let _ = channel.send(false);
+            }
+            return Err(e);
+        }
+    };
+
+    // the label of this keeper's metrics
+    let keeper = kc.addr().to_string();
+
     // send a true over the ready channel when the service is ready (when ready is not None),
     match kc.ready {
         Some(channel) => {
@@ -47,58 +93,55 @@ pub async fn serve_keeper(kc: KeeperConfig) -> TribResult<()> {
     }
 
     // get a initial status table
-    let mut status_table = scan_server(kc.backs.clone()).await;
+    let mut status_table = scan_server(kc.backs.clone(), &pool).await;
     let mut kc_addr_http = "http://".to_string();
     kc_addr_http.push_str(kc.addrs.get(kc.this).unwrap());
 
-    // try to fetch the previous one
-    let mut hasher = DefaultHasher::new();
-    hasher.write("BackendStatus".as_bytes());
-    let backend_hash = hasher.finish() as usize % kc.backs.len();
-    let mut status_storage_index = backend_hash;
-    while !status_table[status_storage_index].status {
-        status_storage_index = (status_storage_index + 1) % kc.backs.len();
-    }
-
-    let mut status_addr = "http://".to_string();
-    status_addr.push_str(&status_table[status_storage_index].addr.clone());
-    let mut status_client = TribStorageClient::connect(status_addr.to_string()).await?;
-    let previous_status_table = status_client
-        .get(Key {
-            key: "BackendStatus".to_string(),
-        })
-        .await;
-
-    match previous_status_table {
-        Ok(value) => {
-            status_table =
-                serde_json::from_str::<Vec<StatusTableEntry>>(&value.into_inner().value).unwrap();
+    // try to fetch the view published by an earlier keeper
+    let mut epoch = 0;
+    match read_epoch(&kc.backs, kc.replication.factor, &pool).await {
+        Some(published) => {
+            epoch = published.epoch;
+            status_table = published.table;
         }
-        Err(e) => {
-            if e.message().eq("No key provided") {
-                let serialized_table = serde_json::to_string(&status_table).unwrap();
-                let x = write_twice(serialized_table, status_storage_index, &status_table).await;
-            } else {
-                // println!("SHOULD NOT APPEAR");
-            }
+        None => {
+            let x = publish_epoch(
+                &MembershipEpoch {
+                    epoch,
+                    table: status_table.clone(),
+                },
+                kc.replication.factor,
+                &pool,
+            )
+            .await;
         }
     }
+    // the keepers agree on every new epoch through raft before any client
+    // can see it; with a data dir their votes survive restarts, so two
+    // keepers can never both think they lead a term
+    let transport = Arc::new(GrpcTransport {
+        keepers: kc.addrs.clone(),
+        pool: pool.clone(),
+    });
+    let raft = Arc::new(match durable {
+        Some(storage) => Raft::open(kc.this, kc.addrs.len(), transport, Box::new(storage)).await?,
+        None => Raft::new(kc.this, kc.addrs.len(), transport),
+    });
     // now status_table stores the previous recorded backend status table
-    let mut clock: u64 = 0;
     select! {
         _ =  async {
             // println!("the {} keep server starts", kc_addr_http);
             // the server thread
                 // let server starts to work
                 let server = KeeperServer {
-                    storage: Box::new(MemStorage::new()),
+                    raft: raft.clone(),
                 };
                 let keep_server = KeeperWorkServer::new(server);
                 let addr = kc.addrs.get(kc.this).unwrap().to_socket_addrs().unwrap().next();
 
                 let res = match addr {
                     Some(value) => {
-                        let x = Server::builder()
+                        let x = builder
                             .add_service(keep_server)
                             .serve(value)
                             .await;
@@ -106,128 +149,65 @@ pub async fn serve_keeper(kc: KeeperConfig) -> TribResult<()> {
                     None => (),
                 };
         } => {}
+        _ = raft.clone().run() => {}
         _ =  async {
             // the client thread
-            time::sleep(time::Duration::from_secs(1)).await;
-            // println!("the {} keep client starts", kc_addr_http);
-            let keeper = Keeper {
-                keepers: kc.addrs.clone(),
-                backs: kc.backs.clone(),
-            };
-
-            let client = KeeperWorkClient::connect(kc_addr_http.to_string()).await;
-            // connect to itself and set an empty leader id
-            match client {
-                Ok(value) => {
-                    let mut c = value;
-                    match c.set_leader(Leader{ leader_id: -1 }).await {
-                        Ok(_) => (),
-                        Err(_) => (),
-                    };
-                    match c.set_index(Index{index: (kc.this as i64) }).await{
-                        Ok(_) => {
-                            // println!("{} sets the index is {}", kc_addr_http, kc.this);
-                        },
-                        Err(e) => {
-                            // println!("{} set index goes wrong", kc_addr_http);
-                            // println!("{:?}", e);
-                        },
-                    };
-                }
-                Err(e) => {
-                    ();
-                }
-            }
-            // println!("the {} keep client check_leader", kc_addr_http);
-            // just go online
-            let res = keeper.check_leader().await;
-            // println!("the current leader is {}",res);
-            if res > 0 {
-                // println!("{} finds there is a leader", kc_addr_http);
-                // start heartbeat
-                let mut primary_alive = true;
-                while primary_alive {
-                    // send heart beat to the leader of current view
-                    let res = keeper.keeper_heart_beat(kc.this as i64, res).await;
-                    match res {
-                        Ok(value) => {
-                            // the primary alive wait 1 sec
-                            time::sleep(time::Duration::from_secs(1)).await;
-                        }
-                        Err(e) => {
-                            primary_alive = false;
-                        }
-                    }
-                }
-            }
-
             loop {
-                // start selecting leader
-                let leader_id = keeper.select_leader().await;
-                // println!("the {} keep client is not the leader", kc_addr_http);
-                let client = KeeperWorkClient::connect(kc_addr_http.to_string()).await;
-                match client {
-                    Ok(value) => {
-                        let mut c = value;
-                        match c.set_leader(Leader{ leader_id: leader_id }).await {
-                            Ok(_) => (),
-                            Err(_) => (),
-                        };
-                    },
-                    Err(e) => {
-                        ();
-                    }
+                // only the raft leader does data migration
+                let (term, role) = raft.status().await;
+                metrics::KEEPER_LEADER
+                    .with_label_values(&[&keeper])
+                    .set((role == Role::Leader) as i64);
+                if role != Role::Leader {
+                    time::sleep(HEARTBEAT_INTERVAL).await;
+                    continue;
                 }
-                // if this keeper is not the leader,
-                // block it in a hear_beat
-                if leader_id != (kc.this as i64) {
-                    // println!("the {} keep client is not the leader", kc_addr_http);
-                    // start heartbeat
-                    let mut primary_alive = true;
-                    while primary_alive {
-                        // send heart beat to the leader of current view
-                        let res = keeper.keeper_heart_beat(kc.this as i64, leader_id).await;
-                        match res {
-                            Ok(value) => {
-                                // the primary alive wait 1 sec
-                                time::sleep(time::Duration::from_secs(1)).await;
-                            }
-                            Err(e) => {
-                                // println!("{} is dead", leader_id);
-                                primary_alive = false;
-                            }
-                        }
+                // another keeper may have led since this one started, so
+                // carry on from the newest epoch it committed or published
+                let committed = raft
+                    .last_committed()
+                    .await
+                    .and_then(|c| serde_json::from_str::<MembershipEpoch>(&c).ok());
+                let published = read_epoch(&kc.backs, kc.replication.factor, &pool).await;
+                for newer in committed.into_iter().chain(published) {
+                    if newer.epoch > epoch {
+                        epoch = newer.epoch;
+                        status_table = newer.table;
                     }
-                } else {
-                    // println!("the {} keep client is the leader", kc_addr_http);
-                    loop{
+                }
+                while raft.status().await == (term, Role::Leader) {
+                        let mut changed = false;
+                        let mut deposed = false;
                         //println!(" {} starts do its work", kc_addr_http);
                         // **********************************************************************
                         // **********************************************************************
                         // **********************************************************************
-                        let mut clocks = Vec::new();
                         for i in 0..kc.backs.len() {
-                            let mut addr_http = "http://".to_string();
-                            addr_http.push_str(&kc.backs[i]);
-                            let client = TribStorageClient::connect(addr_http.to_string()).await;
-                            match client {
-                                Ok(value) => {
-                                    let mut c = value;
-                                    match c.clock(Clock { timestamp: clock }).await {
-                                        Ok(v0) => {
-                                            clocks.push(v0.into_inner().timestamp);
-                                        }
-                                        Err(e) => {
-                                            // return Box::new(TribblerError::Unknown(e.to_string()));
-                                        }
-                                    }
+                            // a pooled channel can outlive its backend, so a backend
+                            // only counts as alive when it answers the health rpc,
+                            // and in time, so one that hangs cannot stall the sweep
+                            let alive = scan_single_server(kc.backs[i].clone(), pool.clone()).await.status;
+                            match alive {
+                                true => {
                                     // newly joined node
                                     if !status_table[i].status {
-                                        match node_join(i, &status_table).await {
-                                            Ok(_) => {},
-                                            Err(_) => {},
+                                        // a keeper deposed since this pass began must not move data
+                                        if raft.status().await != (term, Role::Leader) {
+                                            deposed = true;
+                                            break;
+                                        }
+                                        let joined = node_join(i, &status_table, &kc.replication, &pool).await;
+                                        metrics::KEEPER_MIGRATIONS
+                                            .with_label_values(&[&keeper, "join", metrics::result_label(&joined)])
+                                            .inc();
+                                        // until its data is in place the backend stays out of
+                                        // the table, and the next sweep tries again
+                                        if let Err(e) = joined {
+                                            log::warn!("migrating onto backend {} failed: {}", i, e);
+                                            continue;
                                         }
                                         status_table[i].status = true;
+                                        changed = true;
                                     }
                                     // ============ DEBUG ============
                                     // println!("***************** backend {} ***************** ", i);
@@ -280,44 +260,54 @@ pub async fn serve_keeper(kc: KeeperConfig) -> TribResult<()> {
                                     // println!("\n");
                                     // // ============ DEBUG ============
                                 }
-                                Err(e) => {
+                                false => {
                                     // node leaves
                                     if status_table[i].status {
-                                        match node_leave(i, &status_table).await {
-                                            Ok(_) => {},
-                                            Err(_) => {},
+                                        if raft.status().await != (term, Role::Leader) {
+                                            deposed = true;
+                                            break;
+                                        }
+                                        let left = node_leave(i, &status_table, &kc.replication, &pool).await;
+                                        metrics::KEEPER_MIGRATIONS
+                                            .with_label_values(&[&keeper, "leave", metrics::result_label(&left)])
+                                            .inc();
+                                        if let Err(e) = left {
+                                            log::warn!("migrating off backend {} failed: {}", i, e);
+                                            continue;
                                         }
                                         status_table[i].status = false;
+                                        changed = true;
                                     }
                                     // println!("Connect to backend {} failed", i);
                                     // return Box::new(TribblerError::Unknown(e.to_string()));
                                 }
                             }
                         }
-                        // write the updated status_table into storage
-                        let serialized_table = serde_json::to_string(&status_table).unwrap();
-                        let x = write_twice(serialized_table, backend_hash, &status_table).await;
-
-                        clock = *clocks.iter().max().unwrap();
-                        for addr in kc.backs.iter() {
-                            let mut addr_http = "http://".to_string();
-                            addr_http.push_str(addr);
-                            match TribStorageClient::connect(addr_http.to_string()).await {
-                                Ok(mut c) => {let _ = c.clock(Clock { timestamp: clock });}
-                                Err(e) => (),
+                        if deposed {
+                            break;
+                        }
+                        // publish the updated status_table as a new epoch once
+                        // its migrations are done, so clients route the same way
+                        if changed {
+                            epoch += 1;
+                            // a deposed leader must not publish its view
+                            let next = MembershipEpoch { epoch, table: status_table.clone() };
+                            let command = serde_json::to_string(&next).unwrap_or_default();
+                            if raft.propose(command).await.is_err() {
+                                break;
                             }
                         }
-                        time::sleep(time::Duration::from_secs(3)).await;
+                        let x = publish_epoch(&MembershipEpoch { epoch, table: status_table.clone() }, kc.replication.factor, &pool).await;
+                        let alive = status_table.iter().filter(|s| s.status).count();
+                        metrics::KEEPER_BACKENDS_ALIVE.with_label_values(&[&keeper]).set(alive as i64);
+                        metrics::KEEPER_EPOCH.with_label_values(&[&keeper]).set(epoch as i64);
+
+                        time::sleep(SWEEP_INTERVAL).await;
 
                         // **********************************************************************
                         // **********************************************************************
                         // **********************************************************************
                     }
-
-                // if this keeper is the leader,
-                // do clock sync and data migration
-                }
-
             }
 
         } => {}
@@ -345,8 +335,19 @@ pub async fn serve_keeper(kc: KeeperConfig) -> TribResult<()> {
 #[allow(unused_variables)]
 pub async fn new_front(
     bin_storage: Box<dyn BinStorage>,
+) -> TribResult<Box<dyn tribbler::trib::Server + Send + Sync>> {
+    new_limited_front(bin_storage, RateLimits::default()).await
+}
+
+/// Like [new_front], but every user's posts and follows, and all sign-ups,
+/// are held to `limits`. The token buckets are kept in `bin_storage`, so
+/// front-ends sharing it share the limits.
+pub async fn new_limited_front(
+    bin_storage: Box<dyn BinStorage>,
+    limits: RateLimits,
 ) -> TribResult<Box<dyn tribbler::trib::Server + Send + Sync>> {
     Ok(Box::new(FrontServer {
-        bin_storage: bin_storage,
+        bin_storage,
+        limits,
     }))
 }
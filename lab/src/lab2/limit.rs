@@ -0,0 +1,81 @@
+//! Token buckets kept in the bin storage, so that every front-end in front of
+//! the same storage draws from the same buckets.
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use tribbler::config::RateLimit;
+use tribbler::err::{TribResult, TribblerError};
+use tribbler::storage::Storage;
+
+/// The bin the buckets are kept in; not a valid user name, so it cannot
+/// clash with a user's bin.
+pub const LIMITS_BIN: &str = "RateLimits";
+
+/// How many times taking a token is retried when other front-ends keep
+/// taking tokens from the same bucket under it.
+const TAKE_RETRIES: usize = 5;
+
+/// A bucket as stored: the tokens left, and when they were counted.
+struct Bucket {
+    tokens: f64,
+    at_ms: u64,
+}
+
+impl Bucket {
+    fn parse(s: &str) -> Option<Bucket> {
+        let (tokens, at_ms) = s.split_once(' ')?;
+        Some(Bucket {
+            tokens: tokens.parse().ok()?,
+            at_ms: at_ms.parse().ok()?,
+        })
+    }
+
+    /// The bucket as it is at `now_ms`, refilled by `limit` since it was
+    /// last counted.
+    fn refill(&self, limit: &RateLimit, now_ms: u64) -> Bucket {
+        let minutes = now_ms.saturating_sub(self.at_ms) as f64 / 60_000.0;
+        Bucket {
+            tokens: (self.tokens + minutes * limit.per_minute as f64).min(limit.burst as f64),
+            at_ms: now_ms.max(self.at_ms),
+        }
+    }
+}
+
+/// Takes a token from the bucket `key` in `storage`, which `limit` fills.
+///
+/// Returns [TribblerError::RateLimited] when the bucket is empty, naming
+/// `what` was limited.
+pub async fn take(
+    storage: &dyn Storage,
+    key: &str,
+    limit: &RateLimit,
+    what: &str,
+) -> TribResult<()> {
+    for _ in 0..TAKE_RETRIES {
+        let now = now_ms();
+        let stored = storage.get(key).await?;
+        let bucket = match stored.as_deref().and_then(Bucket::parse) {
+            Some(b) => b.refill(limit, now),
+            None => Bucket {
+                tokens: limit.burst as f64,
+                at_ms: now,
+            },
+        };
+        if bucket.tokens < 1.0 {
+            break;
+        }
+        let taken = format!("{} {}", bucket.tokens - 1.0, bucket.at_ms);
+        if storage.cas(key, stored, &taken).await? {
+            return Ok(());
+        }
+    }
+    Err(Box::new(TribblerError::RateLimited(format!(
+        "too many calls to {}, at most {} a minute",
+        what, limit.per_minute
+    ))))
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as u64)
+}
@@ -0,0 +1,134 @@
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+
+use tokio::sync::{Notify, RwLock};
+use tokio::{select, time};
+
+use super::client::scan_server;
+use super::ring::HashRing;
+use super::utils::{live_ring, read_epoch, StatusTableEntry};
+use crate::lab1::pool::ChannelPool;
+use tribbler::config::ReplicationConfig;
+
+/// How often the background task re-probes every backend.
+pub const REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// One view of the backends, with the ring over the live ones built once
+/// for all the bin operations routed through it.
+struct Snapshot {
+    // the epoch the table was published under, None while no keeper has
+    // published one
+    epoch: Option<u64>,
+    table: Vec<StatusTableEntry>,
+    ring: HashRing,
+}
+
+impl Snapshot {
+    fn new(epoch: Option<u64>, table: Vec<StatusTableEntry>, vnodes: usize) -> Snapshot {
+        let ring = live_ring(&table, vnodes);
+        Snapshot { epoch, table, ring }
+    }
+}
+
+struct View {
+    current: RwLock<Snapshot>,
+    stale: Notify,
+}
+
+/// A view of which backends are alive, shared by every bin handed out by one
+/// bin client.
+///
+/// The view is the [MembershipEpoch](super::utils::MembershipEpoch) last
+/// published by the leader keeper, so bins are routed exactly the way the
+/// keeper migrated them; only while no keeper has published anything yet do
+/// the backends get health-checked directly. It is re-read every
+/// [REFRESH_INTERVAL], or sooner when a caller
+/// [reports](Membership::report_failure) a failed RPC, and never moves back
+/// to an older epoch. The background task stops once every clone of the view
+/// has been dropped.
+#[derive(Clone)]
+pub struct Membership {
+    view: Arc<View>,
+}
+
+impl Membership {
+    /// Loads the view once, so the first one is already accurate, then
+    /// starts the background refresh. `replication` has to be the keeper's:
+    /// it says how many copies of the epoch the keeper publishes, and how
+    /// many points every backend has on the ring.
+    pub async fn start(
+        backs: Vec<String>,
+        pool: ChannelPool,
+        replication: ReplicationConfig,
+    ) -> Membership {
+        let current = load(&backs, &pool, &replication).await;
+        let view = Arc::new(View {
+            current: RwLock::new(current),
+            stale: Notify::new(),
+        });
+        tokio::spawn(refresh(Arc::downgrade(&view), backs, pool, replication));
+        Membership { view }
+    }
+
+    /// Returns the current status of every backend, in configuration order.
+    pub async fn table(&self) -> Vec<StatusTableEntry> {
+        self.view.current.read().await.table.clone()
+    }
+
+    /// Returns the epoch of the current view, if a keeper has published one.
+    pub async fn epoch(&self) -> Option<u64> {
+        self.view.current.read().await.epoch
+    }
+
+    /// The `http://` addresses of the `replicas` live backends holding bin
+    /// `name` in the current view, primary first. Fewer are returned when
+    /// fewer backends are alive.
+    pub async fn replica_addrs(&self, name: &str, replicas: usize) -> Vec<String> {
+        let current = self.view.current.read().await;
+        current
+            .ring
+            .successors(name, replicas)
+            .into_iter()
+            .map(|i| format!("http://{}", current.table[i].addr))
+            .collect()
+    }
+
+    /// Asks for an early refresh after an RPC to some backend failed.
+    pub fn report_failure(&self) {
+        self.view.stale.notify_one();
+    }
+}
+
+async fn load(backs: &[String], pool: &ChannelPool, replication: &ReplicationConfig) -> Snapshot {
+    let vnodes = replication.vnodes;
+    match read_epoch(backs, replication.factor, pool).await {
+        Some(published) => Snapshot::new(Some(published.epoch), published.table, vnodes),
+        None => Snapshot::new(None, scan_server(backs.to_vec(), pool).await, vnodes),
+    }
+}
+
+async fn refresh(
+    view: Weak<View>,
+    backs: Vec<String>,
+    pool: ChannelPool,
+    replication: ReplicationConfig,
+) {
+    while let Some(v) = view.upgrade() {
+        select! {
+            _ = time::sleep(REFRESH_INTERVAL) => {}
+            _ = v.stale.notified() => {}
+        }
+        if Arc::strong_count(&v) == 1 {
+            // nobody but this task is looking at the view any more
+            return;
+        }
+        let loaded = load(&backs, &pool, &replication).await;
+        let mut current = v.current.write().await;
+        match (loaded.epoch, current.epoch) {
+            // an unreachable keeper or a stale copy must not roll us back
+            (None, Some(_)) => {}
+            (Some(e), Some(c)) if e < c => {}
+            _ => *current = loaded,
+        }
+    }
+}
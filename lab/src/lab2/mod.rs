@@ -169,9 +169,14 @@
 //! Happy Lab 3. :-)
 mod client;
 mod lab;
+mod limit;
+pub mod membership;
+pub mod ring;
 mod server;
 mod utils;
 mod wrapper;
 pub use crate::lab2::lab::new_bin_client;
 pub use crate::lab2::lab::new_front;
+pub use crate::lab2::lab::new_limited_front;
+pub use crate::lab2::lab::new_replicated_bin_client;
 pub use crate::lab2::lab::serve_keeper;
@@ -0,0 +1,87 @@
+/// Virtual nodes placed on the ring for every backend unless told otherwise.
+pub const DEFAULT_VNODES: usize = tribbler::config::DEFAULT_VNODES;
+
+const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+/// 64-bit FNV-1a. Unlike [std::collections::hash_map::DefaultHasher] its
+/// output is fixed, so every process places a name at the same point no
+/// matter which Rust release it was built with.
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut h = FNV_OFFSET;
+    for b in bytes {
+        h ^= *b as u64;
+        h = h.wrapping_mul(FNV_PRIME);
+    }
+    h
+}
+
+/// Where `bytes` lands on the ring. FNV-1a alone leaves names that differ
+/// only in their last few bytes (like the virtual node labels) bunched
+/// together, so the hash is run through the MurmurHash3 finalizer to spread
+/// them over the whole ring.
+fn point(bytes: &[u8]) -> u64 {
+    let mut h = fnv1a(bytes);
+    h ^= h >> 33;
+    h = h.wrapping_mul(0xff51afd7ed558ccd);
+    h ^= h >> 33;
+    h = h.wrapping_mul(0xc4ceb9fe1a85ec53);
+    h ^ (h >> 33)
+}
+
+/// A consistent hashing ring over a set of backends.
+///
+/// Every backend is placed at `vnodes` points derived from its address, so a
+/// backend joining or leaving only moves the names adjacent to its own
+/// points, and the load stays even with just a handful of backends. Backends
+/// are identified by their index in the caller's backend list.
+#[derive(Clone, Debug)]
+pub struct HashRing {
+    vnodes: usize,
+    // (point, backend index), sorted by point
+    points: Vec<(u64, usize)>,
+}
+
+impl HashRing {
+    pub fn new(vnodes: usize) -> HashRing {
+        HashRing {
+            vnodes: vnodes.max(1),
+            points: Vec::new(),
+        }
+    }
+
+    /// Places backend `index`, reachable at `addr`, on the ring.
+    pub fn add(&mut self, index: usize, addr: &str) {
+        for v in 0..self.vnodes {
+            self.points
+                .push((point(format!("{}#{}", addr, v).as_bytes()), index));
+        }
+        self.points.sort_unstable();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    /// Returns up to `n` distinct backends responsible for `name`, walking
+    /// clockwise from the point `name` hashes to. The first one is the
+    /// primary.
+    pub fn successors(&self, name: &str, n: usize) -> Vec<usize> {
+        let mut found: Vec<usize> = Vec::with_capacity(n);
+        if self.points.is_empty() {
+            return found;
+        }
+        let h = point(name.as_bytes());
+        let start = self.points.partition_point(|&(p, _)| p < h);
+        for i in 0..self.points.len() {
+            if found.len() == n {
+                break;
+            }
+            let (_, index) = self.points[(start + i) % self.points.len()];
+            if !found.contains(&index) {
+                found.push(index);
+            }
+        }
+        found
+    }
+}
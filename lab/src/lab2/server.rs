@@ -1,15 +1,28 @@
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
-use std::{cmp::min, cmp::Ordering, sync::Arc, time::SystemTime};
+use std::{cmp::min, cmp::Ordering, sync::Arc};
+use tribbler::config::{RateLimit, RateLimits};
 use tribbler::err::{TribResult, TribblerError};
-use tribbler::storage::{BinStorage, KeyValue};
+use tribbler::hlc::Hlc;
+use tribbler::storage::{BinStorage, KeyValue, Precondition, Txn, TxnOp};
 use tribbler::trib::{
     is_valid_username, Server, Trib, MAX_FOLLOWING, MAX_TRIB_FETCH, MAX_TRIB_LEN, MIN_LIST_USER,
 };
 
+use crate::lab2::limit::{self, LIMITS_BIN};
+
+/// How many times a follow or unfollow is retried when other requests keep
+/// changing the same follow log under it.
+const FOLLOW_RETRIES: usize = 5;
+
+/// The key in every user's bin naming the last change to their follow log,
+/// which a follow or unfollow checks has not moved before it writes.
+const FOLLOW_VERSION: &str = "log::version";
+
 pub struct FrontServer {
     pub bin_storage: Box<dyn BinStorage>,
+    pub limits: RateLimits,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
@@ -98,6 +111,94 @@ impl PartialEq for OrderTrib {
     }
 }
 
+/// Who the follow log `logs` says is being followed. Entries which are not
+/// a [FollowLog] are skipped.
+fn followees(logs: &[String]) -> HashSet<String> {
+    let mut res: HashSet<String> = HashSet::new();
+    for entry in logs
+        .iter()
+        .filter_map(|log| serde_json::from_str::<FollowLog>(log).ok())
+    {
+        if entry.follow {
+            res.insert(entry.name);
+        } else {
+            res.remove(&entry.name); // whether succeed or fail do not matter
+        }
+    }
+    res
+}
+
+impl FrontServer {
+    /// Takes a call to `what` out of the bucket `key`, if `limit` is set.
+    async fn limit(&self, limit: Option<RateLimit>, key: &str, what: &str) -> TribResult<()> {
+        match limit {
+            Some(limit) => {
+                let bin = self.bin_storage.bin(LIMITS_BIN).await?;
+                limit::take(&*bin, key, &limit, what).await
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Makes `who` follow or unfollow `whom`. The entry is only appended if
+    /// the [FOLLOW_VERSION] of the log is still the one read before the log
+    /// was, and every entry moves it on, so of two concurrent requests at
+    /// most one goes through on a given log.
+    async fn update_follow(&self, who: &str, whom: &str, follow: bool) -> TribResult<()> {
+        let bin = self.bin_storage.bin(who).await?;
+        for _ in 0..FOLLOW_RETRIES {
+            let version = bin.get(FOLLOW_VERSION).await?.unwrap_or_default();
+            let logs = bin.list_get("log").await?.0;
+            let following = followees(&logs);
+            match (follow, following.contains(whom)) {
+                (true, true) => {
+                    return Err(Box::new(TribblerError::AlreadyFollowing(
+                        who.to_string(),
+                        whom.to_string(),
+                    )))
+                }
+                (false, false) => {
+                    return Err(Box::new(TribblerError::NotFollowing(
+                        who.to_string(),
+                        whom.to_string(),
+                    )))
+                }
+                _ => {}
+            }
+            if follow && following.len() >= MAX_FOLLOWING {
+                return Err(Box::new(TribblerError::FollowingTooMany));
+            }
+
+            let id = bin.clock(0).await?;
+            let follow_log = serde_json::to_string(&FollowLog {
+                name: whom.to_string(),
+                follow,
+                id,
+            })
+            .unwrap();
+            let txn = Txn {
+                checks: vec![Precondition::Value {
+                    key: FOLLOW_VERSION.to_string(),
+                    value: version,
+                }],
+                ops: vec![
+                    TxnOp::Set(KeyValue::new(FOLLOW_VERSION, &id.to_string())),
+                    TxnOp::ListAppend(KeyValue {
+                        key: "log".to_string(),
+                        value: follow_log,
+                    }),
+                ],
+            };
+            if bin.transaction(&txn).await?.committed {
+                return Ok(());
+            }
+        }
+        Err(Box::new(TribblerError::Unknown(
+            "the follow log kept changing under concurrent requests".to_string(),
+        )))
+    }
+}
+
 #[async_trait]
 impl Server for FrontServer {
     async fn sign_up(&self, user: &str) -> TribResult<()> {
@@ -105,21 +206,29 @@ impl Server for FrontServer {
         if !is_valid_username(user) {
             return Err(Box::new(TribblerError::InvalidUsername(user.to_string())));
         }
+        self.limit(self.limits.sign_up, "sign-up", "sign_up")
+            .await?;
 
+        // a user listed from before names were claimed is taken as well
         let user_list = storage_client.list_get("Users").await?.0;
         if user_list.contains(&user.to_string()) {
             return Err(Box::new(TribblerError::UsernameTaken(user.to_string())));
         }
-
-        let flag = storage_client
-            .list_append(&KeyValue {
-                key: "Users".to_string(),
-                value: user.to_string(),
-            })
-            .await?;
-
-        if !flag {
-            return Err(Box::new(TribblerError::Unknown(user.to_string())));
+        // the name is claimed and listed in one transaction, so concurrent
+        // sign-ups of the same user race on a single key and at most one of
+        // them wins, and a claimed name is never left out of the list
+        let txn = Txn {
+            checks: vec![Precondition::Value {
+                key: format!("user::{}", user),
+                value: "".to_string(),
+            }],
+            ops: vec![
+                TxnOp::Set(KeyValue::new(&format!("user::{}", user), "1")),
+                TxnOp::ListAppend(KeyValue::new("Users", user)),
+            ],
+        };
+        if !storage_client.transaction(&txn).await?.committed {
+            return Err(Box::new(TribblerError::UsernameTaken(user.to_string())));
         }
         Ok(())
     }
@@ -149,14 +258,17 @@ impl Server for FrontServer {
         if !user_list.contains(&who.to_string()) {
             return Err(Box::new(TribblerError::UserDoesNotExist(who.to_string())));
         }
+        self.limit(self.limits.post, &format!("post::{}", who), "post")
+            .await?;
 
+        // the clock reading is past everything the poster has seen and
+        // close to the wall clock, so it also gives the posting time
+        let clock = self.bin_storage.bin(who).await?.clock(clock).await?;
         let post = serde_json::to_string(&Trib {
             user: who.to_string(),
             message: post.to_string(),
-            clock: self.bin_storage.bin(who).await?.clock(clock).await?,
-            time: SystemTime::now()
-                .duration_since(SystemTime::UNIX_EPOCH)?
-                .as_secs(),
+            clock,
+            time: Hlc::from_u64(clock).as_secs(),
         })
         .unwrap();
 
@@ -189,19 +301,28 @@ impl Server for FrontServer {
             return Err(Box::new(TribblerError::UserDoesNotExist(user.to_string())));
         }
 
-        let tribs = self.bin_storage.bin(user).await?.list_get("tribs").await?.0;
-        let ntrib = tribs.len();
-        let start = match ntrib.cmp(&MAX_TRIB_FETCH) {
-            Ordering::Greater => ntrib - MAX_TRIB_FETCH,
-            _ => 0,
-        };
+        // only the newest MAX_TRIB_FETCH tribs can make it into the answer
+        let tribs = self
+            .bin_storage
+            .bin(user)
+            .await?
+            .list_range("tribs", -(MAX_TRIB_FETCH as i64), -1)
+            .await?
+            .0;
+        // an entry which is not a trib is skipped rather than failing the
+        // whole timeline
         let mut res = tribs
-            .to_vec()
             .iter()
-            .map(|x| OrderTrib {
-                trib: Arc::new(serde_json::from_str::<Trib>(x).unwrap()).clone(),
+            .filter_map(|x| serde_json::from_str::<Trib>(x).ok())
+            .map(|trib| OrderTrib {
+                trib: Arc::new(trib),
             })
             .collect::<Vec<OrderTrib>>();
+        let ntrib = res.len();
+        let start = match ntrib.cmp(&MAX_TRIB_FETCH) {
+            Ordering::Greater => ntrib - MAX_TRIB_FETCH,
+            _ => 0,
+        };
         res.sort();
         let res0 = res[start..]
             .iter()
@@ -227,66 +348,9 @@ impl Server for FrontServer {
         if !user_list.contains(&whom.to_string()) {
             return Err(Box::new(TribblerError::UserDoesNotExist(whom.to_string())));
         }
-        let is_following = self.is_following(who, whom).await?;
-        if is_following {
-            return Err(Box::new(TribblerError::AlreadyFollowing(
-                who.to_string(),
-                whom.to_string(),
-            )));
-        }
-        let following = self.following(who).await?;
-        if following.len() == MAX_FOLLOWING {
-            return Err(Box::new(TribblerError::FollowingTooMany));
-        }
-
-        // follow
-        let id = self.bin_storage.bin(who).await?.clock(0).await?;
-        let follow_log = serde_json::to_string(&FollowLog {
-            name: whom.to_string(),
-            follow: true,
-            id: id,
-        })
-        .unwrap();
-        let flag = self
-            .bin_storage
-            .bin(who)
-            .await?
-            .list_append(&KeyValue {
-                key: "log".to_string(),
-                value: follow_log,
-            })
+        self.limit(self.limits.follow, &format!("follow::{}", who), "follow")
             .await?;
-        if !flag {
-            return Err(Box::new(TribblerError::Unknown(
-                "Follow failed due to list_append error".to_string(),
-            )));
-        }
-
-        // check if succeed
-        let logs = self.bin_storage.bin(who).await?.list_get("log").await?.0;
-        let mut already_follow = false;
-        for log in logs.iter() {
-            let entry = serde_json::from_str::<FollowLog>(log).unwrap();
-            if entry.name.eq(whom) {
-                if entry.id == id {
-                    if already_follow {
-                        return Err(Box::new(TribblerError::Unknown(
-                            "Follow failed due to multiple requests.".to_string(),
-                        )));
-                    } else {
-                        return Ok(());
-                    }
-                } else {
-                    if entry.follow {
-                        already_follow = true;
-                    } else {
-                        already_follow = false;
-                    }
-                }
-            }
-        }
-
-        return Ok(());
+        self.update_follow(who, whom, true).await
     }
 
     async fn unfollow(&self, who: &str, whom: &str) -> TribResult<()> {
@@ -306,63 +370,7 @@ impl Server for FrontServer {
         if !user_list.contains(&whom.to_string()) {
             return Err(Box::new(TribblerError::UserDoesNotExist(whom.to_string())));
         }
-        let is_following = self.is_following(who, whom).await?;
-        if !is_following {
-            return Err(Box::new(TribblerError::NotFollowing(
-                who.to_string(),
-                whom.to_string(),
-            )));
-        }
-
-        // unfollow
-        let id = self.bin_storage.bin(who).await?.clock(0).await?;
-        let follow_log = serde_json::to_string(&FollowLog {
-            name: whom.to_string(),
-            follow: false,
-            id: id,
-        })
-        .unwrap();
-        let flag = self
-            .bin_storage
-            .bin(who)
-            .await?
-            .list_append(&KeyValue {
-                key: "log".to_string(),
-                value: follow_log,
-            })
-            .await?;
-        if !flag {
-            return Err(Box::new(TribblerError::Unknown(
-                "Unollow failed due to list_append error".to_string(),
-            )));
-        }
-
-        // check if succeed
-        let logs = self.bin_storage.bin(who).await?.list_get("log").await?.0;
-        let mut already_follow = false;
-        for log in logs.iter() {
-            let entry = serde_json::from_str::<FollowLog>(log).unwrap();
-            if entry.name.eq(whom) {
-                if entry.id == id {
-                    if already_follow {
-                        // already_follow, then unfollow
-                        return Ok(());
-                    } else {
-                        // already unfollow, then fail
-                        return Err(Box::new(TribblerError::Unknown(
-                            "Unfollow failed due to multiple requests.".to_string(),
-                        )));
-                    }
-                } else {
-                    if entry.follow {
-                        already_follow = true;
-                    } else {
-                        already_follow = false;
-                    }
-                }
-            }
-        }
-        return Ok(());
+        self.update_follow(who, whom, false).await
     }
 
     async fn is_following(&self, who: &str, whom: &str) -> TribResult<bool> {
@@ -386,7 +394,10 @@ impl Server for FrontServer {
         let logs = self.bin_storage.bin(who).await?.list_get("log").await?.0;
         let mut res = false;
         for log in logs.iter().rev() {
-            let entry = serde_json::from_str::<FollowLog>(log).unwrap();
+            let entry = match serde_json::from_str::<FollowLog>(log) {
+                Ok(entry) => entry,
+                Err(_) => continue,
+            };
             if entry.name.eq(whom) {
                 res = entry.follow;
                 break;
@@ -407,17 +418,8 @@ impl Server for FrontServer {
             return Err(Box::new(TribblerError::UserDoesNotExist(who.to_string())));
         }
 
-        let mut res: HashSet<String> = HashSet::new();
         let logs = self.bin_storage.bin(who).await?.list_get("log").await?.0;
-        for log in logs.iter() {
-            let entry = serde_json::from_str::<FollowLog>(log).unwrap();
-            if entry.follow {
-                res.insert(entry.name);
-            } else {
-                res.remove(&entry.name); // whether succeed or fail do not matter
-            }
-        }
-        return Ok(res.iter().map(String::clone).collect());
+        return Ok(followees(&logs).iter().map(String::clone).collect());
     }
 
     async fn home(&self, user: &str) -> TribResult<Vec<Arc<Trib>>> {
@@ -432,13 +434,19 @@ impl Server for FrontServer {
             return Err(Box::new(TribblerError::UserDoesNotExist(user.to_string())));
         }
 
-        let following_list = self.following(user).await?;
-        let mut all_tribs: Vec<Arc<Trib>> = Vec::new();
-        for name in following_list {
-            let mut each_tribs = self.tribs(&name).await?;
-            all_tribs.append(&mut each_tribs);
-        }
-        all_tribs.append(&mut self.tribs(user).await?);
+        // fetch the newest tribs of every timeline in one batch instead of
+        // one call per followee
+        let mut names = self.following(user).await?;
+        names.push(user.to_string());
+        let all_tribs = self
+            .bin_storage
+            .multi_bin_list_range(&names, "tribs", -(MAX_TRIB_FETCH as i64), -1)
+            .await?
+            .into_iter()
+            .flat_map(|list| list.0)
+            .filter_map(|x| serde_json::from_str::<Trib>(&x).ok())
+            .map(Arc::new)
+            .collect::<Vec<Arc<Trib>>>();
         let ntrib = all_tribs.len();
         let start = match ntrib.cmp(&MAX_TRIB_FETCH) {
             Ordering::Greater => ntrib - MAX_TRIB_FETCH,
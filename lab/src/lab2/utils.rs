@@ -1,11 +1,28 @@
-use std::collections::hash_map::DefaultHasher;
-use std::hash::Hasher;
+use std::collections::HashMap;
 use std::time::Duration;
 
+use super::ring::{fnv1a, HashRing};
+use crate::lab1::pool::ChannelPool;
 use serde::{Deserialize, Serialize};
-use tribbler::colon::unescape;
-use tribbler::rpc::{Key, KeyValue, Pattern};
-use tribbler::{err::TribResult, rpc::trib_storage_client::TribStorageClient};
+use tokio::sync::mpsc::{self, Sender};
+use tokio::task::JoinHandle;
+use tokio::time;
+use tokio_stream::wrappers::ReceiverStream;
+use tribbler::colon::BinKey;
+use tribbler::config::ReplicationConfig;
+use tribbler::err::TribResult;
+use tribbler::rpc::{Key, KeyValue, Pattern, Record};
+
+/// How many records may be in flight between the scan of the source backend
+/// and the ingest on the destination during a migration.
+const MIGRATION_BUFFER: usize = 256;
+
+/// The raw backend key under which the leader keeper publishes the current
+/// [MembershipEpoch].
+pub const STATUS_KEY: &str = "BackendStatus";
+
+/// how long a backend may take to hand out the published [MembershipEpoch]
+const STATUS_TIMEOUT: Duration = Duration::from_secs(1);
 
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct StatusTableEntry {
@@ -13,193 +30,231 @@ pub struct StatusTableEntry {
     pub status: bool,
 }
 
-#[allow(unused_variables)]
-pub async fn write_twice(
+/// A backend status table as published by the leader keeper. The epoch goes
+/// up by one every time the keeper finishes migrating data for a backend that
+/// joined or left, so a larger epoch is always the more recent view.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct MembershipEpoch {
+    pub epoch: u64,
+    pub table: Vec<StatusTableEntry>,
+}
+
+/// Index of the first backend that may hold the published epoch. The
+/// [MembershipEpoch] is stored on the first live backends from here on.
+pub(crate) fn status_home(backs: usize) -> usize {
+    (fnv1a(STATUS_KEY.as_bytes()) % backs as u64) as usize
+}
+
+/// Publishes `epoch` on the first `replicas` live backends of its own table.
+pub async fn publish_epoch(
+    epoch: &MembershipEpoch,
+    replicas: usize,
+    pool: &ChannelPool,
+) -> TribResult<()> {
+    let message = serde_json::to_string(epoch)?;
+    let home = status_home(epoch.table.len());
+    write_replicas(message, home, &epoch.table, replicas, pool).await
+}
+
+/// Reads the newest [MembershipEpoch] stored on the first `replicas`
+/// reachable backends from [status_home]. Returns [None] when no keeper has
+/// published one yet.
+pub async fn read_epoch(
+    backs: &[String],
+    replicas: usize,
+    pool: &ChannelPool,
+) -> Option<MembershipEpoch> {
+    if backs.is_empty() {
+        return None;
+    }
+    let home = status_home(backs.len());
+    let mut newest: Option<MembershipEpoch> = None;
+    let mut answered = 0;
+    for i in 0..backs.len() {
+        if answered == replicas {
+            break;
+        }
+        let addr = format!("http://{}", backs[(home + i) % backs.len()]);
+        let fetch = async {
+            let mut client = pool.client(&addr).await?;
+            let r = client
+                .get(Key {
+                    key: STATUS_KEY.to_string(),
+                })
+                .await;
+            TribResult::Ok(r)
+        };
+        let value = match time::timeout(STATUS_TIMEOUT, fetch).await {
+            Ok(Ok(Ok(v))) => v.into_inner().value,
+            _ => {
+                pool.invalidate(&addr);
+                continue;
+            }
+        };
+        answered += 1;
+        // reachable, but nothing published here
+        let value = match value {
+            Some(value) => value,
+            None => continue,
+        };
+        if let Ok(epoch) = serde_json::from_str::<MembershipEpoch>(&value) {
+            if newest.as_ref().map(|n| n.epoch) < Some(epoch.epoch) {
+                newest = Some(epoch);
+            }
+        }
+    }
+    newest
+}
+
+/// Writes the backend status table to the first `replicas` live backends
+/// starting at index `backend`.
+pub async fn write_replicas(
     message: String,
     backend: usize,
-    status_table: &Vec<StatusTableEntry>,
+    status_table: &[StatusTableEntry],
+    replicas: usize,
+    pool: &ChannelPool,
 ) -> TribResult<()> {
     let mut index = backend;
-
-    // write  into promary
-    while !status_table[index].status {
+    let mut written = 0;
+    for _ in 0..status_table.len() {
+        if written == replicas {
+            break;
+        }
+        if status_table[index].status {
+            let mut client = pool
+                .client(&format!("http://{}", status_table[index].addr))
+                .await?;
+            client
+                .set(KeyValue {
+                    key: STATUS_KEY.to_string(),
+                    value: message.to_string(),
+                })
+                .await?;
+            written += 1;
+        }
         index = (index + 1) % status_table.len();
     }
-    let mut addr_http = "http://".to_string();
-    addr_http.push_str(&status_table[index].addr.clone());
-    let mut client = TribStorageClient::connect(addr_http.to_string()).await?;
-
-    client
-        .set(KeyValue {
-            key: "BackendStatus".to_string(),
-            value: message.to_string(),
-        })
-        .await?;
+    Ok(())
+}
 
-    // write into replica
-    index = (index + 1) % status_table.len();
-    while !status_table[index].status {
-        index = (index + 1) % status_table.len();
+/// Builds the ring of `vnodes` points per backend over the backends that
+/// `table` marks as alive. Both the bin client and the keeper route through
+/// this, so given the same [ReplicationConfig] they always agree on where a
+/// bin lives.
+pub(crate) fn live_ring(table: &[StatusTableEntry], vnodes: usize) -> HashRing {
+    let mut ring = HashRing::new(vnodes);
+    for (i, entry) in table.iter().enumerate() {
+        if entry.status {
+            ring.add(i, &entry.addr);
+        }
     }
-    let mut replica_addr_http = "http://".to_string();
-    replica_addr_http.push_str(&status_table[index].addr.clone());
-    let mut replica_client = TribStorageClient::connect(replica_addr_http.to_string()).await?;
-
-    replica_client
-        .set(KeyValue {
-            key: "BackendStatus".to_string(),
-            value: message.to_string(),
-        })
-        .await?;
+    ring
+}
 
+/// Copies every bin whose replica set differs between the `old` and `new`
+/// backend tables onto the backends that just became responsible for it.
+///
+/// Each such bin is sent by exactly one backend: the first of its old
+/// replicas that is still alive in `new`.
+pub async fn migrate(
+    old: &[StatusTableEntry],
+    new: &[StatusTableEntry],
+    replication: &ReplicationConfig,
+    pool: &ChannelPool,
+) -> TribResult<()> {
+    let old_ring = live_ring(old, replication.vnodes);
+    let new_ring = live_ring(new, replication.vnodes);
+    let replicas = replication.factor;
+    for src in 0..new.len() {
+        if old[src].status && new[src].status {
+            migrate_from(src, &old_ring, &new_ring, new, replicas, pool).await?;
+        }
+    }
     Ok(())
 }
 
-// Migrate data from src node to dest node
-// Initial order: start -> dest -> src
-// Range: (start, dest] or (start, src]
-// TODO: error catching?
-#[allow(unused_variables)]
-pub async fn data_migration(
-    start: usize,
-    dst: usize,
+// Streams a single scan of src out to one ingest per destination backend.
+async fn migrate_from(
     src: usize,
-    leave: bool,
-    status_table: &Vec<StatusTableEntry>,
+    old_ring: &HashRing,
+    new_ring: &HashRing,
+    new: &[StatusTableEntry],
+    replicas: usize,
+    pool: &ChannelPool,
 ) -> TribResult<()> {
-    // println!("DATA MIGRATION INFORMATION");
-    // println!("start {}, dst {}, src {}, leave {}", start, dst, src, leave);
-    // connect to dest and src
-    let mut addr_http = "http://".to_string();
-    addr_http.push_str(&status_table[dst].addr);
-    let mut d = TribStorageClient::connect(addr_http).await?;
-    let mut addr_http0 = "http://".to_string();
-    addr_http0.push_str(&status_table[src].addr);
-    let mut s = TribStorageClient::connect(addr_http0).await?;
-
-    // Key-value pair
-    let all_keys = s
-        .keys(Pattern {
+    let mut s = pool.client(&format!("http://{}", new[src].addr)).await?;
+    let mut records = s
+        .scan(Pattern {
             prefix: "".to_string(),
             suffix: "".to_string(),
         })
         .await?
-        .into_inner()
-        .list;
-    for each_key in all_keys {
-        let tmp: Vec<String> = each_key.split("::").map(|x| x.to_string()).collect();
-        let unescape_key = unescape(tmp.get(0).unwrap()).to_string();
-        // check if should copy this one
-        let mut hasher = DefaultHasher::new();
-        hasher.write(unescape_key.as_bytes());
-        let h = hasher.finish() as usize % status_table.len();
-        // println!("hash: {}, key: {}", h, each_key);
-        if (!leave && ((h <= dst && h > start) || (start > dst && (h > start || h <= dst))))
-            || (leave && ((h <= src && h > start) || (start > src && (h > start || h <= src))))
-        {
-            d.set(KeyValue {
-                key: each_key.to_string(),
-                value: s.get(Key { key: each_key }).await?.into_inner().value,
-            })
-            .await?;
+        .into_inner();
+
+    let mut ingests = HashMap::new();
+    while let Some(record) = records.message().await? {
+        // the published epoch has its own placement, and copying an old one
+        // over a newer one would roll clients back
+        if record.key == STATUS_KEY {
+            continue;
         }
-    }
-    // Key-List
-    let all_list_keys = s
-        .list_keys(Pattern {
-            prefix: "".to_string(),
-            suffix: "".to_string(),
-        })
-        .await?
-        .into_inner()
-        .list;
-    for each_key in all_list_keys {
-        let tmp: Vec<String> = each_key.split("::").map(|x| x.to_string()).collect();
-        let unescape_key = unescape(tmp.get(0).unwrap()).to_string();
-        let mut hasher = DefaultHasher::new();
-        hasher.write(unescape_key.as_bytes());
-        let h = hasher.finish() as usize % status_table.len();
-        if (!leave && ((h <= dst && h > start) || (start > dst && (h > start || h <= dst))))
-            || (leave && ((h <= src && h > start) || (start > src && (h > start || h <= src))))
-        {
-            // remove old entries (no need to do it now)
-            // let old_records = d
-            //     .list_get(Key {
-            //         key: each_key.to_string(),
-            //     })
-            //     .await?
-            //     .into_inner()
-            //     .list;
-            // for entry in old_records {
-            //     d.list_remove(KeyValue {
-            //         key: each_key.to_string(),
-            //         value: entry,
-            //     })
-            //     .await?;
-            // }
-            // append new records
-            let records = s
-                .list_get(Key {
-                    key: each_key.to_string(),
-                })
-                .await?
-                .into_inner()
-                .list;
-            for entry in records {
-                d.list_append(KeyValue {
-                    key: each_key.to_string(),
-                    value: entry,
-                })
-                .await?;
+        // keys no bin client wrote stay where they are
+        let name = match BinKey::decode(&record.key) {
+            Some(k) => k.bin,
+            None => continue,
+        };
+        let old_set = old_ring.successors(&name, replicas);
+        if old_set.iter().find(|&&b| new[b].status) != Some(&src) {
+            continue;
+        }
+        for dst in new_ring.successors(&name, replicas) {
+            if old_set.contains(&dst) {
+                continue;
             }
+            let (tx, _) = ingests
+                .entry(dst)
+                .or_insert_with(|| spawn_ingest(format!("http://{}", new[dst].addr), pool.clone()));
+            // a failed ingest drops its receiver; the error is reported when
+            // its handle is joined below
+            let _ = tx.send(record.clone()).await;
         }
     }
+    for (_, (tx, handle)) in ingests {
+        drop(tx);
+        handle.await??;
+    }
     Ok(())
 }
 
-#[allow(unused_variables)]
-pub async fn node_leave(curr: usize, status_table: &Vec<StatusTableEntry>) -> TribResult<()> {
-    // find successor and the second previous node
-    let len = status_table.len();
-    let mut prev = (curr + len - 1) % len;
-    let mut next = (curr + 1) % len;
-    while !status_table[next].status {
-        next = (next + 1) % len;
-    }
-    while !status_table[prev].status {
-        prev = (prev + len - 1) % len;
-    }
-    let mut prev_prev = (prev + len - 1) % len;
-    let mut next_next = (next + 1) % len;
-    while !status_table[prev_prev].status {
-        prev_prev = (prev_prev + len - 1) % len;
-    }
-    while !status_table[next_next].status {
-        next_next = (next_next + 1) % len;
-    }
-
-    let _ = data_migration(prev, next_next, next, true, status_table).await?;
-    let _ = data_migration(prev_prev, next, prev, true, status_table).await?;
-    Ok(())
+fn spawn_ingest(addr: String, pool: ChannelPool) -> (Sender<Record>, JoinHandle<TribResult<()>>) {
+    let (tx, rx) = mpsc::channel(MIGRATION_BUFFER);
+    let handle = tokio::spawn(async move {
+        let mut d = pool.client(&addr).await?;
+        d.ingest(ReceiverStream::new(rx)).await?;
+        Ok(())
+    });
+    (tx, handle)
 }
 
-#[allow(unused_variables)]
-pub async fn node_join(curr: usize, status_table: &Vec<StatusTableEntry>) -> TribResult<()> {
-    // find successor and prodecessor's predecessor
-    let len = status_table.len();
-    let mut prev = (curr + len - 1) % len;
-    let mut next = (curr + 1) % len;
-    while !status_table[next].status {
-        next = (next + 1) % len;
-    }
-    while !status_table[prev].status {
-        prev = (prev + len - 1) % len;
-    }
-    prev = (prev + len - 1) % len;
-    while !status_table[prev].status {
-        prev = (prev + len - 1) % len;
-    }
+pub async fn node_leave(
+    curr: usize,
+    status_table: &[StatusTableEntry],
+    replication: &ReplicationConfig,
+    pool: &ChannelPool,
+) -> TribResult<()> {
+    let mut new = status_table.to_vec();
+    new[curr].status = false;
+    migrate(status_table, &new, replication, pool).await
+}
 
-    // data migration from succ to curr, copy data range (prev, curr]
-    return data_migration(prev, curr, next, false, status_table).await;
+pub async fn node_join(
+    curr: usize,
+    status_table: &[StatusTableEntry],
+    replication: &ReplicationConfig,
+    pool: &ChannelPool,
+) -> TribResult<()> {
+    let mut new = status_table.to_vec();
+    new[curr].status = true;
+    migrate(status_table, &new, replication, pool).await
 }
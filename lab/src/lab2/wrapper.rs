@@ -1,25 +1,42 @@
 use crate::lab1::client::StorageClient;
+use crate::lab1::pool::ChannelPool;
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::collections::{BTreeSet, HashSet};
+use std::error::Error;
 use std::iter::FromIterator;
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::Duration;
 use std::{cmp::min, cmp::Ordering};
-use tokio::sync::Mutex;
 use tokio::time::error::Elapsed;
-use tribbler::colon::{escape, unescape};
-use tribbler::err::TribResult;
-use tribbler::storage::{KeyList, KeyString, KeyValue, List, Pattern, Storage};
+use tribbler::colon::{escape, BinKey};
+use tribbler::config::ReplicationConfig;
+use tribbler::err::{TribResult, TribblerError};
+use tribbler::metrics;
+use tribbler::storage::{
+    KeyList, KeyPage, KeyString, KeyValue, List, Pattern, Precondition, Storage, Txn, TxnOp,
+    TxnResult,
+};
 
-use super::client::{hash_name_ip, scan_server};
-use super::utils::StatusTableEntry;
+use super::membership::Membership;
 
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct LogEntry {
     message: String,
     clock: u64,
 }
+
+impl LogEntry {
+    /// Reads a stored entry. One which is not a [LogEntry], as appended
+    /// before entries were stamped, counts as older than every stamped one.
+    fn decode(raw: &str) -> LogEntry {
+        serde_json::from_str(raw).unwrap_or_else(|_| LogEntry {
+            message: raw.to_string(),
+            clock: 0,
+        })
+    }
+}
+
 struct OrderLogEntry {
     logentry: Arc<LogEntry>,
 }
@@ -60,334 +77,805 @@ impl PartialEq for OrderLogEntry {
             && self.logentry.message == other.logentry.message
     }
 }
+/// Merges the raw [LogEntry] lists read from the replicas of a bin into the
+/// list of messages the caller sees, ordered by clock.
+pub fn merge_log_lists(replicas: Vec<Vec<String>>) -> List {
+    // the replica that has seen the most entries is the most up to date one
+    let all_res_set =
+        replicas
+            .into_iter()
+            .map(HashSet::<String>::from_iter)
+            .fold(HashSet::new(), |best, set| {
+                if set.len() > best.len() {
+                    set
+                } else {
+                    best
+                }
+            });
+    let mut res = all_res_set
+        .iter()
+        .map(|x| OrderLogEntry {
+            logentry: Arc::new(LogEntry::decode(x)),
+        })
+        .collect::<Vec<OrderLogEntry>>();
+    res.sort();
+    let res0 = res
+        .iter()
+        .map(|x| x.logentry.message.clone())
+        .collect::<Vec<String>>();
+    List(res0)
+}
+
+/// The range every replica is asked for when a caller wants `start..=end`:
+/// the first `end + 1` or the last `-start` entries, or the whole list when
+/// the two indices count from different ends.
+pub(crate) fn replica_range(start: i64, end: i64) -> (i64, i64) {
+    match (start >= 0, end >= 0) {
+        (true, true) => (0, end),
+        (false, false) => (start, -1),
+        _ => (0, -1),
+    }
+}
+
+/// Merges the [LogEntry] ranges the replicas of a bin returned for
+/// [replica_range]`(start, end)`. An entry missing from one replica only
+/// pushes that replica's range further along, so every entry of the real
+/// range is in at least one answer: the answers are combined, ordered by
+/// clock, and cut down to `start..=end`.
+pub fn merge_log_ranges(replicas: Vec<Vec<String>>, start: i64, end: i64) -> List {
+    let all = replicas.into_iter().flatten().collect::<HashSet<String>>();
+    let mut res = all
+        .iter()
+        .map(|x| OrderLogEntry {
+            logentry: Arc::new(LogEntry::decode(x)),
+        })
+        .collect::<Vec<OrderLogEntry>>();
+    res.sort();
+    List(
+        res.iter()
+            .map(|x| x.logentry.message.clone())
+            .collect::<Vec<String>>(),
+    )
+    .range(start, end)
+}
+
+/// A value as the replicas store it: stamped with the clock of the write
+/// that set it, so that the newest copy can be told apart from a stale one.
+/// A deleted key keeps its stamp, with an empty value.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Versioned {
+    clock: u64,
+    value: String,
+}
+
+impl Versioned {
+    /// Reads a stored copy. A value stored before values were stamped
+    /// counts as older than every stamped one.
+    fn decode(raw: &str) -> Versioned {
+        serde_json::from_str(raw).unwrap_or_else(|_| Versioned {
+            clock: 0,
+            value: raw.to_string(),
+        })
+    }
+
+    fn encode(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+}
+
+/// The newest of the stored copies of one key, by clock and then by value.
+fn newest(copies: impl Iterator<Item = Option<String>>) -> Option<String> {
+    copies.flatten().max_by_key(|raw| Versioned::decode(raw))
+}
+
+/// What the caller sees of a stored copy: a deleted key is not set.
+fn value_of(raw: Option<String>) -> Option<String> {
+    raw.map(|raw| Versioned::decode(&raw).value)
+        .filter(|v| !v.is_empty())
+}
+
+/// Whether `e` only says that a replica could not be reached, so that
+/// another one may be asked instead.
+fn unreachable(e: &(dyn Error + Send + Sync + 'static)) -> bool {
+    match e.downcast_ref::<TribblerError>() {
+        Some(TribblerError::Unavailable(_)) | Some(TribblerError::Timeout(_)) => true,
+        Some(_) => false,
+        // the request never got as far as the replica
+        None => true,
+    }
+}
+
+/// How many times a transaction is tried again after its deciding replica
+/// turned out to hold an older copy of a checked key than another replica.
+const TXN_ATTEMPTS: usize = 3;
+
+/// Keeps the answers of the replicas that succeeded, provided there are at
+/// least `quorum` of them; otherwise returns the last error seen. A bin placed
+/// on fewer backends than the replication factor (because fewer are alive)
+/// only needs all of them to answer. Fails outright when the quorums of
+/// `replication` do not [overlap](ReplicationConfig::check).
+pub(crate) fn check_quorum<T>(
+    results: Vec<TribResult<T>>,
+    replication: &ReplicationConfig,
+    quorum: usize,
+) -> TribResult<Vec<T>> {
+    replication.check()?;
+    let needed = quorum.min(results.len());
+    let mut answers = Vec::with_capacity(results.len());
+    let mut last_err = None;
+    for r in results {
+        match r {
+            Ok(v) => answers.push(v),
+            Err(e) => {
+                log::warn!("replica failed, the table may be out of date: {:?}", e);
+                last_err = Some(e);
+            }
+        }
+    }
+    if answers.len() >= needed.max(1) {
+        return Ok(answers);
+    }
+    Err(last_err.unwrap_or_else(|| {
+        Box::new(TribblerError::Unavailable(
+            "no replica available".to_string(),
+        ))
+    }))
+}
+
+/// Turns physical keys back into the keys of the bin and returns the
+/// largest set among the replicas.
+fn merge_keys(replicas: Vec<Vec<String>>) -> List {
+    let all_keys_set =
+        replicas
+            .into_iter()
+            .map(HashSet::<String>::from_iter)
+            .fold(HashSet::new(), |best, set| {
+                if set.len() > best.len() {
+                    set
+                } else {
+                    best
+                }
+            });
+    List(all_keys_set.iter().filter_map(|k| bin_key(k)).collect())
+}
+
+/// The key a caller of the bin used for the physical key `key`.
+fn bin_key(key: &str) -> Option<String> {
+    BinKey::decode(key).map(|k| k.key)
+}
+
+/// Merges the pages several replicas returned for the same request into one
+/// page of at most `limit` keys of the bin.
+fn merge_key_pages(replicas: Vec<KeyPage>, limit: usize) -> KeyPage {
+    let more = replicas.iter().any(|p| p.next.is_some());
+    let keys = replicas
+        .into_iter()
+        .flat_map(|p| p.keys.0)
+        .collect::<BTreeSet<String>>();
+    let mut page = KeyPage::from_sorted(keys.into_iter(), limit);
+    if more && page.next.is_none() {
+        page.next = page.keys.0.last().cloned();
+    }
+    KeyPage {
+        keys: List(page.keys.0.iter().filter_map(|k| bin_key(k)).collect()),
+        next: page.next.and_then(|k| bin_key(&k)),
+    }
+}
+
 pub struct StorageClientWrapper {
-    pub backs: Vec<String>,
-    pub status_table: Arc<Mutex<Vec<StatusTableEntry>>>,
+    pub pool: ChannelPool,
+    pub replication: ReplicationConfig,
+    pub membership: Membership,
     pub name: String,
-    // pub storage_client_primary: StorageClient,
-    // pub storage_client_backup: StorageClient,
-    pub timestamp: Arc<Mutex<Instant>>,
-    pub addr_primary: Arc<Mutex<String>>,
-    pub addr_backup: Arc<Mutex<String>>,
 }
 impl StorageClientWrapper {
-    pub async fn update_table(&self) -> (StorageClient, StorageClient) {
-        // let time = Instant::now();
-        let mut e = self.timestamp.lock().await;
-        let elapsed = e.elapsed().as_secs();
-        if elapsed >= 3 {
-            let table = scan_server(self.backs.clone()).await;
-            *e = Instant::now();
-            let (tmp_addr_primary, tmp_addr_backup) = hash_name_ip(&self.name, table.clone()).await;
-            let mut status_table_lock = self.status_table.lock().await;
-            *status_table_lock = table;
-            drop(status_table_lock);
-            let mut addr_primary_lock = self.addr_primary.lock().await;
-            *addr_primary_lock = tmp_addr_primary;
-            drop(addr_primary_lock);
-            let mut addr_backup_lock = self.addr_backup.lock().await;
-            *addr_backup_lock = tmp_addr_backup;
-            drop(addr_backup_lock);
+    /// Clients for the backends currently holding this bin, primary first.
+    pub async fn update_table(&self) -> Vec<StorageClient> {
+        self.membership
+            .replica_addrs(&self.name, self.replication.factor)
+            .await
+            .iter()
+            .map(|addr| StorageClient::new(addr, self.pool.clone()))
+            .collect()
+    }
+
+    /// [check_quorum], plus asking the membership view for a refresh when
+    /// some replica failed.
+    fn check<T>(&self, results: Vec<TribResult<T>>, quorum: usize) -> TribResult<Vec<T>> {
+        let failed = results.iter().filter(|r| r.is_err()).count();
+        if failed > 0 {
+            metrics::REPLICA_FAILURES.inc_by(failed as u64);
+            self.membership.report_failure();
         }
-        drop(e);
-        let addr_primary_lock = &self.addr_primary.lock().await;
-        let storage_client_primary = StorageClient {
-            addr: (*addr_primary_lock).to_string(),
-        };
-        drop(addr_primary_lock);
-        let addr_backup_lock = self.addr_backup.lock().await;
-        let storage_client_backup = StorageClient {
-            addr: (*addr_backup_lock).to_string(),
+        check_quorum(results, &self.replication, quorum)
+    }
+
+    /// The newest copy, as stored, of every key in `key_names` among the
+    /// replicas that answer.
+    async fn newest_copies(
+        &self,
+        clients: &[StorageClient],
+        key_names: &[String],
+    ) -> TribResult<Vec<Option<String>>> {
+        let mut results = Vec::with_capacity(clients.len());
+        for c in clients.iter() {
+            results.push(c.multi_get(key_names).await);
+        }
+        let answers = self.check(results, self.replication.read_quorum)?;
+        let copies = |i: usize| {
+            answers
+                .iter()
+                .map(move |values| values.get(i).cloned().flatten())
         };
-        drop(addr_backup_lock);
-        (storage_client_primary, storage_client_backup)
+        Ok((0..key_names.len()).map(|i| newest(copies(i))).collect())
     }
-}
-#[async_trait]
-impl KeyString for StorageClientWrapper {
-    // todo: catch err and update table=> no need to do so, as long as migrate within 10s, the data is safe
-    async fn get(&self, key: &str) -> TribResult<Option<String>> {
-        // todo: get value twice and compare??return the newest one!
-        // if backend crash, just return None instead of error
-        let (storage_client_primary, storage_client_backup) = self.update_table().await;
-        let mut key_name = escape(self.name.clone()).to_string();
-        key_name.push_str(&"::".to_string());
-        key_name.push_str(&escape(key));
-        let res1 = match storage_client_primary.get(&key_name).await {
-            Ok(v) => v,
-            Err(e) => {
-                println!("the primary backend dies in get function, because delayed update of table, may cause data loss!{:?}",e);
-                return storage_client_backup.get(&key_name).await;
+
+    /// Stores `value` on every replica, stamped past the clock of each.
+    async fn put(&self, key: &str, value: &str, ttl: Option<Duration>) -> TribResult<bool> {
+        let clients = self.update_table().await;
+        let clock = self.max_clock(&clients).await?;
+        let entry = KeyValue {
+            key: self.key_name(key),
+            value: Versioned {
+                clock,
+                value: value.to_string(),
             }
+            .encode(),
         };
-        let res2 = match storage_client_backup.get(&key_name).await {
-            Ok(v) => v,
-            Err(e) => {
-                println!("the backup backend dies in get function, because delayed update of table, may cause data loss!{:?}",e);
-                return Ok(res1);
+        let mut results = Vec::with_capacity(clients.len());
+        for c in clients.iter() {
+            results.push(match ttl {
+                Some(ttl) => c.set_with_ttl(&entry, ttl).await,
+                None => c.set(&entry).await,
+            });
+        }
+        let answers = self.check(results, self.replication.write_quorum)?;
+        Ok(answers.into_iter().any(|x| x))
+    }
+
+    /// `keys` without the ones that were deleted, which the replicas still
+    /// hold as stamped empty values.
+    async fn live_keys(&self, keys: Vec<String>) -> TribResult<Vec<String>> {
+        let values = self.multi_get(&keys).await?;
+        Ok(keys
+            .into_iter()
+            .zip(values)
+            .filter(|(_, v)| v.is_some())
+            .map(|(k, _)| k)
+            .collect())
+    }
+
+    /// Runs `txn` on the first replica that can be reached, which decides
+    /// whether it commits. Returns the index of that replica and its result.
+    async fn decide(&self, clients: &[StorageClient], txn: &Txn) -> TribResult<(usize, TxnResult)> {
+        let mut last_err = None;
+        for (i, c) in clients.iter().enumerate() {
+            match c.transaction(txn).await {
+                Ok(r) => return Ok((i, r)),
+                Err(e) if unreachable(&*e) => {
+                    log::warn!(
+                        "replica failed in transaction, the table may be out of date: {:?}",
+                        e
+                    );
+                    metrics::REPLICA_FAILURES.inc();
+                    self.membership.report_failure();
+                    last_err = Some(e);
+                }
+                Err(e) => return Err(e),
             }
-        };
-        let time1 = storage_client_primary.clock(0).await?;
-        let time2 = storage_client_backup.clock(0).await?;
-        if time1 > time2 {
-            Ok(res1)
-        } else {
-            Ok(res2)
         }
+        Err(last_err.unwrap_or_else(|| {
+            Box::new(TribblerError::Unavailable(
+                "no replica available".to_string(),
+            ))
+        }))
     }
 
-    async fn set(&self, kv: &KeyValue) -> TribResult<bool> {
-        // todo: set value twice and compare?? deal with error??
-        let (storage_client_primary, storage_client_backup) = self.update_table().await;
-
-        let mut key_name = escape(self.name.clone()).to_string();
-        key_name.push_str(&"::".to_string());
-        key_name.push_str(&escape(kv.key.clone()));
-        let res1 = match storage_client_primary
-            .set(&KeyValue {
-                key: key_name.to_string(),
-                value: kv.value.to_string(),
-            })
-            .await
-        {
-            Ok(v) => v,
-            Err(e) => {
-                println!("the primary backend dies in get function, because delayed update of table, may cause data loss!{:?}",e);
-                return storage_client_backup
-                    .set(&KeyValue {
-                        key: key_name.to_string(),
-                        value: kv.value.to_string(),
-                    })
-                    .await;
+    /// Brings the copies `replica` holds of the keys `checks` look at by
+    /// value up to the values checked for, where its own are older. Returns
+    /// whether any of them was behind.
+    async fn catch_up(&self, replica: &StorageClient, checks: &[Precondition]) -> TribResult<bool> {
+        let mut behind = false;
+        for check in checks {
+            if let Precondition::Value { key, value } = check {
+                let current = replica.get(key).await?.unwrap_or_default();
+                if Versioned::decode(&current) < Versioned::decode(value) {
+                    replica.cas(key, Some(current), value).await?;
+                    behind = true;
+                }
             }
+        }
+        Ok(behind)
+    }
+
+    fn key_name(&self, key: &str) -> String {
+        BinKey::new(&self.name, key).encode()
+    }
+
+    fn pattern(&self, p: &Pattern) -> Pattern {
+        Pattern {
+            prefix: self.key_name(&p.prefix),
+            suffix: escape(&p.suffix),
+        }
+    }
+
+    /// The physical pattern and cursor of a page request. Pages follow the
+    /// order of the physical (escaped) keys, which is not always the order of
+    /// the keys themselves, but is the same from one page to the next.
+    fn page_bounds(&self, p: &Pattern, start_after: &str) -> (Pattern, String) {
+        let start = match start_after {
+            "" => String::new(),
+            key => self.key_name(key),
         };
-        let res2 = match storage_client_backup
-            .set(&KeyValue {
-                key: key_name.to_string(),
-                value: kv.value.to_string(),
-            })
-            .await
-        {
-            Ok(v) => v,
-            Err(e) => {
-                println!("the backup backend dies in get function, because delayed update of table, may cause data loss!{:?}",e);
-                return Ok(res1);
-            }
+        (self.pattern(p), start)
+    }
+
+    /// The largest clock among the replicas that answer, which every new
+    /// log entry must be stamped past.
+    async fn max_clock(&self, clients: &[StorageClient]) -> TribResult<u64> {
+        let mut results = Vec::with_capacity(clients.len());
+        for c in clients {
+            results.push(c.clock(0).await);
+        }
+        let clocks = self.check(results, self.replication.write_quorum)?;
+        Ok(clocks.into_iter().max().unwrap_or(0))
+    }
+
+    /// Appends `kv` as a clocked log entry to every replica, giving the list
+    /// a new deadline when `ttl` is set.
+    async fn append(&self, kv: &KeyValue, ttl: Option<Duration>) -> TribResult<bool> {
+        let clients = self.update_table().await;
+        let c = self.max_clock(&clients).await?;
+        let key_name = self.key_name(&kv.key);
+        let log_entry = serde_json::to_string(&LogEntry {
+            message: kv.value.to_string(),
+            clock: c,
+        })
+        .unwrap();
+        let entry = KeyValue {
+            key: key_name,
+            value: log_entry,
         };
-        if res1 || res2 {
-            // at least one replica??
-            Ok(true)
-        } else {
-            Ok(false)
+
+        let mut results = Vec::with_capacity(clients.len());
+        for client in clients.iter() {
+            results.push(match ttl {
+                Some(ttl) => client.list_append_with_ttl(&entry, ttl).await,
+                None => client.list_append(&entry).await,
+            });
         }
+        let answers = self.check(results, self.replication.write_quorum)?;
+        Ok(answers.into_iter().any(|x| x))
     }
 
-    async fn keys(&self, p: &Pattern) -> TribResult<List> {
-        // todo: get value twice and merge!!!
-        let (storage_client_primary, storage_client_backup) = self.update_table().await;
-
-        let mut prefix = escape(self.name.to_string());
-        prefix.push_str(&"::".to_string());
-        prefix.push_str(&escape(p.prefix.to_string()));
-        let suffix = escape(p.suffix.to_string());
-        let all_keys_primary = storage_client_primary
-            .keys(&Pattern {
-                prefix: prefix.to_string(),
-                suffix: suffix.to_string(),
+    /// `txn` as it is run on the replicas: keys become physical keys, and
+    /// set and appended values become [Versioned] values and [LogEntry]s,
+    /// just as in the single calls. The values checked for are left as the
+    /// caller gave them.
+    async fn physical_txn(&self, txn: &Txn, clients: &[StorageClient]) -> TribResult<Txn> {
+        let writes = |op: &TxnOp| matches!(op, TxnOp::Set(_) | TxnOp::ListAppend(_));
+        let clock = match txn.ops.iter().any(writes) {
+            true => self.max_clock(clients).await?,
+            false => 0,
+        };
+        let kv = |kv: &KeyValue| KeyValue {
+            key: self.key_name(&kv.key),
+            value: kv.value.to_string(),
+        };
+        let checks = txn
+            .checks
+            .iter()
+            .map(|c| match c {
+                Precondition::Value { key, value } => Precondition::Value {
+                    key: self.key_name(key),
+                    value: value.to_string(),
+                },
+                Precondition::ListLen { key, len } => Precondition::ListLen {
+                    key: self.key_name(key),
+                    len: *len,
+                },
             })
-            .await?
-            .0;
-        let all_keys_backup = storage_client_backup
-            .keys(&Pattern {
-                prefix: prefix.to_string(),
-                suffix: suffix.to_string(),
+            .collect();
+        let ops = txn
+            .ops
+            .iter()
+            .map(|op| match op {
+                TxnOp::Get(key) => TxnOp::Get(self.key_name(key)),
+                TxnOp::Set(v) => TxnOp::Set(KeyValue {
+                    key: self.key_name(&v.key),
+                    value: Versioned {
+                        clock,
+                        value: v.value.to_string(),
+                    }
+                    .encode(),
+                }),
+                TxnOp::ListAppend(v) => TxnOp::ListAppend(KeyValue {
+                    key: self.key_name(&v.key),
+                    value: serde_json::to_string(&LogEntry {
+                        message: v.value.to_string(),
+                        clock,
+                    })
+                    .unwrap(),
+                }),
+                TxnOp::ListRemove(v) => TxnOp::ListRemove(kv(v)),
             })
-            .await?
-            .0;
-        let all_keys_primary_set: HashSet<String> = HashSet::from_iter(all_keys_primary);
-        let all_keys_backup_set: HashSet<String> = HashSet::from_iter(all_keys_backup);
-
-        // let union_set = all_keys_primary_set
-        //     .union(&all_keys_backup_set)
-        //     .collect::<HashSet<&String>>();
-        // let all_keys: Vec<&String> = Vec::from_iter(union_set);
-        let mut all_keys_set = HashSet::new();
-        if all_keys_primary_set.len() >= all_keys_backup_set.len() {
-            all_keys_set = all_keys_primary_set;
-        } else {
-            all_keys_set = all_keys_backup_set;
+            .collect();
+        Ok(Txn { checks, ops })
+    }
+}
+#[async_trait]
+impl KeyString for StorageClientWrapper {
+    async fn get(&self, key: &str) -> TribResult<Option<String>> {
+        // every copy carries the clock of the write that stored it, and the
+        // newest copy among the replicas that answer wins
+        let clients = self.update_table().await;
+        let copies = self.newest_copies(&clients, &[self.key_name(key)]).await?;
+        Ok(value_of(copies.into_iter().next().flatten()))
+    }
+
+    async fn set(&self, kv: &KeyValue) -> TribResult<bool> {
+        self.put(&kv.key, &kv.value, None).await
+    }
+
+    async fn set_with_ttl(&self, kv: &KeyValue, ttl: Duration) -> TribResult<bool> {
+        self.put(&kv.key, &kv.value, Some(ttl)).await
+    }
+
+    async fn cas(&self, key: &str, expected: Option<String>, new: &str) -> TribResult<bool> {
+        // decided and replicated the same way as any other transaction
+        let txn = Txn {
+            checks: vec![Precondition::Value {
+                key: key.to_string(),
+                value: expected.unwrap_or_default(),
+            }],
+            ops: vec![TxnOp::Set(KeyValue::new(key, new))],
+        };
+        Ok(self.transaction(&txn).await?.committed)
+    }
+
+    async fn multi_get(&self, keys: &[String]) -> TribResult<Vec<Option<String>>> {
+        // same rules as get(), key by key
+        let clients = self.update_table().await;
+        let key_names = keys
+            .iter()
+            .map(|key| self.key_name(key))
+            .collect::<Vec<String>>();
+        let copies = self.newest_copies(&clients, &key_names).await?;
+        Ok(copies.into_iter().map(value_of).collect())
+    }
+
+    async fn keys(&self, p: &Pattern) -> TribResult<List> {
+        let clients = self.update_table().await;
+        let pattern = self.pattern(p);
+        let mut results = Vec::with_capacity(clients.len());
+        for c in clients.iter() {
+            results.push(c.keys(&pattern).await.map(|l| l.0));
+        }
+        let answers = self.check(results, self.replication.read_quorum)?;
+        Ok(List(self.live_keys(merge_keys(answers).0).await?))
+    }
+
+    async fn keys_page(&self, p: &Pattern, start_after: &str, limit: usize) -> TribResult<KeyPage> {
+        let clients = self.update_table().await;
+        let (pattern, start) = self.page_bounds(p, start_after);
+        let mut results = Vec::with_capacity(clients.len());
+        for c in clients.iter() {
+            results.push(c.keys_page(&pattern, &start, limit).await);
         }
-        let all_keys: Vec<String> = Vec::from_iter(all_keys_set);
-        let mut all_keys_unescaped = Vec::new();
-        all_keys.iter().into_iter().for_each(|key| {
-            let tmp: Vec<String> = key.split("::").map(|x| x.to_string()).collect();
-            let key_name = unescape(tmp.get(1).unwrap()).to_string();
-            all_keys_unescaped.push(key_name.clone());
-        });
-        Ok(List(all_keys_unescaped))
+        let answers = self.check(results, self.replication.read_quorum)?;
+        let page = merge_key_pages(answers, limit);
+        Ok(KeyPage {
+            keys: List(self.live_keys(page.keys.0).await?),
+            next: page.next,
+        })
     }
 }
 
 #[async_trait]
 impl KeyList for StorageClientWrapper {
     async fn list_get(&self, key: &str) -> TribResult<List> {
-        // todo: get value twice and compare??
-        let (storage_client_primary, storage_client_backup) = self.update_table().await;
-
-        let mut key_name = escape(self.name.clone()).to_string();
-        key_name.push_str(&"::".to_string());
-        key_name.push_str(&escape(key));
-
-        let res_primary = storage_client_primary
-            .list_get(&key_name.to_string())
-            .await?
-            .0;
-        let res_backup = storage_client_backup
-            .list_get(&key_name.to_string())
-            .await?
-            .0;
-        let res_primary_set: HashSet<String> = HashSet::from_iter(res_primary);
-        let res_backup_set: HashSet<String> = HashSet::from_iter(res_backup);
-        let mut all_res_set = HashSet::new();
-        if res_primary_set.len() >= res_backup_set.len() {
-            all_res_set = res_primary_set;
-        } else {
-            all_res_set = res_backup_set;
+        let clients = self.update_table().await;
+        let key_name = self.key_name(key);
+        let mut results = Vec::with_capacity(clients.len());
+        for c in clients.iter() {
+            results.push(c.list_get(&key_name).await.map(|l| l.0));
         }
-        let all_res: Vec<String> = Vec::from_iter(all_res_set);
-        let mut res = all_res
-            .to_vec()
-            .iter()
-            .map(|x| OrderLogEntry {
-                logentry: Arc::new(serde_json::from_str::<LogEntry>(x).unwrap()).clone(),
-            })
-            .collect::<Vec<OrderLogEntry>>();
-        res.sort();
-        let res0 = res
-            .iter()
-            .map(|x| x.logentry.message.clone())
-            .collect::<Vec<String>>();
-        return Ok(List(res0));
+        let answers = self.check(results, self.replication.read_quorum)?;
+        Ok(merge_log_lists(answers))
     }
 
     async fn list_append(&self, kv: &KeyValue) -> TribResult<bool> {
-        // todo: append kv twice
-        let (storage_client_primary, storage_client_backup) = self.update_table().await;
-
-        let clock1 = storage_client_primary.clock(0).await?;
-        let clock2 = storage_client_backup.clock(0).await?;
-        let c = match clock1 > clock2 {
-            true => clock1,
-            false => clock2,
-        };
-        let mut key_name = escape(self.name.clone()).to_string();
-        key_name.push_str(&"::".to_string());
-        key_name.push_str(&escape(kv.key.clone()));
-        let log_entry = serde_json::to_string(&LogEntry {
-            message: kv.value.to_string(),
-            clock: c,
-        })
-        .unwrap();
+        self.append(kv, None).await
+    }
 
-        let res1 = storage_client_primary
-            .list_append(&KeyValue {
-                key: key_name.to_string(),
-                value: log_entry.to_string(),
-            })
-            .await?;
-        let res2 = storage_client_backup
-            .list_append(&KeyValue {
-                key: key_name.to_string(),
-                value: log_entry.to_string(),
-            })
-            .await?;
-        if res1 || res2 {
-            Ok(true)
-        } else {
-            Ok(false)
-        }
+    async fn list_append_with_ttl(&self, kv: &KeyValue, ttl: Duration) -> TribResult<bool> {
+        self.append(kv, Some(ttl)).await
     }
 
     async fn list_remove(&self, kv: &KeyValue) -> TribResult<u32> {
-        // todo: remove kv twice
-        let (storage_client_primary, storage_client_backup) = self.update_table().await;
+        let clients = self.update_table().await;
+        let key_name = self.key_name(&kv.key);
+        let mut results = Vec::with_capacity(clients.len());
+        for c in clients.iter() {
+            results.push(
+                c.list_remove(&KeyValue {
+                    key: key_name.to_string(),
+                    value: kv.value.to_string(),
+                })
+                .await,
+            );
+        }
+        let answers = self.check(results, self.replication.write_quorum)?;
+        Ok(answers.into_iter().max().unwrap_or(0))
+    }
+
+    async fn list_trim(&self, key: &str, keep_last: u64) -> TribResult<u32> {
+        let clients = self.update_table().await;
+        let key_name = self.key_name(key);
+        let mut results = Vec::with_capacity(clients.len());
+        for c in clients.iter() {
+            results.push(c.list_trim(&key_name, keep_last).await);
+        }
+        let answers = self.check(results, self.replication.write_quorum)?;
+        Ok(answers.into_iter().max().unwrap_or(0))
+    }
 
-        let mut key_name = escape(self.name.clone()).to_string();
-        key_name.push_str(&"::".to_string());
-        key_name.push_str(&escape(kv.key.clone()));
+    async fn list_len(&self, key: &str) -> TribResult<u64> {
+        // the replica that has seen the most entries is the most up to date
+        let clients = self.update_table().await;
+        let key_name = self.key_name(key);
+        let mut results = Vec::with_capacity(clients.len());
+        for c in clients.iter() {
+            results.push(c.list_len(&key_name).await);
+        }
+        let answers = self.check(results, self.replication.read_quorum)?;
+        Ok(answers.into_iter().max().unwrap_or(0))
+    }
 
-        let res1 = storage_client_primary
-            .list_remove(&KeyValue {
-                key: key_name.to_string(),
-                value: kv.value.to_string(),
-            })
-            .await?;
-        let _res2 = storage_client_backup
-            .list_remove(&KeyValue {
-                key: key_name.to_string(),
-                value: kv.value.to_string(),
-            })
-            .await?;
-        Ok(res1)
+    async fn list_range(&self, key: &str, start: i64, end: i64) -> TribResult<List> {
+        let clients = self.update_table().await;
+        let key_name = self.key_name(key);
+        let (s, e) = replica_range(start, end);
+        let mut results = Vec::with_capacity(clients.len());
+        for c in clients.iter() {
+            results.push(c.list_range(&key_name, s, e).await.map(|l| l.0));
+        }
+        let answers = self.check(results, self.replication.read_quorum)?;
+        Ok(merge_log_ranges(answers, start, end))
+    }
+
+    async fn multi_list_range(
+        &self,
+        keys: &[String],
+        start: i64,
+        end: i64,
+    ) -> TribResult<Vec<List>> {
+        let clients = self.update_table().await;
+        let key_names = keys
+            .iter()
+            .map(|key| self.key_name(key))
+            .collect::<Vec<String>>();
+        let (s, e) = replica_range(start, end);
+        let mut results = Vec::with_capacity(clients.len());
+        for c in clients.iter() {
+            results.push(c.multi_list_range(&key_names, s, e).await);
+        }
+        let answers = self.check(results, self.replication.read_quorum)?;
+        Ok(merge_multi_ranges(answers, keys.len(), start, end))
     }
 
     async fn list_keys(&self, p: &Pattern) -> TribResult<List> {
-        // todo: list kv twice
-        let (storage_client_primary, storage_client_backup) = self.update_table().await;
-
-        let mut prefix = escape(self.name.to_string());
-        prefix.push_str(&"::".to_string());
-        prefix.push_str(&escape(p.prefix.to_string()));
-        let suffix = escape(p.suffix.to_string());
-        let all_keys_primary = storage_client_primary
-            .list_keys(&Pattern {
-                prefix: prefix.to_string(),
-                suffix: suffix.to_string(),
-            })
-            .await?
-            .0;
-        let all_keys_backup = storage_client_backup
-            .list_keys(&Pattern {
-                prefix: prefix.to_string(),
-                suffix: suffix.to_string(),
+        let clients = self.update_table().await;
+        let pattern = self.pattern(p);
+        let mut results = Vec::with_capacity(clients.len());
+        for c in clients.iter() {
+            results.push(c.list_keys(&pattern).await.map(|l| l.0));
+        }
+        let answers = self.check(results, self.replication.read_quorum)?;
+        Ok(merge_keys(answers))
+    }
+
+    async fn list_keys_page(
+        &self,
+        p: &Pattern,
+        start_after: &str,
+        limit: usize,
+    ) -> TribResult<KeyPage> {
+        let clients = self.update_table().await;
+        let (pattern, start) = self.page_bounds(p, start_after);
+        let mut results = Vec::with_capacity(clients.len());
+        for c in clients.iter() {
+            results.push(c.list_keys_page(&pattern, &start, limit).await);
+        }
+        let answers = self.check(results, self.replication.read_quorum)?;
+        Ok(merge_key_pages(answers, limit))
+    }
+
+    async fn multi_list_get(&self, keys: &[String]) -> TribResult<Vec<List>> {
+        let clients = self.update_table().await;
+        let key_names = keys
+            .iter()
+            .map(|key| self.key_name(key))
+            .collect::<Vec<String>>();
+        let mut results = Vec::with_capacity(clients.len());
+        for c in clients.iter() {
+            results.push(c.multi_list_get(&key_names).await);
+        }
+        let answers = self.check(results, self.replication.read_quorum)?;
+        Ok(merge_multi_lists(answers, keys.len()))
+    }
+
+    async fn list_append_many(&self, key: &str, values: &[String]) -> TribResult<bool> {
+        let clients = self.update_table().await;
+        let c = self.max_clock(&clients).await?;
+        // the batch takes the clock values c, c + 1, ... so it keeps its
+        // order once merged with other entries, then every replica is moved
+        // past the last one
+        let n = values.len() as u64;
+        for client in clients.iter() {
+            let _ = client.clock(c + n).await;
+        }
+        let key_name = self.key_name(key);
+        let log_entries = values
+            .iter()
+            .enumerate()
+            .map(|(i, value)| {
+                serde_json::to_string(&LogEntry {
+                    message: value.to_string(),
+                    clock: c + i as u64,
+                })
+                .unwrap()
             })
-            .await?
-            .0;
-        let all_keys_primary_set: HashSet<String> = HashSet::from_iter(all_keys_primary);
-        let all_keys_backup_set: HashSet<String> = HashSet::from_iter(all_keys_backup);
-        let mut all_keys_set = HashSet::new();
-        if all_keys_primary_set.len() >= all_keys_backup_set.len() {
-            all_keys_set = all_keys_primary_set;
-        } else {
-            all_keys_set = all_keys_backup_set;
+            .collect::<Vec<String>>();
+
+        let mut results = Vec::with_capacity(clients.len());
+        for client in clients.iter() {
+            results.push(client.list_append_many(&key_name, &log_entries).await);
+        }
+        let answers = self.check(results, self.replication.write_quorum)?;
+        Ok(answers.into_iter().any(|x| x))
+    }
+}
+
+/// Merges the batched answers of several replicas, list by list.
+pub fn merge_multi_lists(replicas: Vec<Vec<List>>, n: usize) -> Vec<List> {
+    per_key(replicas, n)
+        .into_iter()
+        .map(merge_log_lists)
+        .collect()
+}
+
+/// Merges the batched range answers of several replicas, list by list; see
+/// [merge_log_ranges].
+pub fn merge_multi_ranges(replicas: Vec<Vec<List>>, n: usize, start: i64, end: i64) -> Vec<List> {
+    per_key(replicas, n)
+        .into_iter()
+        .map(|lists| merge_log_ranges(lists, start, end))
+        .collect()
+}
+
+/// Regroups the answers of several replicas from one batch per replica to
+/// one set of replica answers per key.
+fn per_key(replicas: Vec<Vec<List>>, n: usize) -> Vec<Vec<Vec<String>>> {
+    let mut per_key: Vec<Vec<Vec<String>>> = vec![Vec::new(); n];
+    for lists in replicas {
+        for (i, list) in lists.into_iter().enumerate().take(n) {
+            per_key[i].push(list.0);
         }
-        let all_keys: Vec<String> = Vec::from_iter(all_keys_set);
-        let mut all_keys_unescaped = Vec::new();
-        all_keys.iter().into_iter().for_each(|key| {
-            let tmp: Vec<String> = key.split("::").map(|x| x.to_string()).collect();
-            let key_name = unescape(tmp.get(1).unwrap()).to_string();
-            all_keys_unescaped.push(key_name.clone());
-        });
-        Ok(List(all_keys_unescaped))
     }
+    per_key
 }
 
 #[async_trait]
 impl Storage for StorageClientWrapper {
     async fn clock(&self, at_least: u64) -> TribResult<u64> {
-        let (storage_client_primary, storage_client_backup) = self.update_table().await;
-        let res1 = storage_client_primary.clock(at_least).await?;
-        let res2 = storage_client_backup.clock(at_least).await?;
-        if res1 > res2 {
-            let _ = storage_client_backup.clock(res1).await;
-            Ok(res1)
-        } else {
-            if res1 < res2 {
-                let _ = storage_client_primary.clock(res2).await;
-                Ok(res2)
-            } else {
-                Ok(res1)
+        // move every replica up to the largest clock any of them has
+        let clients = self.update_table().await;
+        let mut results = Vec::with_capacity(clients.len());
+        for c in clients.iter() {
+            results.push(c.clock(at_least).await);
+        }
+        let clocks = self.check(results, self.replication.write_quorum)?;
+        let max = clocks.into_iter().max().unwrap_or(at_least);
+        for c in clients.iter() {
+            let _ = c.clock(max).await;
+        }
+        Ok(max)
+    }
+
+    async fn transaction(&self, txn: &Txn) -> TribResult<TxnResult> {
+        // the values checked for are judged against the newest copies among
+        // the replicas, and the replica deciding is held to exactly those
+        // copies; only once it has committed do the others repeat whatever
+        // it changed
+        let clients = self.update_table().await;
+        let txn = self.physical_txn(txn, &clients).await?;
+        let changes = Txn {
+            checks: vec![],
+            ops: txn
+                .ops
+                .iter()
+                .filter(|op| !matches!(op, TxnOp::Get(_)))
+                .cloned()
+                .collect(),
+        };
+        let (keys, expected): (Vec<String>, Vec<String>) = txn
+            .checks
+            .iter()
+            .filter_map(|c| match c {
+                Precondition::Value { key, value } => Some((key.to_string(), value.to_string())),
+                Precondition::ListLen { .. } => None,
+            })
+            .unzip();
+        for _ in 0..TXN_ATTEMPTS {
+            let copies = self.newest_copies(&clients, &keys).await?;
+            let holds = copies
+                .iter()
+                .zip(expected.iter())
+                .all(|(copy, value)| value_of(copy.clone()).unwrap_or_default() == *value);
+            if !holds {
+                return Ok(TxnResult {
+                    committed: false,
+                    values: vec![],
+                });
+            }
+            let mut copies = copies.into_iter();
+            let pinned = Txn {
+                checks: txn
+                    .checks
+                    .iter()
+                    .map(|c| match c {
+                        Precondition::Value { key, .. } => Precondition::Value {
+                            key: key.to_string(),
+                            value: copies.next().flatten().unwrap_or_default(),
+                        },
+                        other => other.clone(),
+                    })
+                    .collect(),
+                ops: txn.ops.clone(),
+            };
+
+            let (i, r) = self.decide(&clients, &pinned).await?;
+            if !r.committed {
+                // a replica that was behind is caught up and asked again;
+                // otherwise the checks really did not hold
+                match self.catch_up(&clients[i], &pinned.checks).await? {
+                    true => continue,
+                    false => return Ok(r),
+                }
+            }
+
+            let mut acks = 1;
+            for (j, other) in clients.iter().enumerate() {
+                if j == i {
+                    continue;
+                }
+                match other.transaction(&changes).await {
+                    Ok(_) => acks += 1,
+                    Err(e) => {
+                        log::warn!(
+                            "replica failed in transaction, the table may be out of date: {:?}",
+                            e
+                        );
+                        metrics::REPLICA_FAILURES.inc();
+                        self.membership.report_failure();
+                    }
+                }
+            }
+            let needed = self.replication.write_quorum.min(clients.len()).max(1);
+            if acks < needed {
+                return Err(Box::new(TribblerError::Unavailable(format!(
+                    "only {} of the {} replicas needed took the transaction",
+                    acks, needed
+                ))));
             }
+            return Ok(TxnResult {
+                committed: true,
+                values: r.values.into_iter().map(value_of).collect(),
+            });
         }
+        Err(Box::new(TribblerError::Conflict(
+            "the transaction kept racing with other writes".to_string(),
+        )))
     }
 }
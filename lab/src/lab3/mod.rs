@@ -168,3 +168,4 @@
 //!
 //! Happy Lab 3. :-)
 pub mod myKeeper;
+pub mod raft;
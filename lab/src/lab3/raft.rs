@@ -0,0 +1,508 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+use tokio::time;
+
+use crate::keeper::{AppendRequest, AppendResponse, RaftEntry, VoteRequest, VoteResponse};
+use tribbler::err::{TribResult, TribblerError};
+use tribbler::storage::{KeyValue, Storage};
+
+/// How often a leader sends heartbeats, and how often the timers are checked.
+pub const HEARTBEAT_INTERVAL: Duration = Duration::from_millis(100);
+/// A follower that hears from no leader for a random time between this and
+/// [ELECTION_TIMEOUT_MAX] starts an election. A leader that has not heard
+/// back from a majority for this long steps down.
+pub const ELECTION_TIMEOUT_MIN: Duration = Duration::from_millis(500);
+pub const ELECTION_TIMEOUT_MAX: Duration = Duration::from_millis(1000);
+/// How long a single RPC to a peer may take.
+pub const RPC_TIMEOUT: Duration = Duration::from_millis(200);
+/// How long [Raft::propose] waits for its entry to commit.
+pub const PROPOSE_TIMEOUT: Duration = Duration::from_secs(3);
+/// The key a [Raft] given a storage keeps its [Durable] state under.
+const DURABLE_KEY: &str = "raft";
+
+/// How a [Raft] node reaches its peers. Peers are identified by their index
+/// in the keeper list.
+#[async_trait]
+pub trait RaftTransport: Send + Sync {
+    async fn request_vote(&self, to: usize, req: VoteRequest) -> TribResult<VoteResponse>;
+    async fn append_entries(&self, to: usize, req: AppendRequest) -> TribResult<AppendResponse>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    Follower,
+    Candidate,
+    Leader,
+}
+
+struct State {
+    term: u64,
+    voted_for: Option<usize>,
+    // entry i lives at log[i - 1]; index 0 is the empty prefix
+    log: Vec<RaftEntry>,
+    commit_index: u64,
+    role: Role,
+    leader: Option<usize>,
+    // when a follower or candidate gives up on the current leader
+    deadline: Instant,
+    // leader only, per peer
+    next_index: Vec<u64>,
+    match_index: Vec<u64>,
+    last_ack: Vec<Instant>,
+    // the durable state as last written to the storage
+    saved: String,
+}
+
+/// What a member must not forget across a restart: its term, whom it voted
+/// for in that term, and its log.
+#[derive(Serialize, Deserialize, Default)]
+struct Durable {
+    term: u64,
+    voted_for: Option<usize>,
+    log: Vec<(u64, String)>,
+}
+
+impl State {
+    fn last_index(&self) -> u64 {
+        self.log.len() as u64
+    }
+
+    fn term_at(&self, index: u64) -> u64 {
+        match index {
+            0 => 0,
+            i => self.log[(i - 1) as usize].term,
+        }
+    }
+
+    fn reset_deadline(&mut self) {
+        let timeout = rand::thread_rng().gen_range(ELECTION_TIMEOUT_MIN..ELECTION_TIMEOUT_MAX);
+        self.deadline = Instant::now() + timeout;
+    }
+
+    fn become_follower(&mut self, term: u64) {
+        if term > self.term {
+            self.term = term;
+            self.voted_for = None;
+        }
+        if self.role == Role::Leader {
+            self.leader = None;
+        }
+        self.role = Role::Follower;
+        self.reset_deadline();
+    }
+}
+
+/// A single member of a Raft group: leader election with terms, and a log of
+/// string commands that is replicated to, and committed by, a majority.
+///
+/// A member made with [Raft::open] writes its term, vote and log to its
+/// storage before it answers a vote or an append, so that after a restart it
+/// can neither vote twice in a term nor lose entries it helped commit. One
+/// made with [Raft::new] keeps them in memory only, and a restart makes it a
+/// new member which must not rejoin the same group.
+pub struct Raft {
+    id: usize,
+    peers: usize,
+    transport: Arc<dyn RaftTransport>,
+    state: Mutex<State>,
+    storage: Option<Box<dyn Storage>>,
+}
+
+impl Raft {
+    /// Creates member `id` of a group of `peers` members.
+    pub fn new(id: usize, peers: usize, transport: Arc<dyn RaftTransport>) -> Raft {
+        let mut state = State {
+            term: 0,
+            voted_for: None,
+            log: vec![],
+            commit_index: 0,
+            role: Role::Follower,
+            leader: None,
+            deadline: Instant::now(),
+            next_index: vec![1; peers],
+            match_index: vec![0; peers],
+            last_ack: vec![Instant::now(); peers],
+            saved: String::new(),
+        };
+        state.reset_deadline();
+        Raft {
+            id,
+            peers,
+            transport,
+            state: Mutex::new(state),
+            storage: None,
+        }
+    }
+
+    /// Like [Raft::new], but keeps the durable state in `storage`, picking up
+    /// whatever an earlier run of this member left there.
+    pub async fn open(
+        id: usize,
+        peers: usize,
+        transport: Arc<dyn RaftTransport>,
+        storage: Box<dyn Storage>,
+    ) -> TribResult<Raft> {
+        let mut raft = Raft::new(id, peers, transport);
+        if let Some(saved) = storage.get(DURABLE_KEY).await? {
+            let durable: Durable = serde_json::from_str(&saved)?;
+            let s = raft.state.get_mut();
+            s.term = durable.term;
+            s.voted_for = durable.voted_for;
+            s.log = durable
+                .log
+                .into_iter()
+                .map(|(term, command)| RaftEntry { term, command })
+                .collect();
+            s.saved = saved;
+        }
+        raft.storage = Some(storage);
+        Ok(raft)
+    }
+
+    /// Writes the durable part of `s` to the storage, if it changed since
+    /// it was last written.
+    async fn persist(&self, s: &mut State) -> TribResult<()> {
+        let storage = match &self.storage {
+            Some(storage) => storage,
+            None => return Ok(()),
+        };
+        let durable = Durable {
+            term: s.term,
+            voted_for: s.voted_for,
+            log: s.log.iter().map(|e| (e.term, e.command.clone())).collect(),
+        };
+        let saved = serde_json::to_string(&durable)?;
+        if saved != s.saved {
+            storage.set(&KeyValue::new(DURABLE_KEY, &saved)).await?;
+            s.saved = saved;
+        }
+        Ok(())
+    }
+
+    /// Drives the timers forever. Dropping the future stops the node from
+    /// starting elections or sending heartbeats.
+    pub async fn run(self: Arc<Self>) {
+        loop {
+            time::sleep(HEARTBEAT_INTERVAL).await;
+            self.tick().await;
+        }
+    }
+
+    /// Sends heartbeats when leading, or starts an election once the current
+    /// leader has been silent for too long.
+    pub async fn tick(&self) {
+        let (role, expired) = {
+            let s = self.state.lock().await;
+            (s.role, Instant::now() >= s.deadline)
+        };
+        match role {
+            Role::Leader => self.replicate().await,
+            _ if expired => self.campaign().await,
+            _ => {}
+        }
+    }
+
+    pub async fn status(&self) -> (u64, Role) {
+        let s = self.state.lock().await;
+        (s.term, s.role)
+    }
+
+    pub async fn is_leader(&self) -> bool {
+        self.state.lock().await.role == Role::Leader
+    }
+
+    /// The leader of the current term, as far as this member knows.
+    pub async fn leader(&self) -> Option<usize> {
+        self.state.lock().await.leader
+    }
+
+    /// Every committed command, oldest first.
+    pub async fn committed(&self) -> Vec<String> {
+        let s = self.state.lock().await;
+        s.log[..s.commit_index as usize]
+            .iter()
+            .map(|e| e.command.clone())
+            .collect()
+    }
+
+    /// The newest committed command.
+    pub async fn last_committed(&self) -> Option<String> {
+        let s = self.state.lock().await;
+        match s.commit_index {
+            0 => None,
+            i => Some(s.log[(i - 1) as usize].command.clone()),
+        }
+    }
+
+    /// Appends `command` to the log and waits until a majority has it.
+    /// Returns the index of the entry, or an error when this member is not
+    /// the leader or loses leadership before the entry commits.
+    pub async fn propose(&self, command: String) -> TribResult<u64> {
+        let (term, index) = {
+            let mut s = self.state.lock().await;
+            if s.role != Role::Leader {
+                return Err(not_leader());
+            }
+            let term = s.term;
+            s.log.push(RaftEntry { term, command });
+            if let Err(e) = self.persist(&mut s).await {
+                s.log.pop();
+                return Err(e);
+            }
+            (term, s.last_index())
+        };
+        let deadline = Instant::now() + PROPOSE_TIMEOUT;
+        loop {
+            self.replicate().await;
+            {
+                let s = self.state.lock().await;
+                if s.term != term || s.role != Role::Leader {
+                    return Err(not_leader());
+                }
+                if s.commit_index >= index {
+                    return Ok(index);
+                }
+            }
+            if Instant::now() >= deadline {
+                return Err(Box::new(TribblerError::Unknown(
+                    "raft entry did not commit in time".to_string(),
+                )));
+            }
+            time::sleep(HEARTBEAT_INTERVAL / 2).await;
+        }
+    }
+
+    pub async fn handle_vote(&self, req: VoteRequest) -> VoteResponse {
+        let mut s = self.state.lock().await;
+        if req.term > s.term {
+            s.become_follower(req.term);
+            s.leader = None;
+        }
+        let candidate = req.candidate as usize;
+        let last = s.last_index();
+        let up_to_date = req.last_log_term > s.term_at(last)
+            || (req.last_log_term == s.term_at(last) && req.last_log_index >= last);
+        let granted = req.term == s.term
+            && (s.voted_for.is_none() || s.voted_for == Some(candidate))
+            && up_to_date;
+        if granted {
+            s.voted_for = Some(candidate);
+            s.reset_deadline();
+        }
+        // the vote only counts once it cannot be forgotten
+        let granted = self.persist(&mut s).await.is_ok() && granted;
+        VoteResponse {
+            term: s.term,
+            granted,
+        }
+    }
+
+    pub async fn handle_append(&self, req: AppendRequest) -> AppendResponse {
+        let mut s = self.state.lock().await;
+        if req.term < s.term {
+            return AppendResponse {
+                term: s.term,
+                success: false,
+                match_index: 0,
+            };
+        }
+        s.become_follower(req.term);
+        s.leader = Some(req.leader as usize);
+
+        if req.prev_log_index > s.last_index() {
+            return AppendResponse {
+                term: s.term,
+                success: false,
+                match_index: s.last_index(),
+            };
+        }
+        if s.term_at(req.prev_log_index) != req.prev_log_term {
+            return AppendResponse {
+                term: s.term,
+                success: false,
+                match_index: req.prev_log_index - 1,
+            };
+        }
+        let mut index = req.prev_log_index;
+        for entry in req.entries {
+            index += 1;
+            if index <= s.last_index() {
+                if s.term_at(index) == entry.term {
+                    continue;
+                }
+                // a conflicting suffix from a deposed leader
+                s.log.truncate((index - 1) as usize);
+            }
+            s.log.push(entry);
+        }
+        if self.persist(&mut s).await.is_err() {
+            return AppendResponse {
+                term: s.term,
+                success: false,
+                match_index: req.prev_log_index,
+            };
+        }
+        if req.leader_commit > s.commit_index {
+            s.commit_index = req.leader_commit.min(index);
+        }
+        AppendResponse {
+            term: s.term,
+            success: true,
+            match_index: index,
+        }
+    }
+
+    fn others(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.peers).filter(move |&p| p != self.id)
+    }
+
+    async fn campaign(&self) {
+        let req = {
+            let mut s = self.state.lock().await;
+            s.term += 1;
+            s.role = Role::Candidate;
+            s.voted_for = Some(self.id);
+            s.leader = None;
+            s.reset_deadline();
+            if self.persist(&mut s).await.is_err() {
+                return;
+            }
+            let last = s.last_index();
+            VoteRequest {
+                term: s.term,
+                candidate: self.id as u64,
+                last_log_index: last,
+                last_log_term: s.term_at(last),
+            }
+        };
+
+        let mut handles = vec![];
+        for to in self.others() {
+            let transport = self.transport.clone();
+            let req = req.clone();
+            handles.push(tokio::spawn(async move {
+                time::timeout(RPC_TIMEOUT, transport.request_vote(to, req)).await
+            }));
+        }
+        let mut votes = 1;
+        for handle in handles {
+            if let Ok(Ok(Ok(resp))) = handle.await {
+                if resp.term > req.term {
+                    self.state.lock().await.become_follower(resp.term);
+                    return;
+                }
+                if resp.granted {
+                    votes += 1;
+                }
+            }
+        }
+
+        {
+            let mut s = self.state.lock().await;
+            if s.role != Role::Candidate || s.term != req.term || votes <= self.peers / 2 {
+                return;
+            }
+            s.role = Role::Leader;
+            s.leader = Some(self.id);
+            let next = s.last_index() + 1;
+            s.next_index = vec![next; self.peers];
+            s.match_index = vec![0; self.peers];
+            s.last_ack = vec![Instant::now(); self.peers];
+        }
+        self.replicate().await;
+    }
+
+    /// Sends every peer the entries it is missing (or just a heartbeat), then
+    /// commits whatever a majority now holds.
+    async fn replicate(&self) {
+        let (term, reqs) = {
+            let s = self.state.lock().await;
+            if s.role != Role::Leader {
+                return;
+            }
+            let reqs = self
+                .others()
+                .map(|to| {
+                    let prev = s.next_index[to] - 1;
+                    let req = AppendRequest {
+                        term: s.term,
+                        leader: self.id as u64,
+                        prev_log_index: prev,
+                        prev_log_term: s.term_at(prev),
+                        entries: s.log[prev as usize..].to_vec(),
+                        leader_commit: s.commit_index,
+                    };
+                    (to, req)
+                })
+                .collect::<Vec<_>>();
+            (s.term, reqs)
+        };
+
+        let mut handles = vec![];
+        for (to, req) in reqs {
+            let transport = self.transport.clone();
+            handles.push((
+                to,
+                tokio::spawn(async move {
+                    time::timeout(RPC_TIMEOUT, transport.append_entries(to, req)).await
+                }),
+            ));
+        }
+        for (to, handle) in handles {
+            if let Ok(Ok(Ok(resp))) = handle.await {
+                let mut s = self.state.lock().await;
+                if resp.term > s.term {
+                    s.become_follower(resp.term);
+                    return;
+                }
+                if s.role != Role::Leader || s.term != term {
+                    return;
+                }
+                s.last_ack[to] = Instant::now();
+                if resp.success {
+                    s.match_index[to] = s.match_index[to].max(resp.match_index);
+                    s.next_index[to] = s.match_index[to] + 1;
+                } else {
+                    let retry = (resp.match_index + 1).min(s.next_index[to] - 1);
+                    s.next_index[to] = retry.max(1);
+                }
+            }
+        }
+
+        let mut s = self.state.lock().await;
+        if s.role != Role::Leader || s.term != term {
+            return;
+        }
+        // only entries of the current term are committed by counting
+        // replicas; earlier ones commit along with them
+        let mut n = s.last_index();
+        while n > s.commit_index && s.term_at(n) == s.term {
+            let holders = 1 + self.others().filter(|&p| s.match_index[p] >= n).count();
+            if holders > self.peers / 2 {
+                s.commit_index = n;
+                break;
+            }
+            n -= 1;
+        }
+        // a leader cut off from the majority stops acting as one before the
+        // rest of the group can elect a new leader
+        let now = Instant::now();
+        let acked = 1 + self
+            .others()
+            .filter(|&p| now.duration_since(s.last_ack[p]) < ELECTION_TIMEOUT_MIN)
+            .count();
+        if acked <= self.peers / 2 {
+            let term = s.term;
+            s.become_follower(term);
+        }
+    }
+}
+
+fn not_leader() -> Box<TribblerError> {
+    Box::new(TribblerError::Unknown("not the raft leader".to_string()))
+}
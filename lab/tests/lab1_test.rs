@@ -11,13 +11,20 @@ use lab::{self, lab1};
 use log::LevelFilter;
 use tokio::{sync::mpsc::Sender as MpscSender, task::JoinHandle};
 
+use tokio_stream::StreamExt;
 use tribbler::addr::rand::rand_port;
+use tribbler::hlc::Hlc;
+use tribbler::metrics;
+use tribbler::rpc::{self, trib_storage_client::TribStorageClient};
 #[allow(unused_imports)]
 use tribbler::{
     self,
     config::BackConfig,
     err::{TribResult, TribblerError},
-    storage::{KeyList, KeyString, KeyValue, MemStorage, Pattern, Storage},
+    storage::{
+        ChangeKind, EvictionPolicy, KeyBytes, KeyList, KeyString, KeyValue, ListBytes, MemStorage,
+        MemoryLimit, Pattern, Precondition, Storage, Txn, TxnOp,
+    },
 };
 
 const DEFAULT_HOST: &str = "127.0.0.1:3000";
@@ -45,6 +52,8 @@ async fn setup(
         storage: storage,
         ready: Some(tx.clone()),
         shutdown: Some(shut_rx),
+        memory_limit: None,
+        tls: None,
     };
 
     let handle = spawn_back(cfg);
@@ -62,6 +71,15 @@ fn spawn_back(cfg: BackConfig) -> tokio::task::JoinHandle<TribResult<()>> {
     tokio::spawn(lab1::serve_back(cfg))
 }
 
+/// A packed clock reading `secs` seconds ahead of the wall clock.
+fn ahead(secs: u64) -> u64 {
+    Hlc {
+        physical: Hlc::now().physical + secs * 1000,
+        logical: 0,
+    }
+    .to_u64()
+}
+
 fn kv(key: &str, value: &str) -> KeyValue {
     KeyValue {
         key: key.to_string(),
@@ -106,6 +124,299 @@ async fn test_set_none() -> TribResult<()> {
     Ok(())
 }
 
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_cas() -> TribResult<()> {
+    let addr = format!("127.0.0.1:{}", rand_port());
+    let (client, _handle, _shut) = setup(Some(&addr), None).await?;
+    assert_eq!(true, client.cas("h8liu", None, "1").await?);
+    assert_eq!(false, client.cas("h8liu", None, "2").await?);
+    assert_eq!(
+        false,
+        client.cas("h8liu", Some("2".to_string()), "3").await?
+    );
+    assert_eq!(Some("1".to_string()), client.get("h8liu").await?);
+    assert_eq!(true, client.cas("h8liu", Some("1".to_string()), "3").await?);
+    assert_eq!(Some("3".to_string()), client.get("h8liu").await?);
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_multi() -> TribResult<()> {
+    let addr = format!("127.0.0.1:{}", rand_port());
+    let (client, _handle, _shut) = setup(Some(&addr), None).await?;
+    let _ = client.set(&kv("h8liu", "1")).await?;
+    let keys = vec!["h8liu".to_string(), "fenglu".to_string()];
+    assert_eq!(
+        vec![Some("1".to_string()), None],
+        client.multi_get(&keys).await?
+    );
+
+    let values = vec!["a".to_string(), "b".to_string()];
+    assert_eq!(true, client.list_append_many("lst", &values).await?);
+    let lists = client.multi_list_get(&keys).await?;
+    assert_eq!(2, lists.len());
+    assert!(lists.iter().all(|l| l.0.is_empty()));
+    let lists = client.multi_list_get(&["lst".to_string()]).await?;
+    assert_eq!(values, lists[0].0);
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_scan_ingest() -> TribResult<()> {
+    let src_addr = format!("127.0.0.1:{}", rand_port());
+    let dst_addr = format!("127.0.0.1:{}", rand_port());
+    let (src, _src_handle, _src_shut) = setup(Some(&src_addr), None).await?;
+    let (dst, _dst_handle, _dst_shut) = setup(Some(&dst_addr), None).await?;
+    let _ = src.set(&kv("h8liu", "1")).await?;
+    let _ = src.set(&kv("fenglu", "2")).await?;
+    let _ = src.list_append(&kv("h8lst", "a")).await?;
+    let _ = src.list_append(&kv("h8lst", "b")).await?;
+    // more keys than the server reads at a time
+    for i in 0..300 {
+        let _ = src.set(&kv(&format!("h8key{}", i), "v")).await?;
+    }
+    // an earlier copy which got part of the way, and an entry appended on
+    // the destination while the copy was running
+    let _ = dst.list_append(&kv("h8lst", "a")).await?;
+    let _ = dst.list_append(&kv("h8lst", "c")).await?;
+
+    let mut s = TribStorageClient::connect(format!("http://{}", src_addr)).await?;
+    let mut d = TribStorageClient::connect(format!("http://{}", dst_addr)).await?;
+    let records = s
+        .scan(rpc::Pattern {
+            prefix: "h8".to_string(),
+            suffix: "".to_string(),
+        })
+        .await?
+        .into_inner();
+    // Streaming<Record> yields Results, ingest wants bare records
+    let records = records.filter_map(|r| r.ok());
+    assert_eq!(302, d.ingest(records).await?.into_inner().ingested);
+
+    assert_eq!(Some("1".to_string()), dst.get("h8liu").await?);
+    assert_eq!(None, dst.get("fenglu").await?);
+    assert_eq!(Some("v".to_string()), dst.get("h8key299").await?);
+    assert_eq!(vec!["a", "c", "b"], dst.list_get("h8lst").await?.0);
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_scan_ingest_ttl() -> TribResult<()> {
+    let src_addr = format!("127.0.0.1:{}", rand_port());
+    let dst_addr = format!("127.0.0.1:{}", rand_port());
+    let (src, _src_handle, _src_shut) = setup(Some(&src_addr), None).await?;
+    let (dst, _dst_handle, _dst_shut) = setup(Some(&dst_addr), None).await?;
+    let ttl = Duration::from_millis(500);
+    let _ = src.set_with_ttl(&kv("session", "alice"), ttl).await?;
+    let _ = src.list_append_with_ttl(&kv("bucket", "a"), ttl).await?;
+    let _ = src.list_append(&kv("bucket", "b")).await?;
+    let _ = src.set(&kv("user", "bob")).await?;
+
+    let mut s = TribStorageClient::connect(format!("http://{}", src_addr)).await?;
+    let mut d = TribStorageClient::connect(format!("http://{}", dst_addr)).await?;
+    let records = s.scan(rpc::Pattern::default()).await?.into_inner();
+    let records = records.filter_map(|r| r.ok());
+    assert_eq!(3, d.ingest(records).await?.into_inner().ingested);
+    assert_eq!(Some("alice".to_string()), dst.get("session").await?);
+    assert_eq!(vec!["a", "b"], dst.list_get("bucket").await?.0);
+
+    // the copies expire along with the originals
+    tokio::time::sleep(ttl * 2).await;
+    assert_eq!(None, dst.get("session").await?);
+    assert!(dst.list_get("bucket").await?.0.is_empty());
+    assert_eq!(Some("bob".to_string()), dst.get("user").await?);
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_health() -> TribResult<()> {
+    let addr = format!("127.0.0.1:{}", rand_port());
+    let (client, _handle, _shut) = setup(Some(&addr), None).await?;
+    // run the clock ahead of the wall clock so only calls can move it
+    let before = client.clock(ahead(60)).await?;
+    let mut c = TribStorageClient::connect(format!("http://{}", addr)).await?;
+    assert!(c.health(rpc::HealthCheck {}).await?.into_inner().serving);
+    // the probe leaves the clock alone
+    assert_eq!(before + 1, client.clock(0).await?);
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_ttl() -> TribResult<()> {
+    let addr = format!("127.0.0.1:{}", rand_port());
+    let (client, _handle, _shut) = setup(Some(&addr), None).await?;
+    let ttl = Duration::from_millis(200);
+    assert!(client.set_with_ttl(&kv("session", "s"), ttl).await?);
+    assert!(client.list_append_with_ttl(&kv("window", "1"), ttl).await?);
+    assert!(client.list_append(&kv("window", "2")).await?);
+    assert_eq!(Some("s".to_string()), client.get("session").await?);
+    assert_eq!(2, client.list_get("window").await?.0.len());
+
+    tokio::time::sleep(ttl * 2).await;
+    assert_eq!(None, client.get("session").await?);
+    assert_eq!(0, client.keys(&pat("", "")).await?.0.len());
+    assert_eq!(0, client.list_get("window").await?.0.len());
+    assert_eq!(0, client.list_keys(&pat("", "")).await?.0.len());
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_keys_page() -> TribResult<()> {
+    let addr = format!("127.0.0.1:{}", rand_port());
+    let (client, _handle, _shut) = setup(Some(&addr), None).await?;
+    for i in 0..25 {
+        client.set(&kv(&format!("k{:02}", i), "v")).await?;
+        client.list_append(&kv(&format!("l{:02}", i), "v")).await?;
+    }
+    let mut keys = vec![];
+    let mut cursor = String::new();
+    loop {
+        let page = client.keys_page(&pat("k", ""), &cursor, 10).await?;
+        assert!(page.keys.0.len() <= 10);
+        keys.extend(page.keys.0);
+        match page.next {
+            Some(next) => cursor = next,
+            None => break,
+        }
+    }
+    let want = (0..25).map(|i| format!("k{:02}", i)).collect::<Vec<_>>();
+    assert_eq!(want, keys);
+
+    let page = client.list_keys_page(&pat("l", "9"), "l09", 100).await?;
+    assert_eq!(vec!["l19"], page.keys.0);
+    assert_eq!(None, page.next);
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_list_range() -> TribResult<()> {
+    let addr = format!("127.0.0.1:{}", rand_port());
+    let (client, _handle, _shut) = setup(Some(&addr), None).await?;
+    for i in 0..10 {
+        client.list_append(&kv("l", &i.to_string())).await?;
+    }
+    assert_eq!(10, client.list_len("l").await?);
+    assert_eq!(vec!["7", "8", "9"], client.list_range("l", -3, -1).await?.0);
+    assert_eq!(vec!["0", "1"], client.list_range("l", 0, 1).await?.0);
+    let lists = client
+        .multi_list_range(&["l".to_string(), "empty".to_string()], -1, -1)
+        .await?;
+    assert_eq!(vec!["9"], lists[0].0);
+    assert_eq!(0, lists[1].0.len());
+
+    assert_eq!(6, client.list_trim("l", 4).await?);
+    assert_eq!(vec!["6", "7", "8", "9"], client.list_get("l").await?.0);
+    assert_eq!(0, client.list_len("empty").await?);
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_watch() -> TribResult<()> {
+    let addr = format!("127.0.0.1:{}", rand_port());
+    let (client, _handle, _shut) = setup(Some(&addr), None).await?;
+    let watcher = client.watcher().expect("remote storage can be watched");
+    let mut w = watcher.watch(&pat("user:", ""), 0).await?;
+    client.set(&kv("user:a", "1")).await?;
+    client.set(&kv("other", "2")).await?;
+    client.list_append(&kv("user:b", "3")).await?;
+
+    let first = w.recv().await.unwrap()?;
+    assert_eq!(ChangeKind::Set, first.kind);
+    assert_eq!(("user:a", "1"), (&*first.key, &*first.value));
+    let second = w.recv().await.unwrap()?;
+    assert_eq!(ChangeKind::ListAppend, second.kind);
+    assert_eq!("user:b", second.key);
+
+    // a new watcher picks up right after the first change
+    let mut resumed = watcher.watch(&pat("user:", ""), first.seq).await?;
+    assert_eq!(second, resumed.recv().await.unwrap()?);
+    let ahead = watcher
+        .watch(&pat("", ""), second.seq + 10)
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        ahead.downcast_ref::<TribblerError>(),
+        Some(TribblerError::OutOfRange(_))
+    ));
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_txn() -> TribResult<()> {
+    let addr = format!("127.0.0.1:{}", rand_port());
+    let (client, _handle, _shut) = setup(Some(&addr), None).await?;
+    client.set(&kv("owner", "alice")).await?;
+    let txn = Txn {
+        checks: vec![Precondition::Value {
+            key: "owner".to_string(),
+            value: "alice".to_string(),
+        }],
+        ops: vec![
+            TxnOp::Set(kv("owner", "bob")),
+            TxnOp::ListAppend(kv("history", "alice")),
+            TxnOp::Get("owner".to_string()),
+        ],
+    };
+    let r = client.transaction(&txn).await?;
+    assert!(r.committed);
+    assert_eq!(vec![Some("bob".to_string())], r.values);
+    assert!(!client.transaction(&txn).await?.committed);
+    assert_eq!(vec!["alice"], client.list_get("history").await?.0);
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_txn_empty_parts() -> TribResult<()> {
+    let addr = format!("127.0.0.1:{}", rand_port());
+    let (client, _handle, _shut) = setup(Some(&addr), None).await?;
+    client.set(&kv("owner", "alice")).await?;
+    let mut c = TribStorageClient::connect(format!("http://{}", addr)).await?;
+    let set = rpc::TxnOp {
+        op: Some(rpc::txn_op::Op::Set(rpc::KeyValue {
+            key: "owner".to_string(),
+            value: "mallory".to_string(),
+        })),
+    };
+    // a check that says nothing must not turn into no check at all
+    let unguarded = rpc::TxnRequest {
+        checks: vec![rpc::Precondition { check: None }],
+        ops: vec![set],
+    };
+    let status = c.txn(unguarded).await.unwrap_err();
+    assert_eq!(tonic::Code::InvalidArgument, status.code());
+    let empty_step = rpc::TxnRequest {
+        checks: vec![],
+        ops: vec![rpc::TxnOp { op: None }],
+    };
+    let status = c.txn(empty_step).await.unwrap_err();
+    assert_eq!(tonic::Code::InvalidArgument, status.code());
+    assert_eq!(Some("alice".to_string()), client.get("owner").await?);
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_ttl_zero() -> TribResult<()> {
+    let addr = format!("127.0.0.1:{}", rand_port());
+    let (client, _handle, _shut) = setup(Some(&addr), None).await?;
+    let mut c = TribStorageClient::connect(format!("http://{}", addr)).await?;
+    // 0 means "never" in a scanned record, so an expiring write refuses it
+    let zero = rpc::ExpiringKeyValue {
+        key: "session".to_string(),
+        value: "s".to_string(),
+        ttl_ms: 0,
+    };
+    let status = c.set_with_ttl(zero.clone()).await.unwrap_err();
+    assert_eq!(tonic::Code::InvalidArgument, status.code());
+    let status = c.list_append_with_ttl(zero).await.unwrap_err();
+    assert_eq!(tonic::Code::InvalidArgument, status.code());
+    assert_eq!(None, client.get("session").await?);
+    // a sub-millisecond TTL rounds up rather than down to 0
+    let ttl = Duration::from_micros(10);
+    assert!(client.set_with_ttl(&kv("session", "s"), ttl).await?);
+    assert!(client.list_append_with_ttl(&kv("lst", "s"), ttl).await?);
+    Ok(())
+}
+
 #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
 async fn test_keys() -> TribResult<()> {
     let (client, _handle, _tx) = setup(None, None).await?;
@@ -183,6 +494,8 @@ async fn test_bad_address() -> TribResult<()> {
         storage: Box::new(MemStorage::default()),
         ready: Some(tx),
         shutdown: None,
+        memory_limit: None,
+        tls: None,
     };
     let handle = spawn_back(cfg);
     if let Ok(ready) = rx.recv_timeout(Duration::from_secs(1)) {
@@ -205,6 +518,8 @@ async fn test_store_before_serve() -> TribResult<()> {
         storage: Box::new(store),
         ready: Some(tx),
         shutdown: None,
+        memory_limit: None,
+        tls: None,
     };
     let _handle = spawn_back(cfg);
     let ready = rx.recv_timeout(Duration::from_secs(1))?;
@@ -224,12 +539,16 @@ async fn test_multi_serve() -> TribResult<()> {
         storage: Box::new(MemStorage::default()),
         ready: Some(tx.clone()),
         shutdown: None,
+        memory_limit: None,
+        tls: None,
     };
     let cfg2 = BackConfig {
         addr: "127.0.0.1:3001".to_string(),
         storage: Box::new(MemStorage::default()),
         ready: Some(tx.clone()),
         shutdown: None,
+        memory_limit: None,
+        tls: None,
     };
     spawn_back(cfg);
     spawn_back(cfg2);
@@ -243,10 +562,114 @@ async fn test_multi_serve() -> TribResult<()> {
 
 #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
 async fn test_clock() -> TribResult<()> {
-    let (client, _srv, _shut) = setup(None, None).await?;
-    assert_eq!(2999, client.clock(2999).await?);
-    assert_eq!(3000, client.clock(0).await?);
-    assert_eq!(3001, client.clock(2999).await?);
+    // other tests move the clock of the server on the default address
+    let addr = format!("127.0.0.1:{}", rand_port());
+    let (client, _srv, _shut) = setup(Some(&addr), None).await?;
+    let a = ahead(60);
+    let t = client.clock(a).await?;
+    assert_eq!(a, t);
+    assert_eq!(t + 1, client.clock(0).await?);
+    assert_eq!(t + 2, client.clock(t).await?);
+    // with nothing ahead of it the clock follows the wall clock
+    let addr = format!("127.0.0.1:{}", rand_port());
+    let (client, _srv, _shut) = setup(Some(&addr), None).await?;
+    let before = Hlc::now();
+    let c = Hlc::from_u64(client.clock(2999).await?);
+    assert!(c >= before && c.physical <= Hlc::now().physical);
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_memory_limit() -> TribResult<()> {
+    let addr = format!("127.0.0.1:{}", rand_port());
+    let (tx, rx): (Sender<bool>, Receiver<bool>) = mpsc::channel();
+    let (shut_tx, shut_rx) = tokio::sync::mpsc::channel(1);
+    let cfg = BackConfig {
+        addr: addr.clone(),
+        storage: Box::new(MemStorage::new()),
+        ready: Some(tx),
+        shutdown: Some(shut_rx),
+        memory_limit: Some(MemoryLimit {
+            bytes: 20,
+            policy: EvictionPolicy::Reject,
+        }),
+        tls: None,
+    };
+    let _handle = spawn_back(cfg);
+    assert_eq!(true, rx.recv_timeout(Duration::from_secs(5))?);
+    let client = lab1::new_client(format!("http://{}", addr).as_str()).await?;
+
+    client.set(&KeyValue::new("hello", "world")).await?;
+    client.list_append(&KeyValue::new("lst", "item")).await?;
+    let err = client
+        .set(&KeyValue::new("big", "value"))
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<TribblerError>(),
+        Some(TribblerError::QuotaExceeded(_))
+    ));
+    let stats = client.stats().await?;
+    assert_eq!(17, stats.used_bytes);
+    assert_eq!(Some(20), stats.limit_bytes);
+    assert_eq!((1, 1, 1), (stats.keys, stats.lists, stats.rejected));
+    let _ = shut_tx.send(()).await;
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_bytes() -> TribResult<()> {
+    let addr = format!("127.0.0.1:{}", rand_port());
+    let (client, _handle, shut_tx) = setup(Some(&addr), None).await?;
+    let bytes = client.bytes().unwrap();
+    let binary = vec![0xff, 0x00, 0xfe];
+    assert!(bytes.set_bytes(b"raw", &binary).await?);
+    assert_eq!(Some(binary.clone()), bytes.get_bytes(b"raw").await?);
+    assert_eq!(None, bytes.get_bytes(b"none").await?);
+    assert!(client.get("raw").await.is_err());
+
+    client.set(&kv("text", "v")).await?;
+    assert_eq!(
+        vec![b"raw".to_vec(), b"text".to_vec()],
+        bytes.keys_bytes(b"").await?
+    );
+
+    bytes.list_append_bytes(&[b'l', 0xff], &binary).await?;
+    bytes.list_append_bytes(&[b'l', 0xff], b"x").await?;
+    assert_eq!(
+        vec![binary.clone(), b"x".to_vec()],
+        bytes.list_get_bytes(&[b'l', 0xff]).await?
+    );
+    assert!(client.list_keys(&pat("l", "")).await?.0.is_empty());
+    assert_eq!(1, bytes.list_remove_bytes(&[b'l', 0xff], &binary).await?);
+    assert_eq!(vec![vec![b'l', 0xff]], bytes.list_keys_bytes(b"l").await?);
+    let _ = shut_tx.send(()).await;
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_rpc_metrics() -> TribResult<()> {
+    let addr = format!("127.0.0.1:{}", rand_port());
+    let (client, _handle, shut_tx) = setup(Some(&addr), None).await?;
+    let count = |method: &str, code: &str| {
+        metrics::RPC_REQUESTS
+            .with_label_values(&[&addr, "rpc.TribStorage", method, code])
+            .get()
+    };
+    client.set(&kv("a", "1")).await?;
+    client.set(&kv("b", "2")).await?;
+    client.bytes().unwrap().set_bytes(b"raw", &[0xff]).await?;
+    assert!(client.get("raw").await.is_err());
+    assert_eq!(2, count("set", "Ok"));
+    assert_eq!(1, count("setBytes", "Ok"));
+    assert_eq!(1, count("get", "Internal"));
+    assert_eq!(0, count("get", "Ok"));
+    let timed = format!(
+        "tribbler_rpc_duration_seconds_count{{method=\"set\",server=\"{}\"",
+        addr
+    );
+    assert!(metrics::render().contains(&timed));
+    let _ = shut_tx.send(()).await;
     Ok(())
 }
 
@@ -260,6 +683,8 @@ async fn test_spawn_same_addr() -> TribResult<()> {
         storage: Box::new(MemStorage::default()),
         ready: Some(tx.clone()),
         shutdown: Some(shut_rx),
+        memory_limit: None,
+        tls: None,
     };
     let handle = spawn_back(cfg);
     assert_eq!(true, rx.recv_timeout(Duration::from_secs(2))?);
@@ -271,6 +696,8 @@ async fn test_spawn_same_addr() -> TribResult<()> {
         storage: Box::new(MemStorage::default()),
         ready: Some(tx),
         shutdown: None,
+        memory_limit: None,
+        tls: None,
     };
     let _ = spawn_back(cfg);
     assert_eq!(true, rx.recv_timeout(Duration::from_secs(2))?);
@@ -291,6 +718,8 @@ async fn test_back_spawn_new_storage() -> TribResult<()> {
         storage: Box::new(MemStorage::default()),
         ready: Some(tx.clone()),
         shutdown: Some(shut_rx),
+        memory_limit: None,
+        tls: None,
     };
     let handle = spawn_back(cfg);
     assert_eq!(true, rx.recv_timeout(Duration::from_secs(2))?);
@@ -305,6 +734,8 @@ async fn test_back_spawn_new_storage() -> TribResult<()> {
         storage: Box::new(MemStorage::default()),
         ready: Some(tx),
         shutdown: Some(shut_rx),
+        memory_limit: None,
+        tls: None,
     };
     let _ = spawn_back(cfg);
     assert_eq!(true, rx.recv_timeout(Duration::from_secs(2))?);
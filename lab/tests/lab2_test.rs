@@ -8,8 +8,14 @@ use std::{
 };
 use rand::Rng;
 use lab::{self, lab1, lab2};
+use lab::lab1::client::StorageClient;
+use lab::lab1::pool::ChannelPool;
+use lab::lab2::membership::{Membership, REFRESH_INTERVAL};
+use lab::lab2::ring::{HashRing, DEFAULT_VNODES};
 use tokio::{sync::mpsc::Sender as MpscSender, time};
-use tribbler::{config::KeeperConfig};
+use tribbler::addr::rand::rand_port;
+use tribbler::config::{KeeperConfig, RateLimit, RateLimits, ReplicationConfig, TlsConfig};
+use tribbler::tls;
 #[allow(unused_imports)]
 use tribbler::{
     self,
@@ -66,36 +72,49 @@ async fn setup(backs: Vec<String>, addrs:  Vec<String>) -> TribResult<(MpscSende
         storage: Box::new(MemStorage::default()),
         ready: None,
         shutdown: Some(shut_rx1),
+        memory_limit: None,
+        tls: None,
     };
     let cfg2 = BackConfig {
         addr: backs[1].to_string(),
         storage: Box::new(MemStorage::default()),
         ready: None,
         shutdown: Some(shut_rx2),
+        memory_limit: None,
+        tls: None,
     };
     let cfg3 = BackConfig {
         addr: backs[2].to_string(),
         storage: Box::new(MemStorage::default()),
         ready: None,
         shutdown: Some(shut_rx3),
+        memory_limit: None,
+        tls: None,
     };
     let cfg4 = BackConfig {
         addr: backs[3].to_string(),
         storage: Box::new(MemStorage::default()),
         ready: None,
         shutdown: Some(shut_rx4),
+        memory_limit: None,
+        tls: None,
     };
     let cfg5 = BackConfig {
         addr: backs[4].to_string(),
         storage: Box::new(MemStorage::default()),
         ready: None,
         shutdown: Some(shut_rx5),
+        memory_limit: None,
+        tls: None,
     };
     let kfg1 = KeeperConfig {
         backs: backs.clone(),
         addrs: addrs.clone(),
         this: 0,
         id: 0,
+        replication: Default::default(),
+        tls: None,
+        data_dir: None,
         ready: None,
         shutdown: Some(shut_rx6),
     };
@@ -237,6 +256,8 @@ async fn test_simple_follow() -> TribResult<()> {
         storage: Box::new(MemStorage::default()),
         ready: None,
         shutdown: Some(shut_rx1),
+        memory_limit: None,
+        tls: None,
     };
     spawn_back(cfg1);
     time::sleep(time::Duration::from_secs(5)).await;
@@ -251,4 +272,555 @@ async fn test_simple_follow() -> TribResult<()> {
 }
 
 
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_replication_survives_two_failures() -> TribResult<()> {
+    let backs = (0..3)
+        .map(|_| format!("127.0.0.1:{}", rand_port()))
+        .collect::<Vec<String>>();
+    let mut shutdowns = vec![];
+    for addr in backs.iter() {
+        let (ready_tx, ready_rx) = mpsc::channel();
+        let (shut_tx, shut_rx) = tokio::sync::mpsc::channel(1);
+        spawn_back(BackConfig {
+            addr: addr.to_string(),
+            storage: Box::new(MemStorage::default()),
+            ready: Some(ready_tx),
+            shutdown: Some(shut_rx),
+            memory_limit: None,
+            tls: None,
+        });
+        assert!(ready_rx.recv_timeout(Duration::from_secs(5))?);
+        shutdowns.push(shut_tx);
+    }
+
+    // every write has to reach all three copies, any one copy can serve a read
+    let replication = ReplicationConfig::new(3, 1, 3);
+    let bc = lab2::new_replicated_bin_client(backs.clone(), replication, None).await?;
+    // give the membership view a refresh in case a probe was slow under load
+    time::sleep(REFRESH_INTERVAL * 2).await;
+    let client = bc.bin("h8liu").await?;
+    client.set(&kv("k", "v")).await?;
+    client.list_append(&kv("l", "a")).await?;
+    client.list_append(&kv("l", "b")).await?;
+
+    let _ = shutdowns[0].send(()).await;
+    let _ = shutdowns[1].send(()).await;
+    time::sleep(Duration::from_millis(500)).await;
+
+    assert_eq!(Some("v".to_string()), client.get("k").await?);
+    assert_eq!(vec!["a", "b"], client.list_get("l").await?.0);
+    // writes need every listed copy, so wait for the view to drop the dead
+    time::sleep(REFRESH_INTERVAL * 2).await;
+    let client = bc.bin("h8liu").await?;
+    assert_eq!(Some("v".to_string()), client.get("k").await?);
+    // a fresh bin is placed on the only live backend, and the quorum is
+    // capped at the copies that exist
+    assert!(client.set(&kv("k", "w")).await?);
+    assert_eq!(Some("w".to_string()), client.get("k").await?);
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_newest_copy_wins() -> TribResult<()> {
+    let backs = (0..3)
+        .map(|_| format!("127.0.0.1:{}", rand_port()))
+        .collect::<Vec<String>>();
+    for addr in backs.iter() {
+        let (ready_tx, ready_rx) = mpsc::channel();
+        spawn_back(BackConfig {
+            addr: addr.to_string(),
+            storage: Box::new(MemStorage::default()),
+            ready: Some(ready_tx),
+            shutdown: None,
+            memory_limit: None,
+            tls: None,
+        });
+        assert!(ready_rx.recv_timeout(Duration::from_secs(5))?);
+    }
+
+    // a read quorum has to meet every write quorum
+    let disjoint = ReplicationConfig::new(3, 1, 2);
+    assert!(lab2::new_replicated_bin_client(backs.clone(), disjoint, None).await.is_err());
+
+    let bc = lab2::new_replicated_bin_client(backs.clone(), ReplicationConfig::new(3, 2, 2), None).await?;
+    let client = bc.bin("alice").await?;
+    client.set(&kv("k", "v")).await?;
+
+    // one replica falls behind with a copy no write stamped
+    let physical = tribbler::colon::BinKey::new("alice", "k").encode();
+    let stale = lab1::new_client(&format!("http://{}", backs[0])).await?;
+    stale.set(&kv(&physical, "old")).await?;
+    for _ in 0..5 {
+        assert_eq!(Some("v".to_string()), client.get("k").await?);
+        assert_eq!(vec![Some("v".to_string())], client.multi_get(&["k".to_string()]).await?);
+    }
+
+    // cas is judged against the newest copy, and catches the stale one up
+    assert!(!client.cas("k", Some("old".to_string()), "x").await?);
+    assert!(client.cas("k", Some("v".to_string()), "w").await?);
+    assert_eq!(Some("w".to_string()), client.get("k").await?);
+    assert_ne!(Some("old".to_string()), stale.get(&physical).await?);
+
+    // a deleted key stays deleted, and out of the key listings
+    client.set(&kv("k", "")).await?;
+    assert_eq!(None, client.get("k").await?);
+    assert!(client.keys(&pat("", "")).await?.0.is_empty());
+    assert!(client.keys_page(&pat("", ""), "", 10).await?.keys.0.is_empty());
+    Ok(())
+}
+
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_bin_keys_page() -> TribResult<()> {
+    let backs = (0..3)
+        .map(|_| format!("127.0.0.1:{}", rand_port()))
+        .collect::<Vec<String>>();
+    for addr in backs.iter() {
+        let (ready_tx, ready_rx) = mpsc::channel();
+        spawn_back(BackConfig {
+            addr: addr.to_string(),
+            storage: Box::new(MemStorage::default()),
+            ready: Some(ready_tx),
+            shutdown: None,
+            memory_limit: None,
+            tls: None,
+        });
+        assert!(ready_rx.recv_timeout(Duration::from_secs(5))?);
+    }
+    let bc = lab2::new_bin_client(backs.clone()).await?;
+    let client = bc.bin("pager").await?;
+    let other = bc.bin("other").await?;
+    // bin names and keys may hold the separator of physical keys
+    let colons = bc.bin("pager::").await?;
+    let mut want = vec![];
+    for i in 0..12 {
+        let key = format!("key:{:02}", i);
+        client.set(&kv(&key, "v")).await?;
+        other.set(&kv(&key, "v")).await?;
+        colons.set(&kv(&format!(":{}", key), "v")).await?;
+        want.push(key);
+    }
+    let mut all = client.keys(&pat("", "")).await?.0;
+    all.sort();
+    assert_eq!(want, all);
+    assert_eq!(12, colons.keys(&pat(":key", "")).await?.0.len());
+
+    let mut keys = vec![];
+    let mut cursor = String::new();
+    loop {
+        let page = client.keys_page(&pat("key", ""), &cursor, 5).await?;
+        assert!(page.keys.0.len() <= 5);
+        keys.extend(page.keys.0);
+        match page.next {
+            Some(next) => cursor = next,
+            None => break,
+        }
+    }
+    assert_eq!(want, keys);
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_bin_list_range() -> TribResult<()> {
+    let backs = (0..3)
+        .map(|_| format!("127.0.0.1:{}", rand_port()))
+        .collect::<Vec<String>>();
+    for addr in backs.iter() {
+        let (ready_tx, ready_rx) = mpsc::channel();
+        spawn_back(BackConfig {
+            addr: addr.to_string(),
+            storage: Box::new(MemStorage::default()),
+            ready: Some(ready_tx),
+            shutdown: None,
+            memory_limit: None,
+            tls: None,
+        });
+        assert!(ready_rx.recv_timeout(Duration::from_secs(5))?);
+    }
+    let bc = lab2::new_bin_client(backs.clone()).await?;
+    let names = vec!["alice".to_string(), "bob".to_string()];
+    for (n, name) in names.iter().enumerate() {
+        let bin = bc.bin(name).await?;
+        for i in 0..(5 + n) {
+            bin.list_append(&kv("tribs", &format!("{}{}", name, i)))
+                .await?;
+        }
+    }
+
+    let alice = bc.bin("alice").await?;
+    assert_eq!(5, alice.list_len("tribs").await?);
+    assert_eq!(
+        vec!["alice3", "alice4"],
+        alice.list_range("tribs", -2, -1).await?.0
+    );
+    assert_eq!(
+        vec!["alice1", "alice2"],
+        alice.list_range("tribs", 1, 2).await?.0
+    );
+    let lists = bc.multi_bin_list_range(&names, "tribs", -2, -1).await?;
+    assert_eq!(vec!["alice3", "alice4"], lists[0].0);
+    assert_eq!(vec!["bob4", "bob5"], lists[1].0);
+
+    assert_eq!(3, alice.list_trim("tribs", 2).await?);
+    assert_eq!(vec!["alice3", "alice4"], alice.list_get("tribs").await?.0);
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_concurrent_follow() -> TribResult<()> {
+    let backs = (0..3)
+        .map(|_| format!("127.0.0.1:{}", rand_port()))
+        .collect::<Vec<String>>();
+    for addr in backs.iter() {
+        let (ready_tx, ready_rx) = mpsc::channel();
+        spawn_back(BackConfig {
+            addr: addr.to_string(),
+            storage: Box::new(MemStorage::default()),
+            ready: Some(ready_tx),
+            shutdown: None,
+            memory_limit: None,
+            tls: None,
+        });
+        assert!(ready_rx.recv_timeout(Duration::from_secs(5))?);
+    }
+    let front1 = lab2::new_front(lab2::new_bin_client(backs.clone()).await?).await?;
+    let front2 = lab2::new_front(lab2::new_bin_client(backs.clone()).await?).await?;
+    front1.sign_up("alice").await?;
+    front1.sign_up("bob").await?;
+
+    for _ in 0..5 {
+        let (a, b) = tokio::join!(
+            front1.follow("alice", "bob"),
+            front2.follow("alice", "bob")
+        );
+        // exactly one of the two gets to write the follow
+        assert!(a.is_ok() ^ b.is_ok());
+        assert_eq!(vec!["bob"], front1.following("alice").await?);
+        let (a, b) = tokio::join!(
+            front1.unfollow("alice", "bob"),
+            front2.unfollow("alice", "bob")
+        );
+        assert!(a.is_ok() ^ b.is_ok());
+        assert_eq!(0, front2.following("alice").await?.len());
+    }
+
+    // and both replicas hold the same log
+    let mut logs = vec![];
+    for addr in backs.iter() {
+        let c = lab1::new_client(&format!("http://{}", addr)).await?;
+        let mut all = vec![];
+        for key in c.list_keys(&pat("alice", "")).await?.0 {
+            all.push(c.list_get(&key).await?.0);
+        }
+        if !all.is_empty() {
+            logs.push(all);
+        }
+    }
+    assert_eq!(2, logs.len());
+    assert_eq!(logs[0], logs[1]);
+    Ok(())
+}
+
+fn rate_limited(r: TribResult<()>) -> bool {
+    matches!(
+        r.unwrap_err().downcast_ref::<TribblerError>(),
+        Some(TribblerError::RateLimited(_))
+    )
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_rate_limits() -> TribResult<()> {
+    let addr = format!("127.0.0.1:{}", rand_port());
+    let (ready_tx, ready_rx) = mpsc::channel();
+    spawn_back(BackConfig {
+        addr: addr.clone(),
+        storage: Box::new(MemStorage::default()),
+        ready: Some(ready_tx),
+        shutdown: None,
+        memory_limit: None,
+        tls: None,
+    });
+    assert!(ready_rx.recv_timeout(Duration::from_secs(5))?);
+    let limit = RateLimit {
+        per_minute: 1,
+        burst: 2,
+    };
+    let limits = RateLimits {
+        post: Some(limit),
+        follow: Some(limit),
+        sign_up: Some(RateLimit {
+            per_minute: 1,
+            burst: 3,
+        }),
+    };
+    let backs = vec![addr];
+    let front1 =
+        lab2::new_limited_front(lab2::new_bin_client(backs.clone()).await?, limits).await?;
+    let front2 =
+        lab2::new_limited_front(lab2::new_bin_client(backs.clone()).await?, limits).await?;
+    front1.sign_up("alice").await?;
+    front2.sign_up("bob").await?;
+    front1.sign_up("carol").await?;
+    assert!(rate_limited(front2.sign_up("dave").await));
+
+    // the front-ends draw from the same buckets, but every user has their own
+    front1.post("alice", "one", 0).await?;
+    front2.post("alice", "two", 0).await?;
+    assert!(rate_limited(front1.post("alice", "three", 0).await));
+    assert!(rate_limited(front2.post("alice", "three", 0).await));
+    front2.post("bob", "one", 0).await?;
+    assert_eq!(2, front1.tribs("alice").await?.len());
+
+    front1.follow("alice", "bob").await?;
+    front2.follow("alice", "carol").await?;
+    front1.unfollow("alice", "bob").await?;
+    assert!(rate_limited(front2.follow("alice", "bob").await));
+    front1.follow("bob", "alice").await?;
+
+    // a front-end without limits is not held to them
+    let free = lab2::new_front(lab2::new_bin_client(backs).await?).await?;
+    free.post("alice", "three", 0).await?;
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_membership_notices_failure() -> TribResult<()> {
+    let backs = (0..2)
+        .map(|_| format!("127.0.0.1:{}", rand_port()))
+        .collect::<Vec<String>>();
+    let mut shutdowns = vec![];
+    for addr in backs.iter() {
+        let (ready_tx, ready_rx) = mpsc::channel();
+        let (shut_tx, shut_rx) = tokio::sync::mpsc::channel(1);
+        spawn_back(BackConfig {
+            addr: addr.to_string(),
+            storage: Box::new(MemStorage::default()),
+            ready: Some(ready_tx),
+            shutdown: Some(shut_rx),
+            memory_limit: None,
+            tls: None,
+        });
+        assert!(ready_rx.recv_timeout(Duration::from_secs(5))?);
+        shutdowns.push(shut_tx);
+    }
+
+    let membership = Membership::start(backs.clone(), ChannelPool::new(), ReplicationConfig::new(2, 1, 2)).await;
+    assert!(membership.table().await.iter().all(|e| e.status));
+
+    let _ = shutdowns[1].send(()).await;
+    membership.report_failure();
+    time::sleep(REFRESH_INTERVAL * 3).await;
+    let table = membership.table().await;
+    assert!(table[0].status);
+    assert!(!table[1].status);
+    Ok(())
+}
+
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_membership_follows_keeper() -> TribResult<()> {
+    let backs = (0..3)
+        .map(|_| format!("127.0.0.1:{}", rand_port()))
+        .collect::<Vec<String>>();
+    let mut shutdowns = vec![];
+    for addr in backs.iter() {
+        let (ready_tx, ready_rx) = mpsc::channel();
+        let (shut_tx, shut_rx) = tokio::sync::mpsc::channel(1);
+        spawn_back(BackConfig {
+            addr: addr.to_string(),
+            storage: Box::new(MemStorage::default()),
+            ready: Some(ready_tx),
+            shutdown: Some(shut_rx),
+            memory_limit: None,
+            tls: None,
+        });
+        assert!(ready_rx.recv_timeout(Duration::from_secs(5))?);
+        shutdowns.push(shut_tx);
+    }
+    let (keep_shut_tx, keep_shut_rx) = tokio::sync::mpsc::channel(1);
+    spawn_keep(KeeperConfig {
+        backs: backs.clone(),
+        addrs: vec![format!("127.0.0.1:{}", rand_port())],
+        this: 0,
+        id: 0,
+        replication: Default::default(),
+        tls: None,
+        data_dir: None,
+        ready: None,
+        shutdown: Some(keep_shut_rx),
+    });
+    time::sleep(Duration::from_millis(500)).await;
+
+    let membership = Membership::start(backs.clone(), ChannelPool::new(), ReplicationConfig::new(2, 1, 2)).await;
+    assert!(membership.epoch().await.is_some(), "keeper published no epoch");
+    // a slow first probe can make the keeper publish a backend as dead
+    // before it catches up
+    for _ in 0..20 {
+        if membership.table().await.iter().all(|e| e.status) {
+            break;
+        }
+        time::sleep(REFRESH_INTERVAL).await;
+    }
+    assert!(membership.table().await.iter().all(|e| e.status));
+    let first = membership.epoch().await.unwrap();
+
+    // the view only changes once the keeper has noticed and published
+    let _ = shutdowns[2].send(()).await;
+    let mut epoch = first;
+    for _ in 0..20 {
+        time::sleep(REFRESH_INTERVAL).await;
+        epoch = membership.epoch().await.unwrap();
+        if epoch > first {
+            break;
+        }
+    }
+    assert!(epoch > first);
+    assert!(!membership.table().await[2].status);
+    let _ = keep_shut_tx.send(()).await;
+    Ok(())
+}
+
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_tls_cluster() -> TribResult<()> {
+    let dir = std::env::temp_dir().join(format!("tribbler-tls-{}", rand_port()));
+    let backs = (0..3)
+        .map(|_| format!("127.0.0.1:{}", rand_port()))
+        .collect::<Vec<String>>();
+    let keeper = format!("127.0.0.1:{}", rand_port());
+    let tls = tls::generate(&dir, &[&backs[..], &[keeper.clone()]].concat())?;
+    for addr in backs.iter() {
+        let (ready_tx, ready_rx) = mpsc::channel();
+        spawn_back(BackConfig {
+            addr: addr.to_string(),
+            storage: Box::new(MemStorage::default()),
+            ready: Some(ready_tx),
+            shutdown: None,
+            memory_limit: None,
+            tls: Some(tls.clone()),
+        });
+        assert!(ready_rx.recv_timeout(Duration::from_secs(5))?);
+    }
+    let (ready_tx, ready_rx) = mpsc::channel();
+    spawn_keep(KeeperConfig {
+        backs: backs.clone(),
+        addrs: vec![keeper],
+        this: 0,
+        id: 0,
+        replication: Default::default(),
+        tls: Some(tls.clone()),
+        data_dir: None,
+        ready: Some(ready_tx),
+        shutdown: None,
+    });
+    assert!(ready_rx.recv_timeout(Duration::from_secs(5))?);
+
+    // the keeper reached every backend over TLS; give it a few rounds, as
+    // the machine may be busy
+    let pool = ChannelPool::with_tls(Some(&tls))?;
+    let mut all_alive = false;
+    for _ in 0..50 {
+        let membership = Membership::start(backs.clone(), pool.clone(), ReplicationConfig::new(2, 1, 2)).await;
+        if membership.epoch().await.is_some()
+            && membership.table().await.iter().all(|e| e.status)
+        {
+            all_alive = true;
+            break;
+        }
+        time::sleep(Duration::from_millis(100)).await;
+    }
+    assert!(all_alive, "keeper published no epoch with every backend alive");
+
+    let bc = lab2::new_replicated_bin_client(backs.clone(), Default::default(), Some(&tls)).await?;
+    let alice = bc.bin("alice").await?;
+    alice.set(&kv("k", "v")).await?;
+    assert_eq!(Some("v".to_string()), alice.get("k").await?);
+
+    // neither a client without a certificate nor one with a certificate
+    // from another authority gets in
+    let plain = lab1::new_client(&format!("http://{}", backs[0])).await?;
+    assert!(plain.keys(&pat("", "")).await.is_err());
+    let other = tls::generate(&dir.join("other"), &[])?;
+    let stranger = TlsConfig { client: other.client, ..tls.clone() };
+    let stranger = StorageClient::new(
+        &format!("http://{}", backs[0]),
+        ChannelPool::with_tls(Some(&stranger))?,
+    );
+    assert!(stranger.keys(&pat("", "")).await.is_err());
+    let _ = std::fs::remove_dir_all(&dir);
+    Ok(())
+}
+
+fn ring_of(n: usize, vnodes: usize) -> HashRing {
+    let mut ring = HashRing::new(vnodes);
+    for i in 0..n {
+        ring.add(i, &format!("127.0.0.1:{}", 30000 + i));
+    }
+    ring
+}
+
+#[test]
+fn test_ring_successors() {
+    let ring = ring_of(5, DEFAULT_VNODES);
+    for i in 0..100 {
+        let name = format!("user{}", i);
+        let found = ring.successors(&name, 3);
+        assert_eq!(found, ring.successors(&name, 3));
+        assert_eq!(3, found.len());
+        assert_eq!(3, found.iter().collect::<HashSet<_>>().len());
+        assert_eq!(found[..2], ring.successors(&name, 2)[..]);
+    }
+    // never more than there are backends
+    assert_eq!(5, ring.successors("h8liu", 10).len());
+    assert!(HashRing::new(DEFAULT_VNODES).successors("h8liu", 2).is_empty());
+}
+
+#[test]
+fn test_ring_balance() {
+    let ring = ring_of(4, DEFAULT_VNODES);
+    let mut counts = HashMap::new();
+    for i in 0..4000 {
+        *counts.entry(ring.successors(&format!("bin{}", i), 1)[0]).or_insert(0) += 1;
+    }
+    for i in 0..4 {
+        let c = counts.get(&i).copied().unwrap_or(0);
+        assert!(c > 500 && c < 1500, "backend {} owns {} of 4000", i, c);
+    }
+}
+
+#[test]
+fn test_ring_join_moves_little() {
+    let before = ring_of(4, DEFAULT_VNODES);
+    let after = ring_of(5, DEFAULT_VNODES);
+    let mut moved = 0;
+    for i in 0..4000 {
+        let name = format!("bin{}", i);
+        let (b, a) = (before.successors(&name, 1)[0], after.successors(&name, 1)[0]);
+        if b != a {
+            // only the new backend may take over a bin
+            assert_eq!(4, a);
+            moved += 1;
+        }
+    }
+    assert!(moved < 1600, "{} of 4000 bins moved", moved);
+}
+
+
+#[test]
+fn test_vnodes_config() -> TribResult<()> {
+    // configs written before the ring size was configurable keep routing
+    // the way they did
+    let old: tribbler::config::Config = serde_json::from_str(r#"{"backs": [], "keepers": []}"#)?;
+    assert_eq!(DEFAULT_VNODES, old.replication_config().vnodes);
+    let cfg: tribbler::config::Config =
+        serde_json::from_str(r#"{"backs": [], "keepers": [], "vnodes": 8}"#)?;
+    let replication = cfg.replication_config();
+    assert_eq!(8, replication.vnodes);
+    // the keeper and every bin client get the same ring size from it
+    assert_eq!(replication, cfg.keeper_config(0, None, None)?.replication);
+    assert_eq!(1, ReplicationConfig::default().with_vnodes(0).vnodes);
+    Ok(())
+}
+
+
 // cargo test -p lab --test lab2_test -- --nocapture
\ No newline at end of file
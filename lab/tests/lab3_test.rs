@@ -0,0 +1,267 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use lab::keeper::{AppendRequest, AppendResponse, RaftEntry, VoteRequest, VoteResponse};
+use lab::lab3::raft::{Raft, RaftTransport, Role, ELECTION_TIMEOUT_MAX};
+use tokio::task::JoinHandle;
+use tokio::time;
+use tribbler::disk::DiskStorage;
+use tribbler::err::{TribResult, TribblerError};
+
+/// Every raft node of one test, plus the links that currently drop messages.
+#[derive(Default)]
+struct Net {
+    nodes: Mutex<Vec<Arc<Raft>>>,
+    cut: Mutex<HashSet<(usize, usize)>>,
+}
+
+impl Net {
+    /// Cuts every link between nodes that are not in the same group.
+    fn partition(&self, groups: &[&[usize]]) {
+        let mut cut = self.cut.lock().unwrap();
+        for a in groups.iter() {
+            for b in groups.iter().filter(|b| *b != a) {
+                for &x in a.iter() {
+                    for &y in b.iter() {
+                        cut.insert((x, y));
+                    }
+                }
+            }
+        }
+    }
+
+    fn heal(&self) {
+        self.cut.lock().unwrap().clear();
+    }
+
+    fn route(&self, from: usize, to: usize) -> TribResult<Arc<Raft>> {
+        if self.cut.lock().unwrap().contains(&(from, to)) {
+            return Err(Box::new(TribblerError::Unknown("link is cut".to_string())));
+        }
+        Ok(self.nodes.lock().unwrap()[to].clone())
+    }
+}
+
+struct LocalTransport {
+    from: usize,
+    net: Arc<Net>,
+}
+
+#[async_trait]
+impl RaftTransport for LocalTransport {
+    async fn request_vote(&self, to: usize, req: VoteRequest) -> TribResult<VoteResponse> {
+        Ok(self.net.route(self.from, to)?.handle_vote(req).await)
+    }
+
+    async fn append_entries(&self, to: usize, req: AppendRequest) -> TribResult<AppendResponse> {
+        Ok(self.net.route(self.from, to)?.handle_append(req).await)
+    }
+}
+
+fn cluster(n: usize) -> (Arc<Net>, Vec<Arc<Raft>>) {
+    let net = Arc::new(Net::default());
+    let nodes = (0..n)
+        .map(|i| {
+            let transport = LocalTransport {
+                from: i,
+                net: net.clone(),
+            };
+            Arc::new(Raft::new(i, n, Arc::new(transport)))
+        })
+        .collect::<Vec<_>>();
+    *net.nodes.lock().unwrap() = nodes.clone();
+    for node in nodes.iter() {
+        tokio::spawn(node.clone().run());
+    }
+    (net, nodes)
+}
+
+type Leaders = Arc<Mutex<HashMap<u64, HashSet<usize>>>>;
+
+/// Keeps recording which nodes claim to lead which term.
+fn observe(nodes: Vec<Arc<Raft>>) -> (Leaders, JoinHandle<()>) {
+    let leaders: Leaders = Default::default();
+    let seen = leaders.clone();
+    let handle = tokio::spawn(async move {
+        loop {
+            for (i, node) in nodes.iter().enumerate() {
+                let (term, role) = node.status().await;
+                if role == Role::Leader {
+                    seen.lock().unwrap().entry(term).or_default().insert(i);
+                }
+            }
+            time::sleep(Duration::from_millis(5)).await;
+        }
+    });
+    (leaders, handle)
+}
+
+fn assert_one_leader_per_term(leaders: &Leaders) {
+    for (term, ids) in leaders.lock().unwrap().iter() {
+        assert!(ids.len() <= 1, "term {} had leaders {:?}", term, ids);
+    }
+}
+
+/// Waits for one of `among` to become leader and returns it with its term.
+async fn wait_leader(nodes: &[Arc<Raft>], among: &[usize]) -> (usize, u64) {
+    for _ in 0..100 {
+        for &i in among {
+            let (term, role) = nodes[i].status().await;
+            if role == Role::Leader {
+                return (i, term);
+            }
+        }
+        time::sleep(Duration::from_millis(100)).await;
+    }
+    panic!("no leader among {:?}", among);
+}
+
+async fn wait_committed(nodes: &[Arc<Raft>], want: &[&str]) {
+    for _ in 0..100 {
+        let mut all = true;
+        for node in nodes {
+            all &= node.committed().await == want;
+        }
+        if all {
+            return;
+        }
+        time::sleep(Duration::from_millis(100)).await;
+    }
+    for (i, node) in nodes.iter().enumerate() {
+        assert_eq!(node.committed().await, want, "node {}", i);
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_raft_elects_one_leader() -> TribResult<()> {
+    let (_net, nodes) = cluster(5);
+    let (leaders, observer) = observe(nodes.clone());
+
+    let (leader, _) = wait_leader(&nodes, &[0, 1, 2, 3, 4]).await;
+    nodes[leader].propose("first".to_string()).await?;
+    wait_committed(&nodes, &["first"]).await;
+
+    // a follower cannot append to the log
+    let follower = (leader + 1) % nodes.len();
+    if !nodes[follower].is_leader().await {
+        assert!(nodes[follower].propose("nope".to_string()).await.is_err());
+    }
+
+    time::sleep(ELECTION_TIMEOUT_MAX * 2).await;
+    observer.abort();
+    assert_one_leader_per_term(&leaders);
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_raft_partitioned_leader() -> TribResult<()> {
+    let (net, nodes) = cluster(5);
+    let (leaders, observer) = observe(nodes.clone());
+
+    let (old, old_term) = wait_leader(&nodes, &[0, 1, 2, 3, 4]).await;
+    nodes[old].propose("before".to_string()).await?;
+    wait_committed(&nodes, &["before"]).await;
+
+    // cut the leader and one follower off from the other three
+    let minority = vec![old, (old + 1) % 5];
+    let majority = (0..5).filter(|i| !minority.contains(i)).collect::<Vec<_>>();
+    net.partition(&[&minority, &majority]);
+
+    // whatever the old leader accepts now can never commit
+    assert!(nodes[old].propose("lost".to_string()).await.is_err());
+
+    let (new, new_term) = wait_leader(&nodes, &majority).await;
+    assert!(new_term > old_term);
+    nodes[new].propose("after".to_string()).await?;
+
+    net.heal();
+    wait_committed(&nodes, &["before", "after"]).await;
+    observer.abort();
+    assert_one_leader_per_term(&leaders);
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_raft_minority_cannot_elect() -> TribResult<()> {
+    let (net, nodes) = cluster(3);
+    let (leaders, observer) = observe(nodes.clone());
+
+    wait_leader(&nodes, &[0, 1, 2]).await;
+    // isolate every node from every other one
+    net.partition(&[&[0], &[1], &[2]]);
+    time::sleep(ELECTION_TIMEOUT_MAX * 2).await;
+    for node in nodes.iter() {
+        assert!(!node.is_leader().await);
+    }
+
+    net.heal();
+    let (again, _) = wait_leader(&nodes, &[0, 1, 2]).await;
+    nodes[again].propose("healed".to_string()).await?;
+    wait_committed(&nodes, &["healed"]).await;
+    observer.abort();
+    assert_one_leader_per_term(&leaders);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_raft_remembers_across_restart() -> TribResult<()> {
+    let dir = std::env::temp_dir().join(format!("tribbler-raft-{}", rand::random::<u64>()));
+    let open = || async {
+        let transport = LocalTransport {
+            from: 1,
+            net: Arc::new(Net::default()),
+        };
+        Raft::open(
+            1,
+            3,
+            Arc::new(transport),
+            Box::new(DiskStorage::open(&dir)?),
+        )
+        .await
+    };
+    let vote = |candidate| VoteRequest {
+        term: 5,
+        candidate,
+        last_log_index: 0,
+        last_log_term: 0,
+    };
+
+    let node = open().await?;
+    assert!(node.handle_vote(vote(0)).await.granted);
+    let appended = node
+        .handle_append(AppendRequest {
+            term: 5,
+            leader: 0,
+            prev_log_index: 0,
+            prev_log_term: 0,
+            entries: vec![RaftEntry {
+                term: 5,
+                command: "epoch".to_string(),
+            }],
+            leader_commit: 0,
+        })
+        .await;
+    assert!(appended.success);
+    drop(node);
+
+    // the restarted node neither votes again in term 5 nor loses the entry
+    let node = open().await?;
+    assert!(!node.handle_vote(vote(2)).await.granted);
+    assert_eq!(node.status().await.0, 5);
+    let committed = node
+        .handle_append(AppendRequest {
+            term: 5,
+            leader: 0,
+            prev_log_index: 1,
+            prev_log_term: 5,
+            entries: vec![],
+            leader_commit: 1,
+        })
+        .await;
+    assert!(committed.success);
+    assert_eq!(node.committed().await, ["epoch"]);
+    let _ = std::fs::remove_dir_all(&dir);
+    Ok(())
+}
@@ -0,0 +1,119 @@
+//! Compares [MemStorage] with [ShardedStorage] under many tokio tasks
+//! hitting the storage at once, as the backends see while serving timeline
+//! fetches.
+//!
+//! Run with `cargo bench -p tribbler`.
+use std::sync::Arc;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use tokio::runtime::{Builder, Runtime};
+use tribbler::{
+    sharded::ShardedStorage,
+    storage::{KeyValue, MemStorage, Storage},
+};
+
+/// number of tasks running at once
+const TASKS: usize = 64;
+/// operations done by every task per iteration
+const OPS: usize = 100;
+/// number of distinct keys and lists the tasks spread over
+const KEYS: usize = 1024;
+
+#[derive(Clone, Copy)]
+enum Workload {
+    Get,
+    Set,
+    Append,
+    /// mostly list reads with a few appends, like a front end serving
+    /// timelines
+    Timeline,
+}
+
+impl Workload {
+    fn name(self) -> &'static str {
+        match self {
+            Workload::Get => "get",
+            Workload::Set => "set",
+            Workload::Append => "append",
+            Workload::Timeline => "timeline",
+        }
+    }
+}
+
+fn runtime() -> Runtime {
+    Builder::new_multi_thread().enable_all().build().unwrap()
+}
+
+/// Fills `store` with a key and a ten entry list for every key name.
+async fn populate(store: &dyn Storage) {
+    for k in 0..KEYS {
+        let key = format!("k{}", k);
+        store.set(&KeyValue::new(&key, "value")).await.unwrap();
+        let values = (0..10).map(|i| i.to_string()).collect::<Vec<_>>();
+        store.list_append_many(&key, &values).await.unwrap();
+    }
+}
+
+async fn run(store: Arc<dyn Storage>, workload: Workload) {
+    let tasks = (0..TASKS)
+        .map(|t| {
+            let store = store.clone();
+            tokio::spawn(async move {
+                for i in 0..OPS {
+                    let key = format!("k{}", (t * OPS + i * 7) % KEYS);
+                    match workload {
+                        Workload::Get => {
+                            store.get(&key).await.unwrap();
+                        }
+                        Workload::Set => {
+                            store.set(&KeyValue::new(&key, "value")).await.unwrap();
+                        }
+                        Workload::Append => {
+                            let kv = KeyValue::new(&format!("a{}", key), "value");
+                            store.list_append(&kv).await.unwrap();
+                        }
+                        Workload::Timeline if i % 10 == 0 => {
+                            let kv = KeyValue::new(&key, "value");
+                            store.list_append(&kv).await.unwrap();
+                        }
+                        Workload::Timeline => {
+                            store.list_range(&key, -10, -1).await.unwrap();
+                        }
+                    }
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+    for task in tasks {
+        task.await.unwrap();
+    }
+}
+
+fn concurrent(c: &mut Criterion) {
+    let rt = runtime();
+    let mut group = c.benchmark_group("concurrent");
+    group.throughput(Throughput::Elements((TASKS * OPS) as u64));
+    for workload in [
+        Workload::Get,
+        Workload::Set,
+        Workload::Append,
+        Workload::Timeline,
+    ] {
+        let stores: [(&str, Arc<dyn Storage>); 2] = [
+            ("mem", Arc::new(MemStorage::new())),
+            ("sharded", Arc::new(ShardedStorage::new())),
+        ];
+        for (name, store) in stores {
+            rt.block_on(populate(store.as_ref()));
+            group.bench_with_input(
+                BenchmarkId::new(name, workload.name()),
+                &store,
+                |b, store| b.to_async(&rt).iter(|| run(store.clone(), workload)),
+            );
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, concurrent);
+criterion_main!(benches);
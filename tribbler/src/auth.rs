@@ -0,0 +1,286 @@
+//! module which keeps the passwords and login sessions of tribbler users in
+//! a [Storage], so that a front-end can tell who is making a request instead
+//! of trusting the user name the request carries.
+//!
+//! Passwords are kept as salted PBKDF2-HMAC-SHA256 hashes. A session token
+//! is only ever handed to the user who logged in; the storage keeps its
+//! SHA-256 digest, so reading the storage does not let anyone log in.
+use std::num::NonZeroU32;
+use std::time::Duration;
+
+use ring::digest::{digest, SHA256};
+use ring::pbkdf2;
+use ring::rand::{SecureRandom, SystemRandom};
+
+use crate::err::{TribResult, TribblerError};
+use crate::storage::{KeyValue, Storage};
+use crate::trib::{is_valid_username, Server};
+
+/// The name of the bin a front-end keeps its [Accounts] in. It is not a
+/// valid user name, so it cannot clash with a user's bin.
+pub const ACCOUNTS_BIN: &str = "Accounts";
+
+/// How long a session lasts after logging in
+pub const SESSION_TTL: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// PBKDF2 iterations for newly set passwords; the count is stored with each
+/// hash, so it can be raised without breaking existing passwords.
+const ROUNDS: u32 = 100_000;
+const SALT_LEN: usize = 16;
+const TOKEN_LEN: usize = 32;
+const HASH_SCHEME: &str = "pbkdf2-sha256";
+
+/// The passwords and login sessions of the users of one front-end.
+pub struct Accounts {
+    storage: Box<dyn Storage>,
+    rng: SystemRandom,
+}
+
+impl Accounts {
+    /// Keeps the accounts in `storage`, which is usually the
+    /// [ACCOUNTS_BIN] bin.
+    pub fn new(storage: Box<dyn Storage>) -> Accounts {
+        Accounts {
+            storage,
+            rng: SystemRandom::new(),
+        }
+    }
+
+    /// Gives the user `user` the password `password`. This does not look at
+    /// whether `user` exists, so besides [Accounts::sign_up] it is only for
+    /// operators, e.g. to give users made before there were passwords one.
+    ///
+    /// - Returns error when the username is invalid;
+    /// - Returns error when the user already has a password.
+    pub async fn register(&self, user: &str, password: &str) -> TribResult<()> {
+        if !is_valid_username(user) {
+            return Err(Box::new(TribblerError::InvalidUsername(user.to_string())));
+        }
+        if password.is_empty() {
+            return Err(unauthenticated("the password is empty"));
+        }
+        let hash = self.hash(password)?;
+        if !self.storage.cas(&password_key(user), None, &hash).await? {
+            return Err(Box::new(TribblerError::UsernameTaken(user.to_string())));
+        }
+        Ok(())
+    }
+
+    /// Signs `user` up on `server` with the password `password`.
+    ///
+    /// A user who already exists on `server` cannot be signed up again,
+    /// whether or not they have a password: one made before the front-end
+    /// kept accounts gets theirs from an operator through
+    /// [Accounts::register].
+    pub async fn sign_up(&self, server: &dyn Server, user: &str, password: &str) -> TribResult<()> {
+        self.register(user, password).await?;
+        if let Err(e) = server.sign_up(user).await {
+            self.unregister(user).await?;
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// Takes back the password of `user`, e.g. when the user could not be
+    /// signed up after all.
+    pub async fn unregister(&self, user: &str) -> TribResult<()> {
+        self.storage
+            .set(&KeyValue::new(&password_key(user), ""))
+            .await?;
+        Ok(())
+    }
+
+    /// Checks the password of `user` and starts a session for them,
+    /// returning its token.
+    pub async fn login(&self, user: &str, password: &str) -> TribResult<String> {
+        let hash = self.storage.get(&password_key(user)).await?;
+        if !matches!(hash, Some(h) if verify(&h, password)) {
+            return Err(unauthenticated("wrong user name or password"));
+        }
+        let mut token = [0u8; TOKEN_LEN];
+        self.rng.fill(&mut token).map_err(random_failed)?;
+        let token = to_hex(&token);
+        self.storage
+            .set_with_ttl(&KeyValue::new(&session_key(&token), user), SESSION_TTL)
+            .await?;
+        Ok(token)
+    }
+
+    /// The user whose session `token` is, or [None] when there is no such
+    /// session or it has ended.
+    pub async fn user_of(&self, token: &str) -> TribResult<Option<String>> {
+        self.storage.get(&session_key(token)).await
+    }
+
+    /// Like [Accounts::user_of], but fails when there is no session.
+    pub async fn authorize(&self, token: Option<&str>) -> TribResult<String> {
+        let user = match token {
+            Some(token) => self.user_of(token).await?,
+            None => None,
+        };
+        match user {
+            Some(user) => Ok(user),
+            None => Err(unauthenticated("not logged in")),
+        }
+    }
+
+    /// Ends the session `token`.
+    pub async fn logout(&self, token: &str) -> TribResult<()> {
+        self.storage
+            .set(&KeyValue::new(&session_key(token), ""))
+            .await?;
+        Ok(())
+    }
+
+    fn hash(&self, password: &str) -> TribResult<String> {
+        let mut salt = [0u8; SALT_LEN];
+        self.rng.fill(&mut salt).map_err(random_failed)?;
+        let mut hash = [0u8; 32];
+        pbkdf2::derive(
+            pbkdf2::PBKDF2_HMAC_SHA256,
+            NonZeroU32::new(ROUNDS).unwrap(),
+            &salt,
+            password.as_bytes(),
+            &mut hash,
+        );
+        Ok(format!(
+            "{}${}${}${}",
+            HASH_SCHEME,
+            ROUNDS,
+            to_hex(&salt),
+            to_hex(&hash)
+        ))
+    }
+}
+
+/// Whether `password` matches `stored`, a hash made by [Accounts::hash].
+fn verify(stored: &str, password: &str) -> bool {
+    let parts: Vec<&str> = stored.split('$').collect();
+    let (rounds, salt, hash) = match parts[..] {
+        [HASH_SCHEME, rounds, salt, hash] => (rounds, salt, hash),
+        _ => return false,
+    };
+    let (rounds, salt, hash) = match (
+        rounds.parse().ok().and_then(NonZeroU32::new),
+        from_hex(salt),
+        from_hex(hash),
+    ) {
+        (Some(r), Some(s), Some(h)) => (r, s, h),
+        _ => return false,
+    };
+    pbkdf2::verify(
+        pbkdf2::PBKDF2_HMAC_SHA256,
+        rounds,
+        &salt,
+        password.as_bytes(),
+        &hash,
+    )
+    .is_ok()
+}
+
+fn password_key(user: &str) -> String {
+    format!("password:{}", user)
+}
+
+fn session_key(token: &str) -> String {
+    format!(
+        "session:{}",
+        to_hex(digest(&SHA256, token.as_bytes()).as_ref())
+    )
+}
+
+fn unauthenticated(why: &str) -> Box<TribblerError> {
+    Box::new(TribblerError::Unauthenticated(why.to_string()))
+}
+
+fn random_failed(_: ring::error::Unspecified) -> Box<TribblerError> {
+    Box::new(TribblerError::Unknown(
+        "no randomness for the session".to_string(),
+    ))
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn from_hex(s: &str) -> Option<Vec<u8>> {
+    s.as_bytes()
+        .chunks(2)
+        .map(|c| match std::str::from_utf8(c) {
+            Ok(h) if h.len() == 2 => u8::from_str_radix(h, 16).ok(),
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::Accounts;
+    use crate::err::{TribResult, TribblerError};
+    use crate::ref_impl::RefServer;
+    use crate::storage::MemStorage;
+    use crate::trib::Server;
+
+    fn unauthenticated(r: TribResult<String>) -> bool {
+        matches!(
+            r.unwrap_err().downcast_ref::<TribblerError>(),
+            Some(TribblerError::Unauthenticated(_))
+        )
+    }
+
+    #[tokio::test]
+    async fn accounts_login() -> TribResult<()> {
+        let accounts = Accounts::new(Box::new(MemStorage::new()));
+        accounts.register("alice", "secret").await?;
+        assert!(accounts.register("alice", "other").await.is_err());
+        assert!(accounts.register("Alice", "secret").await.is_err());
+        assert!(unauthenticated(accounts.login("alice", "Secret").await));
+        assert!(unauthenticated(accounts.login("bob", "secret").await));
+        assert!(unauthenticated(accounts.authorize(None).await));
+
+        let token = accounts.login("alice", "secret").await?;
+        assert_eq!(Some("alice".to_string()), accounts.user_of(&token).await?);
+        assert_eq!("alice", accounts.authorize(Some(&token)).await?);
+        assert_eq!(None, accounts.user_of("forged").await?);
+        // neither the password nor the token is stored as such
+        let storage = &accounts.storage;
+        for key in storage.keys(&Default::default()).await?.0 {
+            let value = storage.get(&key).await?.unwrap();
+            assert!(!key.contains(&token) && !value.contains("secret"));
+        }
+
+        accounts.logout(&token).await?;
+        assert!(unauthenticated(accounts.authorize(Some(&token)).await));
+        accounts.unregister("alice").await?;
+        accounts.register("alice", "again").await?;
+        assert!(unauthenticated(accounts.login("alice", "secret").await));
+        accounts.login("alice", "again").await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn accounts_sign_up() -> TribResult<()> {
+        let accounts = Accounts::new(Box::new(MemStorage::new()));
+        let server = RefServer::new();
+        accounts.sign_up(&server, "alice", "secret").await?;
+        assert_eq!(server.list_users().await?, vec!["alice"]);
+        // the name is taken, and so is its password
+        assert!(accounts.sign_up(&server, "alice", "other").await.is_err());
+        assert!(unauthenticated(accounts.login("alice", "other").await));
+
+        // nobody can claim a user from before there were passwords by
+        // signing up as them; only an operator can give them one
+        server.sign_up("bob").await?;
+        assert!(unauthenticated(accounts.login("bob", "").await));
+        assert!(accounts.sign_up(&server, "bob", "mine").await.is_err());
+        assert!(unauthenticated(accounts.login("bob", "mine").await));
+        accounts.register("bob", "hunter2").await?;
+        accounts.login("bob", "hunter2").await?;
+        assert!(accounts.sign_up(&server, "bob", "mine").await.is_err());
+        accounts.login("bob", "hunter2").await?;
+
+        // nothing is kept for a user the server turns down
+        assert!(accounts.sign_up(&server, "Carol", "x").await.is_err());
+        Ok(())
+    }
+}
@@ -38,9 +38,85 @@ pub fn unescape<T: Into<String>>(s: T) -> String {
     out.into_iter().collect()
 }
 
+/// Reverses [escape], failing on strings that [escape] never returns: ones
+/// with a colon, or with a `|` not followed by `|` or `;`.
+fn unescape_exact(s: &str) -> Option<String> {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(x) = chars.next() {
+        match x {
+            '|' => match chars.next()? {
+                '|' => out.push('|'),
+                ';' => out.push(':'),
+                _ => return None,
+            },
+            ':' => return None,
+            x => out.push(x),
+        }
+    }
+    Some(out)
+}
+
+/// Separates the bin from the key in a [BinKey]. Escaped strings have no
+/// colons, so the first separator in a physical key is always this one.
+const BIN_SEPARATOR: &str = "::";
+
+/// The key `key` of the bin `bin`, and the physical key it is stored under
+/// on a backend shared by many bins.
+///
+/// ```rust
+/// use tribbler::colon::BinKey;
+/// let k = BinKey::new("al:ice", "trib::1");
+/// assert_eq!("al|;ice::trib|;|;1", k.encode());
+/// assert_eq!(Some(k), BinKey::decode("al|;ice::trib|;|;1"));
+/// assert_eq!(None, BinKey::decode("no separator"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BinKey {
+    pub bin: String,
+    pub key: String,
+}
+
+impl BinKey {
+    pub fn new(bin: &str, key: &str) -> BinKey {
+        BinKey {
+            bin: bin.to_string(),
+            key: key.to_string(),
+        }
+    }
+
+    /// The physical key, `escape(bin)::escape(key)`.
+    pub fn encode(&self) -> String {
+        let mut physical = BinKey::prefix(&self.bin);
+        physical.push_str(&escape(&self.key));
+        physical
+    }
+
+    /// What every physical key of the bin `bin` starts with. Keys of the bin
+    /// starting with `p` are the physical keys starting with
+    /// `BinKey::new(bin, p).encode()`.
+    pub fn prefix(bin: &str) -> String {
+        let mut prefix = escape(bin);
+        prefix.push_str(BIN_SEPARATOR);
+        prefix
+    }
+
+    /// The bin and key of the physical key `physical`, or [None] if
+    /// [BinKey::encode] never returns it.
+    pub fn decode(physical: &str) -> Option<BinKey> {
+        let (bin, key) = physical.split_once(BIN_SEPARATOR)?;
+        Some(BinKey {
+            bin: unescape_exact(bin)?,
+            key: unescape_exact(key)?,
+        })
+    }
+}
+
 #[cfg(test)]
 mod test {
-    use super::{escape, unescape};
+    use proptest::prelude::*;
+
+    use super::{escape, unescape, BinKey};
 
     fn check(s: &str) {
         assert_eq!(unescape(escape(s)), s);
@@ -78,4 +154,40 @@ mod test {
     fn t8() {
         check("::||::||;;||;;||;:");
     }
+
+    #[test]
+    fn bin_key_rejects_foreign_keys() {
+        for physical in ["", "bin", "a:b", "a::b::c", "a::b|", "a|x::b", ":::"] {
+            assert_eq!(None, BinKey::decode(physical), "{:?}", physical);
+        }
+        let k = BinKey::new("", "");
+        assert_eq!(Some(k.clone()), BinKey::decode(&k.encode()));
+    }
+
+    proptest! {
+        #[test]
+        fn escape_round_trips(s in ".*") {
+            prop_assert_eq!(unescape(escape(&s)), s);
+        }
+
+        #[test]
+        fn bin_key_round_trips(bin in ".*", key in ".*") {
+            let k = BinKey::new(&bin, &key);
+            let physical = k.encode();
+            prop_assert!(physical.starts_with(&BinKey::prefix(&bin)));
+            prop_assert_eq!(BinKey::decode(&physical), Some(k));
+        }
+
+        #[test]
+        fn bin_keys_never_collide(a in "[a:|;]*", b in "[a:|;]*", c in "[a:|;]*", d in "[a:|;]*") {
+            let (x, y) = (BinKey::new(&a, &b), BinKey::new(&c, &d));
+            prop_assert_eq!(x == y, x.encode() == y.encode());
+        }
+
+        #[test]
+        fn bin_prefix_only_matches_own_keys(bin in "[a:|;]*", other in "[a:|;]*", key in ".*") {
+            let physical = BinKey::new(&other, &key).encode();
+            prop_assert_eq!(bin == other, physical.starts_with(&BinKey::prefix(&bin)));
+        }
+    }
 }
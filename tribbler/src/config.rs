@@ -2,6 +2,7 @@
 //! module containing configuration functions which can aid in configuring
 //! and running the tribbler service.
 
+use std::collections::BTreeMap;
 use std::fs;
 use std::io::{stdout, Write};
 use std::sync::mpsc::Sender;
@@ -9,12 +10,24 @@ use std::time::SystemTime;
 
 use serde::{Deserialize, Serialize};
 use tokio::sync::mpsc::Receiver;
+use tonic::transport::{Certificate, ClientTlsConfig, Identity, ServerTlsConfig};
 
-use crate::err::TribResult;
-use crate::storage::Storage;
+use crate::err::{TribResult, TribblerError};
+use crate::storage::{MemoryLimit, Storage};
 
 pub const DEFAULT_CONFIG_LOCATION: &str = "bins.json";
 
+/// number of backends keeping a copy of every bin unless configured otherwise
+pub const DEFAULT_REPLICATION: usize = 2;
+
+/// virtual nodes every backend is placed at on the hash ring unless
+/// configured otherwise
+pub const DEFAULT_VNODES: usize = 64;
+
+/// The name every backend and keeper certificate is issued for, and which
+/// clients check for instead of the address they connect to.
+pub const TLS_DOMAIN: &str = "tribbler";
+
 /// a struct which represents the configuration for a particular storage backend
 pub struct BackConfig {
     /// the address `<host>:<port>` combination to serve on
@@ -31,6 +44,12 @@ pub struct BackConfig {
     /// graceful shutdown of the server. If no channel is present, then
     /// no graceful shutdown mechanism needs to be implemented.
     pub shutdown: Option<Receiver<()>>,
+    /// the memory limit to put on the storage before serving, if any. See
+    /// [Storage::set_memory_limit].
+    pub memory_limit: Option<MemoryLimit>,
+    /// when set, only serve over TLS to clients with a certificate from
+    /// the same authority
+    pub tls: Option<TlsConfig>,
 }
 
 use std::fmt::Debug;
@@ -41,6 +60,8 @@ impl Debug for BackConfig {
             .field("addr", &self.addr)
             .field("ready", &self.ready)
             .field("shutdown", &self.shutdown)
+            .field("memory_limit", &self.memory_limit)
+            .field("tls", &self.tls)
             .finish()
     }
 }
@@ -56,6 +77,14 @@ pub struct KeeperConfig {
     pub this: usize,
     /// Non zero incarnation identifier
     pub id: u128,
+    /// how many copies of every bin the keeper maintains
+    pub replication: ReplicationConfig,
+    /// when set, serve and connect to the back-ends and other keepers over
+    /// TLS only
+    pub tls: Option<TlsConfig>,
+    /// when set, the directory the keeper keeps its raft term, vote and log
+    /// in, so that they survive a restart
+    pub data_dir: Option<String>,
     /// Send a value when the keeper is ready. The distributed key-value
     /// service should be ready to serve when *any* of the keepers is
     /// ready.
@@ -72,11 +101,203 @@ impl KeeperConfig {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+/// How many backends hold a copy of every bin, and how many of those copies
+/// have to answer before a read or a write counts as successful.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReplicationConfig {
+    /// number of replicas of every bin
+    pub factor: usize,
+    /// replicas that must answer a read
+    pub read_quorum: usize,
+    /// replicas that must accept a write
+    pub write_quorum: usize,
+    /// points every backend is placed at on the hash ring; the bin clients
+    /// and the keepers must agree on it, or they disagree on where a bin
+    /// lives
+    #[serde(default = "default_vnodes")]
+    pub vnodes: usize,
+}
+
+impl ReplicationConfig {
+    /// builds a config keeping `factor` replicas on a ring of
+    /// [DEFAULT_VNODES]; both quorums are clamped into `1..=factor`.
+    pub fn new(factor: usize, read_quorum: usize, write_quorum: usize) -> ReplicationConfig {
+        let factor = factor.max(1);
+        ReplicationConfig {
+            factor,
+            read_quorum: read_quorum.clamp(1, factor),
+            write_quorum: write_quorum.clamp(1, factor),
+            vnodes: DEFAULT_VNODES,
+        }
+    }
+
+    /// the same config on a ring with `vnodes` points per backend, at
+    /// least one.
+    pub fn with_vnodes(self, vnodes: usize) -> ReplicationConfig {
+        ReplicationConfig {
+            vnodes: vnodes.max(1),
+            ..self
+        }
+    }
+
+    /// Fails unless every read quorum overlaps every write quorum, that is
+    /// unless a read always reaches at least one replica that accepted the
+    /// last write.
+    pub fn check(&self) -> TribResult<()> {
+        if self.read_quorum + self.write_quorum <= self.factor {
+            return Err(Box::new(TribblerError::Unknown(format!(
+                "read quorum {} and write quorum {} do not overlap among {} replicas",
+                self.read_quorum, self.write_quorum, self.factor
+            ))));
+        }
+        Ok(())
+    }
+}
+
+impl Default for ReplicationConfig {
+    /// [DEFAULT_REPLICATION] replicas, where any single one of them is
+    /// enough to answer a read, but every one of them has to accept a write.
+    fn default() -> Self {
+        ReplicationConfig::new(DEFAULT_REPLICATION, 1, DEFAULT_REPLICATION)
+    }
+}
+
+/// A token bucket: up to `burst` calls may be made at once, and after that
+/// `per_minute` calls a minute.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    /// calls a minute the bucket refills with
+    pub per_minute: u32,
+    /// calls the bucket holds when full
+    pub burst: u32,
+}
+
+/// How often every user may call the front-end operations that write;
+/// [None] leaves an operation unlimited.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RateLimits {
+    /// [post](crate::trib::Server::post)s of every user
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub post: Option<RateLimit>,
+    /// [follow](crate::trib::Server::follow)s of every user
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub follow: Option<RateLimit>,
+    /// [sign_up](crate::trib::Server::sign_up)s, of all users together
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sign_up: Option<RateLimit>,
+}
+
+/// Paths of a PEM certificate and of its PEM private key
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct CertFiles {
+    pub cert: String,
+    pub key: String,
+}
+
+impl CertFiles {
+    fn identity(&self) -> TribResult<Identity> {
+        Ok(Identity::from_pem(
+            fs::read(&self.cert)?,
+            fs::read(&self.key)?,
+        ))
+    }
+}
+
+/// Where the certificates for mutually authenticated TLS between the
+/// back-ends, the keepers and their clients are. Every certificate is signed
+/// by the same authority, and nothing else is trusted on either side.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct TlsConfig {
+    /// PEM certificate of the authority
+    pub ca: String,
+    /// what every back-end and keeper presents to its clients, by address;
+    /// issued for [TLS_DOMAIN]
+    pub nodes: BTreeMap<String, CertFiles>,
+    /// what every client presents to the back-ends and keepers, including
+    /// the keepers themselves
+    pub client: CertFiles,
+}
+
+impl TlsConfig {
+    fn ca(&self) -> TribResult<Certificate> {
+        Ok(Certificate::from_pem(fs::read(&self.ca)?))
+    }
+
+    /// The TLS settings for serving on `addr`, which only let in clients
+    /// with a certificate from the authority.
+    pub fn server(&self, addr: &str) -> TribResult<ServerTlsConfig> {
+        let node = self
+            .nodes
+            .get(addr)
+            .ok_or_else(|| TribblerError::Unknown(format!("no TLS certificate for {}", addr)))?;
+        Ok(ServerTlsConfig::new()
+            .identity(node.identity()?)
+            .client_ca_root(self.ca()?))
+    }
+
+    /// The TLS settings for connecting to any back-end or keeper.
+    pub fn client(&self) -> TribResult<ClientTlsConfig> {
+        Ok(ClientTlsConfig::new()
+            .domain_name(TLS_DOMAIN)
+            .ca_certificate(self.ca()?)
+            .identity(self.client.identity()?))
+    }
+}
+
+fn default_replication() -> usize {
+    DEFAULT_REPLICATION
+}
+
+fn default_quorum() -> usize {
+    1
+}
+
+fn default_vnodes() -> usize {
+    DEFAULT_VNODES
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 /// A config file defining the backend and keeper network addresses
 pub struct Config {
     pub backs: Vec<String>,
     pub keepers: Vec<String>,
+    /// how many backends keep a copy of every bin
+    #[serde(default = "default_replication")]
+    pub replication: usize,
+    /// how many replicas must answer a read
+    #[serde(default = "default_quorum")]
+    pub read_quorum: usize,
+    /// how many replicas must accept a write; every one of them when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub write_quorum: Option<usize>,
+    /// how many points every backend is placed at on the hash ring
+    #[serde(default = "default_vnodes")]
+    pub vnodes: usize,
+    /// the memory limit of every backend, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memory_limit: Option<MemoryLimit>,
+    /// the certificates to secure every connection with, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tls: Option<TlsConfig>,
+    /// how often the front-ends let users write
+    #[serde(default)]
+    pub rate_limits: RateLimits,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            backs: vec![],
+            keepers: vec![],
+            replication: DEFAULT_REPLICATION,
+            read_quorum: default_quorum(),
+            write_quorum: None,
+            vnodes: DEFAULT_VNODES,
+            memory_limit: None,
+            tls: None,
+            rate_limits: RateLimits::default(),
+        }
+    }
 }
 
 impl Config {
@@ -126,6 +347,13 @@ impl Config {
         self.keepers.len()
     }
 
+    /// gets the replication settings, with the quorums clamped into range.
+    pub fn replication_config(&self) -> ReplicationConfig {
+        let write_quorum = self.write_quorum.unwrap_or(self.replication);
+        ReplicationConfig::new(self.replication, self.read_quorum, write_quorum)
+            .with_vnodes(self.vnodes)
+    }
+
     /// build a [BackConfig] for the given index `i` in the list of backend
     /// addresses. `i` must be a valid index in the list of backends.
     ///
@@ -144,6 +372,8 @@ impl Config {
             storage: store,
             ready,
             shutdown,
+            memory_limit: self.memory_limit,
+            tls: self.tls.clone(),
         }
     }
 
@@ -166,6 +396,9 @@ impl Config {
             id: SystemTime::now()
                 .duration_since(std::time::UNIX_EPOCH)?
                 .as_nanos(),
+            replication: self.replication_config(),
+            tls: self.tls.clone(),
+            data_dir: None,
             ready,
             shutdown,
         })
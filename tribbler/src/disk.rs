@@ -0,0 +1,723 @@
+//! module containing a durable implementation of the [Storage] trait which
+//! keeps its state in a directory on disk.
+//!
+//! Every mutating call is first appended to a write-ahead log (`wal.log`)
+//! and only then applied to an in-memory [MemStorage]. Every
+//! [DEFAULT_SNAPSHOT_INTERVAL] logged operations the whole state is written
+//! to `snapshot.json` and the log is truncated. On startup the snapshot is
+//! loaded and the log is replayed on top of it.
+//!
+//! Expiry deadlines are stored as wall-clock times, so keys set with a TTL
+//! still expire on time after a restart.
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::{self, File, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::{
+    err::TribResult,
+    storage::{
+        deadline, now_ms, text, text_list, Expiry, KeyList, KeyPage, KeyString, KeyValue, List,
+        MemDump, MemStorage, Pattern, Storage, StorageStats, Txn, TxnOp, TxnResult, Watch,
+    },
+};
+
+/// number of logged operations after which a new snapshot is taken
+pub const DEFAULT_SNAPSHOT_INTERVAL: usize = 10_000;
+
+const WAL_FILE: &str = "wal.log";
+const SNAPSHOT_FILE: &str = "snapshot.json";
+const SNAPSHOT_TMP_FILE: &str = "snapshot.json.tmp";
+
+/// A single mutating operation as recorded in the write-ahead log
+#[derive(Serialize, Deserialize, Debug, Clone)]
+enum WalRecord {
+    Set(String, String),
+    ListAppend(String, String),
+    ListRemove(String, String),
+    /// a trim down to the given number of last elements
+    ListTrim(String, u64),
+    /// a set that expires at the given deadline
+    SetWithTtl(String, String, u64),
+    /// an append made at the given time, with the deadline it gave the list
+    /// if any
+    ListAppendAt(String, String, u64, Option<u64>),
+    /// a clock value that was handed out to a caller
+    Clock(u64),
+    /// the changes of a transaction, which are replayed all or not at all
+    Txn(Vec<WalRecord>),
+}
+
+/// The full contents of a storage at the time of a snapshot
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct Snapshot {
+    kvs: BTreeMap<String, String>,
+    kv_list: BTreeMap<String, Vec<String>>,
+    /// expiry deadlines of keys in `kvs`
+    #[serde(default)]
+    expires: HashMap<String, u64>,
+    /// expiry deadlines of lists in `kv_list`
+    #[serde(default)]
+    list_expires: HashMap<String, u64>,
+    /// the next clock value the storage may hand out
+    clock: u64,
+}
+
+impl Snapshot {
+    /// replays a single logged operation with the same semantics as
+    /// [MemStorage]
+    fn apply(&mut self, record: WalRecord) {
+        match record {
+            WalRecord::Set(key, value) => {
+                self.expires.remove(&key);
+                if value.is_empty() {
+                    self.kvs.remove(&key);
+                } else {
+                    self.kvs.insert(key, value);
+                }
+            }
+            WalRecord::SetWithTtl(key, value, deadline) => {
+                if value.is_empty() {
+                    self.expires.remove(&key);
+                    self.kvs.remove(&key);
+                } else {
+                    self.expires.insert(key.clone(), deadline);
+                    self.kvs.insert(key, value);
+                }
+            }
+            WalRecord::ListAppend(key, value) => {
+                self.kv_list.entry(key).or_default().push(value);
+            }
+            WalRecord::ListAppendAt(key, value, at, deadline) => {
+                if matches!(self.list_expires.get(&key), Some(d) if *d <= at) {
+                    self.kv_list.remove(&key);
+                    self.list_expires.remove(&key);
+                }
+                if let Some(d) = deadline {
+                    self.list_expires.insert(key.clone(), d);
+                }
+                self.kv_list.entry(key).or_default().push(value);
+            }
+            WalRecord::ListRemove(key, value) => {
+                if let Some(list) = self.kv_list.get_mut(&key) {
+                    list.retain(|x| *x != value);
+                    if list.is_empty() {
+                        self.kv_list.remove(&key);
+                        self.list_expires.remove(&key);
+                    }
+                }
+            }
+            WalRecord::ListTrim(key, keep_last) => {
+                if let Some(list) = self.kv_list.get_mut(&key) {
+                    let drop = list.len().saturating_sub(keep_last as usize);
+                    list.drain(..drop);
+                    if list.is_empty() {
+                        self.kv_list.remove(&key);
+                        self.list_expires.remove(&key);
+                    }
+                }
+            }
+            WalRecord::Clock(c) => {
+                self.clock = self.clock.max(c.saturating_add(1));
+            }
+            WalRecord::Txn(records) => {
+                for record in records {
+                    self.apply(record);
+                }
+            }
+        }
+    }
+}
+
+struct Wal {
+    file: File,
+    /// operations logged since the last snapshot
+    pending: usize,
+}
+
+/// A [Storage] implementation that survives restarts. See the [module
+/// documentation](self) for the on-disk layout.
+///
+/// It reports its [Storage::stats] but cannot be given a memory limit, and
+/// it only holds text: its log and snapshots are JSON, so it offers no
+/// [Storage::bytes].
+pub struct DiskStorage {
+    dir: PathBuf,
+    mem: MemStorage,
+    wal: Mutex<Wal>,
+    snapshot_interval: usize,
+}
+
+impl DiskStorage {
+    /// Opens (creating it if needed) the storage kept in directory `dir`,
+    /// recovering any state left there by a previous run.
+    pub fn open<P: AsRef<Path>>(dir: P) -> TribResult<DiskStorage> {
+        DiskStorage::with_snapshot_interval(dir, DEFAULT_SNAPSHOT_INTERVAL)
+    }
+
+    /// Same as [DiskStorage::open], but takes a snapshot every `interval`
+    /// logged operations instead of every [DEFAULT_SNAPSHOT_INTERVAL].
+    pub fn with_snapshot_interval<P: AsRef<Path>>(
+        dir: P,
+        interval: usize,
+    ) -> TribResult<DiskStorage> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)?;
+
+        let mut state = match fs::read(dir.join(SNAPSHOT_FILE)) {
+            Ok(contents) => serde_json::from_slice::<Snapshot>(&contents)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Snapshot::default(),
+            Err(e) => return Err(Box::new(e)),
+        };
+
+        let mut pending = 0;
+        let wal_path = dir.join(WAL_FILE);
+        if wal_path.exists() {
+            for line in BufReader::new(File::open(&wal_path)?).lines() {
+                // a torn record at the tail means we crashed while it was
+                // being written, so the operation never took effect
+                match serde_json::from_str::<WalRecord>(&line?) {
+                    Ok(record) => state.apply(record),
+                    Err(_) => break,
+                }
+                pending += 1;
+            }
+        }
+
+        let kv_list = state
+            .kv_list
+            .into_iter()
+            .map(|(k, v)| {
+                (
+                    k.into_bytes(),
+                    v.into_iter().map(String::into_bytes).collect(),
+                )
+            })
+            .collect();
+        let mem = MemStorage::restore(MemDump {
+            kvs: state
+                .kvs
+                .into_iter()
+                .map(|(k, v)| (k.into_bytes(), v.into_bytes()))
+                .collect(),
+            kv_list,
+            expiry: Expiry {
+                kvs: deadlines_of(state.expires),
+                lists: deadlines_of(state.list_expires),
+            },
+            clock: state.clock,
+        });
+        let mut wal = Wal {
+            file: OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&wal_path)?,
+            pending,
+        };
+        // fold whatever was replayed into a fresh snapshot so that a torn
+        // tail can never be followed by valid records
+        write_snapshot(&dir, &mem, &mut wal)?;
+        Ok(DiskStorage {
+            dir,
+            mem,
+            wal: Mutex::new(wal),
+            snapshot_interval: interval.max(1),
+        })
+    }
+
+    /// Forces a snapshot of the current state and truncates the log.
+    pub async fn snapshot(&self) -> TribResult<()> {
+        let mut wal = self.wal.lock().await;
+        write_snapshot(&self.dir, &self.mem, &mut wal)
+    }
+
+    /// appends `record` to the log and makes sure it reached the disk
+    fn log(&self, wal: &mut Wal, record: &WalRecord) -> TribResult<()> {
+        self.log_all(wal, std::slice::from_ref(record))
+    }
+
+    /// appends all of `records` to the log with a single sync
+    fn log_all(&self, wal: &mut Wal, records: &[WalRecord]) -> TribResult<()> {
+        let mut lines = vec![];
+        for record in records {
+            lines.extend(serde_json::to_vec(record)?);
+            lines.push(b'\n');
+        }
+        wal.file.write_all(&lines)?;
+        wal.file.sync_data()?;
+        wal.pending += records.len();
+        Ok(())
+    }
+
+    /// logs and applies a single list append, which gives the list a new
+    /// deadline when `ttl` is set
+    async fn append(&self, kv: &KeyValue, ttl: Option<Duration>) -> TribResult<bool> {
+        let mut wal = self.wal.lock().await;
+        let at = now_ms();
+        let d = ttl.map(|ttl| deadline(at, ttl));
+        self.log(
+            &mut wal,
+            &WalRecord::ListAppendAt(kv.key.clone(), kv.value.clone(), at, d),
+        )?;
+        let r = self
+            .mem
+            .list_push(kv.key.as_bytes(), std::slice::from_ref(&kv.value), at, d)?;
+        self.maybe_snapshot(&mut wal)?;
+        Ok(r)
+    }
+
+    async fn append_many(
+        &self,
+        key: &str,
+        values: &[String],
+        ttl: Option<Duration>,
+    ) -> TribResult<bool> {
+        let mut wal = self.wal.lock().await;
+        // the replayed appends have to see the list as expired (or not)
+        // exactly when the live one did, so they all carry the same time
+        let at = now_ms();
+        let d = ttl.map(|ttl| deadline(at, ttl));
+        let records = values
+            .iter()
+            .map(|v| WalRecord::ListAppendAt(key.to_string(), v.to_string(), at, d))
+            .collect::<Vec<WalRecord>>();
+        self.log_all(&mut wal, &records)?;
+        let r = self.mem.list_push(key.as_bytes(), values, at, d)?;
+        self.maybe_snapshot(&mut wal)?;
+        Ok(r)
+    }
+
+    fn maybe_snapshot(&self, wal: &mut Wal) -> TribResult<()> {
+        if wal.pending >= self.snapshot_interval {
+            write_snapshot(&self.dir, &self.mem, wal)?;
+        }
+        Ok(())
+    }
+}
+
+/// writes the contents of `mem` to the snapshot file in `dir`, then empties
+/// the log
+fn write_snapshot(dir: &Path, mem: &MemStorage, wal: &mut Wal) -> TribResult<()> {
+    // everything in the log went in as text, so it all comes out as text
+    let dump = mem.dump()?;
+    let mut kvs = BTreeMap::new();
+    for (k, v) in dump.kvs {
+        kvs.insert(text(k)?, text(v)?);
+    }
+    let mut kv_list = BTreeMap::new();
+    for (k, l) in dump.kv_list {
+        kv_list.insert(text(k)?, text_list(l)?.0);
+    }
+    let snapshot = Snapshot {
+        kvs,
+        kv_list,
+        expires: text_deadlines(dump.expiry.kvs)?,
+        list_expires: text_deadlines(dump.expiry.lists)?,
+        clock: dump.clock,
+    };
+    let tmp = dir.join(SNAPSHOT_TMP_FILE);
+    let mut f = File::create(&tmp)?;
+    f.write_all(&serde_json::to_vec(&snapshot)?)?;
+    f.sync_all()?;
+    fs::rename(&tmp, dir.join(SNAPSHOT_FILE))?;
+
+    wal.file.set_len(0)?;
+    wal.file.sync_all()?;
+    wal.pending = 0;
+    Ok(())
+}
+
+fn deadlines_of(deadlines: HashMap<String, u64>) -> HashMap<Vec<u8>, u64> {
+    deadlines
+        .into_iter()
+        .map(|(k, d)| (k.into_bytes(), d))
+        .collect()
+}
+
+fn text_deadlines(deadlines: HashMap<Vec<u8>, u64>) -> TribResult<HashMap<String, u64>> {
+    deadlines
+        .into_iter()
+        .map(|(k, d)| Ok((text(k)?, d)))
+        .collect()
+}
+
+#[async_trait]
+impl KeyString for DiskStorage {
+    async fn get(&self, key: &str) -> TribResult<Option<String>> {
+        self.mem.get(key).await
+    }
+
+    async fn set(&self, kv: &KeyValue) -> TribResult<bool> {
+        let mut wal = self.wal.lock().await;
+        self.log(&mut wal, &WalRecord::Set(kv.key.clone(), kv.value.clone()))?;
+        let r = self.mem.set(kv).await?;
+        self.maybe_snapshot(&mut wal)?;
+        Ok(r)
+    }
+
+    async fn set_with_ttl(&self, kv: &KeyValue, ttl: Duration) -> TribResult<bool> {
+        let mut wal = self.wal.lock().await;
+        let d = deadline(now_ms(), ttl);
+        self.log(
+            &mut wal,
+            &WalRecord::SetWithTtl(kv.key.clone(), kv.value.clone(), d),
+        )?;
+        let r = self
+            .mem
+            .set_until(kv.key.as_bytes(), kv.value.as_bytes(), Some(d))?;
+        self.maybe_snapshot(&mut wal)?;
+        Ok(r)
+    }
+
+    async fn ttl(&self, key: &str) -> TribResult<Option<Duration>> {
+        self.mem.ttl(key).await
+    }
+
+    async fn cas(&self, key: &str, expected: Option<String>, new: &str) -> TribResult<bool> {
+        // holding the log lock keeps the compare and the swap atomic with
+        // respect to every other write
+        let mut wal = self.wal.lock().await;
+        let current = self.mem.get(key).await?.unwrap_or_default();
+        if current != expected.unwrap_or_default() {
+            return Ok(false);
+        }
+        let kv = KeyValue::new(key, new);
+        self.log(&mut wal, &WalRecord::Set(kv.key.clone(), kv.value.clone()))?;
+        self.mem.set(&kv).await?;
+        self.maybe_snapshot(&mut wal)?;
+        Ok(true)
+    }
+
+    async fn keys(&self, p: &Pattern) -> TribResult<List> {
+        self.mem.keys(p).await
+    }
+
+    async fn keys_page(&self, p: &Pattern, start_after: &str, limit: usize) -> TribResult<KeyPage> {
+        self.mem.keys_page(p, start_after, limit).await
+    }
+
+    async fn multi_get(&self, keys: &[String]) -> TribResult<Vec<Option<String>>> {
+        self.mem.multi_get(keys).await
+    }
+}
+
+#[async_trait]
+impl KeyList for DiskStorage {
+    async fn list_get(&self, key: &str) -> TribResult<List> {
+        self.mem.list_get(key).await
+    }
+
+    async fn list_append(&self, kv: &KeyValue) -> TribResult<bool> {
+        self.append(kv, None).await
+    }
+
+    async fn list_append_with_ttl(&self, kv: &KeyValue, ttl: Duration) -> TribResult<bool> {
+        self.append(kv, Some(ttl)).await
+    }
+
+    async fn list_ttl(&self, key: &str) -> TribResult<Option<Duration>> {
+        self.mem.list_ttl(key).await
+    }
+
+    async fn list_remove(&self, kv: &KeyValue) -> TribResult<u32> {
+        let mut wal = self.wal.lock().await;
+        self.log(
+            &mut wal,
+            &WalRecord::ListRemove(kv.key.clone(), kv.value.clone()),
+        )?;
+        let r = self.mem.list_remove(kv).await?;
+        self.maybe_snapshot(&mut wal)?;
+        Ok(r)
+    }
+
+    async fn list_trim(&self, key: &str, keep_last: u64) -> TribResult<u32> {
+        let mut wal = self.wal.lock().await;
+        self.log(&mut wal, &WalRecord::ListTrim(key.to_string(), keep_last))?;
+        let r = self.mem.list_trim(key, keep_last).await?;
+        self.maybe_snapshot(&mut wal)?;
+        Ok(r)
+    }
+
+    async fn list_len(&self, key: &str) -> TribResult<u64> {
+        self.mem.list_len(key).await
+    }
+
+    async fn list_range(&self, key: &str, start: i64, end: i64) -> TribResult<List> {
+        self.mem.list_range(key, start, end).await
+    }
+
+    async fn multi_list_range(
+        &self,
+        keys: &[String],
+        start: i64,
+        end: i64,
+    ) -> TribResult<Vec<List>> {
+        self.mem.multi_list_range(keys, start, end).await
+    }
+
+    async fn list_keys(&self, p: &Pattern) -> TribResult<List> {
+        self.mem.list_keys(p).await
+    }
+
+    async fn list_keys_page(
+        &self,
+        p: &Pattern,
+        start_after: &str,
+        limit: usize,
+    ) -> TribResult<KeyPage> {
+        self.mem.list_keys_page(p, start_after, limit).await
+    }
+
+    async fn multi_list_get(&self, keys: &[String]) -> TribResult<Vec<List>> {
+        self.mem.multi_list_get(keys).await
+    }
+
+    async fn list_append_many(&self, key: &str, values: &[String]) -> TribResult<bool> {
+        self.append_many(key, values, None).await
+    }
+
+    async fn list_append_many_with_ttl(
+        &self,
+        key: &str,
+        values: &[String],
+        ttl: Duration,
+    ) -> TribResult<bool> {
+        self.append_many(key, values, Some(ttl)).await
+    }
+}
+
+#[async_trait]
+impl Storage for DiskStorage {
+    async fn clock(&self, at_least: u64) -> TribResult<u64> {
+        let mut wal = self.wal.lock().await;
+        let c = self.mem.clock(at_least).await?;
+        // the value is only handed out once it is durable, so a restarted
+        // storage can never return it (or anything smaller) again
+        self.log(&mut wal, &WalRecord::Clock(c))?;
+        self.maybe_snapshot(&mut wal)?;
+        Ok(c)
+    }
+
+    async fn transaction(&self, txn: &Txn) -> TribResult<TxnResult> {
+        // as in cas(), holding the log lock keeps every other write out
+        // between the checks and the steps
+        let mut wal = self.wal.lock().await;
+        let at = now_ms();
+        let checks = Txn {
+            checks: txn.checks.clone(),
+            ops: vec![],
+        };
+        if !self.mem.transaction_at(&checks, at)?.committed {
+            return Ok(TxnResult {
+                committed: false,
+                values: vec![],
+            });
+        }
+        let records = txn
+            .ops
+            .iter()
+            .filter_map(|op| match op {
+                TxnOp::Get(_) => None,
+                TxnOp::Set(kv) => Some(WalRecord::Set(kv.key.clone(), kv.value.clone())),
+                TxnOp::ListAppend(kv) => Some(WalRecord::ListAppendAt(
+                    kv.key.clone(),
+                    kv.value.clone(),
+                    at,
+                    None,
+                )),
+                TxnOp::ListRemove(kv) => {
+                    Some(WalRecord::ListRemove(kv.key.clone(), kv.value.clone()))
+                }
+            })
+            .collect::<Vec<WalRecord>>();
+        if !records.is_empty() {
+            self.log(&mut wal, &WalRecord::Txn(records))?;
+        }
+        let r = self.mem.transaction_at(txn, at)?;
+        self.maybe_snapshot(&mut wal)?;
+        Ok(r)
+    }
+
+    fn watcher(&self) -> Option<&dyn Watch> {
+        // changes are announced once they are logged
+        Some(&self.mem)
+    }
+
+    // no set_memory_limit(): a write refused or a key evicted in memory
+    // would still be in the log, and come back on the next restart
+
+    async fn stats(&self) -> TribResult<StorageStats> {
+        self.mem.stats().await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{fs, io::Write, path::PathBuf, time::Duration};
+
+    use crate::{
+        err::TribResult,
+        hlc::Hlc,
+        storage::{KeyList, KeyString, KeyValue, Pattern, Precondition, Storage, Txn, TxnOp},
+    };
+
+    use super::{DiskStorage, WAL_FILE};
+
+    fn temp_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("tribbler-disk-{}", rand::random::<u64>()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[tokio::test]
+    async fn disk_recover_after_reopen() -> TribResult<()> {
+        let dir = temp_dir();
+        {
+            let s = DiskStorage::open(&dir)?;
+            s.set(&KeyValue::new("k", "v")).await?;
+            s.set(&KeyValue::new("gone", "x")).await?;
+            s.set(&KeyValue::new("gone", "")).await?;
+            s.list_append(&KeyValue::new("l", "a")).await?;
+            s.list_append(&KeyValue::new("l", "b")).await?;
+            s.list_append(&KeyValue::new("l", "a")).await?;
+            assert_eq!(2, s.list_remove(&KeyValue::new("l", "a")).await?);
+        }
+        let s = DiskStorage::open(&dir)?;
+        assert_eq!(Some("v".to_string()), s.get("k").await?);
+        assert_eq!(None, s.get("gone").await?);
+        assert_eq!(vec!["b".to_string()], s.list_get("l").await?.0);
+        assert_eq!(1, s.keys(&Pattern::default()).await?.0.len());
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn disk_clock_never_goes_backwards() -> TribResult<()> {
+        let dir = temp_dir();
+        // far enough ahead of the wall clock that only the log can explain c2
+        let ahead = Hlc {
+            physical: Hlc::now().physical + 60_000,
+            logical: 0,
+        }
+        .to_u64();
+        let c1 = {
+            let s = DiskStorage::open(&dir)?;
+            s.clock(ahead).await?
+        };
+        let s = DiskStorage::open(&dir)?;
+        let c2 = s.clock(0).await?;
+        assert_eq!(ahead, c1);
+        assert!(c2 > c1);
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn disk_snapshot_and_replay() -> TribResult<()> {
+        let dir = temp_dir();
+        {
+            let s = DiskStorage::with_snapshot_interval(&dir, 3)?;
+            for i in 0..10 {
+                s.list_append(&KeyValue::new("l", &i.to_string())).await?;
+            }
+        }
+        let s = DiskStorage::open(&dir)?;
+        assert_eq!(10, s.list_get("l").await?.0.len());
+        assert_eq!("9", s.list_get("l").await?.0[9]);
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn disk_ttl_survives_reopen() -> TribResult<()> {
+        let dir = temp_dir();
+        let short = Duration::from_millis(50);
+        {
+            let s = DiskStorage::open(&dir)?;
+            s.set_with_ttl(&KeyValue::new("short", "v"), short).await?;
+            s.set_with_ttl(&KeyValue::new("long", "v"), Duration::from_secs(60))
+                .await?;
+            s.list_append_with_ttl(&KeyValue::new("l", "old"), short)
+                .await?;
+            tokio::time::sleep(short * 2).await;
+            // lands on an expired list, so it starts a new one
+            s.list_append(&KeyValue::new("l", "new")).await?;
+        }
+        let s = DiskStorage::open(&dir)?;
+        assert_eq!(None, s.get("short").await?);
+        assert_eq!(Some("v".to_string()), s.get("long").await?);
+        assert_eq!(vec!["new".to_string()], s.list_get("l").await?.0);
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn disk_trim_survives_reopen() -> TribResult<()> {
+        let dir = temp_dir();
+        {
+            let s = DiskStorage::open(&dir)?;
+            for v in ["a", "b", "c", "d"] {
+                s.list_append(&KeyValue::new("l", v)).await?;
+            }
+            assert_eq!(2, s.list_trim("l", 2).await?);
+        }
+        let s = DiskStorage::open(&dir)?;
+        assert_eq!(2, s.list_len("l").await?);
+        assert_eq!(vec!["c", "d"], s.list_range("l", 0, -1).await?.0);
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn disk_txn_survives_reopen() -> TribResult<()> {
+        let dir = temp_dir();
+        let txn = Txn {
+            checks: vec![Precondition::ListLen {
+                key: "l".to_string(),
+                len: 0,
+            }],
+            ops: vec![
+                TxnOp::Set(KeyValue::new("k", "v")),
+                TxnOp::ListAppend(KeyValue::new("l", "a")),
+            ],
+        };
+        {
+            let s = DiskStorage::open(&dir)?;
+            assert!(s.transaction(&txn).await?.committed);
+            assert!(!s.transaction(&txn).await?.committed);
+        }
+        let s = DiskStorage::open(&dir)?;
+        assert_eq!(Some("v".to_string()), s.get("k").await?);
+        assert_eq!(vec!["a".to_string()], s.list_get("l").await?.0);
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn disk_ignores_torn_tail() -> TribResult<()> {
+        let dir = temp_dir();
+        {
+            let s = DiskStorage::with_snapshot_interval(&dir, 100)?;
+            s.set(&KeyValue::new("k", "v")).await?;
+        }
+        fs::OpenOptions::new()
+            .append(true)
+            .open(dir.join(WAL_FILE))?
+            .write_all(b"{\"Set\":[\"k\",")?;
+        let s = DiskStorage::open(&dir)?;
+        assert_eq!(Some("v".to_string()), s.get("k").await?);
+        s.set(&KeyValue::new("k2", "v2")).await?;
+        drop(s);
+        let s = DiskStorage::open(&dir)?;
+        assert_eq!(Some("v2".to_string()), s.get("k2").await?);
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
@@ -2,6 +2,13 @@
 //! objects from Tribbler related functions.
 use std::{error::Error, fmt::Display};
 
+use tonic::metadata::MetadataValue;
+use tonic::{Code, Status};
+
+/// The [Status] metadata key which marks a [Code::ResourceExhausted] status
+/// as a [TribblerError::RateLimited].
+pub const RATE_LIMITED: &str = "tribbler-rate-limited";
+
 /// basic error types that can occur when running the tribbler service.
 #[derive(Debug, Clone)]
 pub enum TribblerError {
@@ -27,6 +34,29 @@ pub enum TribblerError {
     WhoWhom(String),
     /// when there are no more seq numbers to give out
     MaxedSeq,
+    /// a key or list the operation needs does not exist
+    NotFound(String),
+    /// the storage, or every replica of it, cannot be reached right now
+    Unavailable(String),
+    /// the storage did not answer in time
+    Timeout(String),
+    /// the operation raced with another one and was not applied
+    Conflict(String),
+    /// the write would take the storage over one of its limits, such as its
+    /// [memory limit](crate::storage::MemoryLimit)
+    QuotaExceeded(String),
+    /// the caller could not be told apart from anyone else, e.g. because
+    /// they are not logged in or gave the wrong password
+    Unauthenticated(String),
+    /// the user made more calls than their
+    /// [rate limit](crate::config::RateLimit) allows
+    RateLimited(String),
+    /// the storage does not offer the operation at all, e.g.
+    /// [stats](crate::storage::Storage::stats)
+    Unsupported(String),
+    /// the storage no longer holds what was asked for, e.g. the changes a
+    /// [watch](crate::storage::Watch::watch) wanted to resume from
+    OutOfRange(String),
     /// catch-all error for other issues
     Unknown(String),
 }
@@ -45,6 +75,15 @@ impl Display for TribblerError {
             TribblerError::NotFollowing(who, whom) => format!("{} doesn't follow {}", who, whom),
             TribblerError::TribTooLong => "tribbler post exceed character limit".to_string(),
             TribblerError::WhoWhom(x) => format!("user {} can't follow themself", x),
+            TribblerError::NotFound(x) => format!("not found: {}", x),
+            TribblerError::Unavailable(x) => format!("unavailable: {}", x),
+            TribblerError::Timeout(x) => format!("timed out: {}", x),
+            TribblerError::Conflict(x) => format!("conflict: {}", x),
+            TribblerError::QuotaExceeded(x) => format!("quota exceeded: {}", x),
+            TribblerError::Unauthenticated(x) => format!("unauthenticated: {}", x),
+            TribblerError::RateLimited(x) => format!("rate limited: {}", x),
+            TribblerError::Unsupported(x) => format!("unsupported: {}", x),
+            TribblerError::OutOfRange(x) => format!("out of range: {}", x),
             TribblerError::Unknown(x) => format!("unknown error: {}", x),
             x => format!("{:?}", x),
         };
@@ -54,15 +93,75 @@ impl Display for TribblerError {
 
 impl std::error::Error for TribblerError {}
 
-impl From<tonic::Status> for TribblerError {
-    fn from(v: tonic::Status) -> Self {
-        TribblerError::RpcError(format!("{:?}", v))
+/// The storage errors travel as the [Status] code below, with their message
+/// as the status message, so that they come out of the [From<Status>]
+/// conversion as they went in. Anything else is sent as [Code::Internal].
+///
+/// [TribblerError::RateLimited] shares [Code::ResourceExhausted] with
+/// [TribblerError::QuotaExceeded], and is told apart by the
+/// [RATE_LIMITED] metadata key.
+impl From<TribblerError> for Status {
+    fn from(v: TribblerError) -> Self {
+        match v {
+            TribblerError::RateLimited(x) => {
+                let mut status = Status::resource_exhausted(x);
+                status
+                    .metadata_mut()
+                    .insert(RATE_LIMITED, MetadataValue::from_static("1"));
+                status
+            }
+            TribblerError::NotFound(x) => Status::not_found(x),
+            TribblerError::Unavailable(x) => Status::unavailable(x),
+            TribblerError::Timeout(x) => Status::deadline_exceeded(x),
+            TribblerError::Conflict(x) => Status::aborted(x),
+            TribblerError::QuotaExceeded(x) => Status::resource_exhausted(x),
+            TribblerError::Unauthenticated(x) => Status::unauthenticated(x),
+            TribblerError::Unsupported(x) => Status::unimplemented(x),
+            TribblerError::OutOfRange(x) => Status::out_of_range(x),
+            x => Status::internal(x.to_string()),
+        }
+    }
+}
+
+impl From<Status> for TribblerError {
+    fn from(v: Status) -> Self {
+        let message = v.message().to_string();
+        match v.code() {
+            Code::NotFound => TribblerError::NotFound(message),
+            Code::Unavailable => TribblerError::Unavailable(message),
+            Code::DeadlineExceeded => TribblerError::Timeout(message),
+            Code::Aborted => TribblerError::Conflict(message),
+            Code::ResourceExhausted if v.metadata().contains_key(RATE_LIMITED) => {
+                TribblerError::RateLimited(message)
+            }
+            Code::ResourceExhausted => TribblerError::QuotaExceeded(message),
+            Code::Unauthenticated => TribblerError::Unauthenticated(message),
+            Code::Unimplemented => TribblerError::Unsupported(message),
+            Code::OutOfRange => TribblerError::OutOfRange(message),
+            _ => TribblerError::RpcError(format!("{:?}", v)),
+        }
     }
 }
 
 impl From<tonic::transport::Error> for TribblerError {
     fn from(v: tonic::transport::Error) -> Self {
-        TribblerError::RpcError(format!("{:?}", v))
+        TribblerError::Unavailable(format!("{:?}", v))
+    }
+}
+
+impl From<tokio::time::error::Elapsed> for TribblerError {
+    fn from(v: tokio::time::error::Elapsed) -> Self {
+        TribblerError::Timeout(v.to_string())
+    }
+}
+
+/// The [Status] a server answers with when an operation fails with `e`:
+/// a [TribblerError] keeps its code, any other error is
+/// [internal](Code::Internal).
+pub fn to_status(e: &(dyn Error + Send + Sync + 'static)) -> Status {
+    match e.downcast_ref::<TribblerError>() {
+        Some(x) => x.clone().into(),
+        None => Status::internal(e.to_string()),
     }
 }
 
@@ -74,3 +173,58 @@ impl From<Box<dyn Error>> for TribblerError {
         TribblerError::Unknown(x.to_string())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use tonic::{Code, Status};
+
+    use super::{to_status, TribblerError};
+
+    #[test]
+    fn storage_errors_survive_status() {
+        let errors = [
+            (TribblerError::NotFound("k".to_string()), Code::NotFound),
+            (
+                TribblerError::Unavailable("down".to_string()),
+                Code::Unavailable,
+            ),
+            (
+                TribblerError::Timeout("slow".to_string()),
+                Code::DeadlineExceeded,
+            ),
+            (TribblerError::Conflict("raced".to_string()), Code::Aborted),
+            (
+                TribblerError::QuotaExceeded("full".to_string()),
+                Code::ResourceExhausted,
+            ),
+            (
+                TribblerError::Unauthenticated("who".to_string()),
+                Code::Unauthenticated,
+            ),
+            (
+                TribblerError::RateLimited("slow down".to_string()),
+                Code::ResourceExhausted,
+            ),
+            (
+                TribblerError::Unsupported("stats".to_string()),
+                Code::Unimplemented,
+            ),
+            (
+                TribblerError::OutOfRange("since 1".to_string()),
+                Code::OutOfRange,
+            ),
+        ];
+        for (e, code) in errors {
+            let status = to_status(&e);
+            assert_eq!(code, status.code());
+            assert_eq!(e.to_string(), TribblerError::from(status).to_string());
+        }
+        let status = to_status(&*Box::<dyn std::error::Error + Send + Sync>::from("oops"));
+        assert_eq!((Code::Internal, "oops"), (status.code(), status.message()));
+        let status = Status::internal("oops");
+        assert!(matches!(
+            TribblerError::from(status),
+            TribblerError::RpcError(_)
+        ));
+    }
+}
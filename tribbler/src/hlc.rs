@@ -0,0 +1,135 @@
+//! module containing the hybrid logical clock behind
+//! [Storage::clock](crate::storage::Storage::clock).
+//!
+//! A hybrid logical clock reading pairs the wall-clock time with a counter
+//! that orders the readings sharing the same millisecond. Readings follow the
+//! wall clock as long as it moves forward; when a clock sees a reading from
+//! ahead of its own wall clock it counts on from that reading instead, so an
+//! event is always ordered after every event it could have heard of, while
+//! the physical part stays close to real time.
+//!
+//! Readings are passed around as [u64]s with the physical part in the upper
+//! 48 bits and the logical part in the lower [LOGICAL_BITS], so comparing the
+//! numbers compares the readings.
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::rpc;
+
+/// number of low bits of a packed reading holding the logical part
+pub const LOGICAL_BITS: u32 = 16;
+
+/// A reading of a hybrid logical clock. Readings order by physical part,
+/// then by logical part.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize, Deserialize,
+)]
+pub struct Hlc {
+    /// milliseconds since the Unix epoch
+    pub physical: u64,
+    /// orders the readings with the same physical part
+    pub logical: u16,
+}
+
+impl Hlc {
+    /// The wall-clock time, as a reading with no logical part.
+    pub fn now() -> Hlc {
+        let physical = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0);
+        Hlc {
+            physical,
+            logical: 0,
+        }
+    }
+
+    /// Unpacks a reading packed by [Hlc::to_u64].
+    pub fn from_u64(v: u64) -> Hlc {
+        Hlc {
+            physical: v >> LOGICAL_BITS,
+            logical: v as u16,
+        }
+    }
+
+    /// Packs the reading into a [u64] which compares like the reading. A
+    /// physical part beyond 48 bits is clamped.
+    pub fn to_u64(self) -> u64 {
+        let physical = self.physical.min(u64::MAX >> LOGICAL_BITS);
+        (physical << LOGICAL_BITS) | self.logical as u64
+    }
+
+    /// The next reading of a clock which may not hand out anything below
+    /// `next` and has just seen `seen`: the wall-clock time when it is ahead
+    /// of both, otherwise the larger of the two.
+    pub fn tick(next: Hlc, seen: Hlc) -> Hlc {
+        Hlc::now().max(next).max(seen)
+    }
+
+    /// The smallest reading after this one. A full logical part carries over
+    /// into the physical part.
+    pub fn successor(self) -> Hlc {
+        Hlc::from_u64(self.to_u64().saturating_add(1))
+    }
+
+    /// The physical part in whole seconds since the Unix epoch.
+    pub fn as_secs(self) -> u64 {
+        self.physical / 1000
+    }
+}
+
+impl From<Hlc> for rpc::Clock {
+    fn from(h: Hlc) -> rpc::Clock {
+        rpc::Clock {
+            physical: h.physical,
+            logical: h.logical as u32,
+        }
+    }
+}
+
+impl From<rpc::Clock> for Hlc {
+    fn from(c: rpc::Clock) -> Hlc {
+        Hlc {
+            physical: c.physical,
+            logical: c.logical.min(u16::MAX as u32) as u16,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Hlc;
+
+    #[test]
+    fn hlc_packing_keeps_order() {
+        let a = Hlc {
+            physical: 1000,
+            logical: u16::MAX,
+        };
+        let b = Hlc {
+            physical: 1001,
+            logical: 0,
+        };
+        assert!(a.to_u64() < b.to_u64());
+        assert_eq!(a, Hlc::from_u64(a.to_u64()));
+        assert_eq!(b, a.successor());
+        assert_eq!(u64::MAX, Hlc::from_u64(u64::MAX).successor().to_u64());
+    }
+
+    #[test]
+    fn hlc_tick() {
+        let ahead = Hlc {
+            physical: Hlc::now().physical + 60_000,
+            logical: 3,
+        };
+        // a reading from ahead of the wall clock is counted on from
+        assert_eq!(ahead, Hlc::tick(Hlc::default(), ahead));
+        assert_eq!(ahead, Hlc::tick(ahead, Hlc::default()));
+        // otherwise the wall clock wins
+        let now = Hlc::now();
+        let t = Hlc::tick(Hlc::default(), Hlc::default());
+        assert!(t >= now);
+        assert_eq!(0, t.logical);
+    }
+}
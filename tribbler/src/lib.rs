@@ -13,11 +13,17 @@
     html_favicon_url = "https://upload.wikimedia.org/wikipedia/commons/thumb/f/f8/Creative-Tail-Animal-penguin.svg/128px-Creative-Tail-Animal-penguin.svg.png?20160314145218"
 )]
 pub mod addr;
+pub mod auth;
 pub mod colon;
 pub mod config;
+pub mod disk;
 pub mod err;
+pub mod hlc;
+pub mod metrics;
 pub mod ref_impl;
 /// protobuf-generated RPC stubs and message structs
 pub mod rpc;
+pub mod sharded;
 pub mod storage;
+pub mod tls;
 pub mod trib;
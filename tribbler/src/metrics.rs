@@ -0,0 +1,248 @@
+//! module with the Prometheus metrics of the tribbler processes, and the
+//! HTTP listener which serves them on `/metrics`.
+//!
+//! Every metric is registered in the default [prometheus] registry, so a
+//! process running several backends or keepers serves them all together;
+//! the `server` and `keeper` labels tell them apart.
+use std::convert::Infallible;
+use std::net::ToSocketAddrs;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Instant;
+
+use hyper::header::CONTENT_TYPE;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Request, Response, StatusCode};
+use lazy_static::lazy_static;
+use prometheus::{
+    register_histogram_vec, register_int_counter, register_int_counter_vec, register_int_gauge_vec,
+    Encoder, HistogramVec, IntCounter, IntCounterVec, IntGaugeVec, TextEncoder,
+};
+use tonic::codegen::{http, BoxFuture, Service};
+use tonic::Code;
+use tower::Layer;
+
+use crate::err::{TribResult, TribblerError};
+
+lazy_static! {
+    /// RPCs served, by server, service, method and status code
+    pub static ref RPC_REQUESTS: IntCounterVec = register_int_counter_vec!(
+        "tribbler_rpc_requests_total",
+        "RPCs served",
+        &["server", "service", "method", "code"]
+    )
+    .unwrap();
+    /// time until an RPC is answered, by server, service and method
+    pub static ref RPC_SECONDS: HistogramVec = register_histogram_vec!(
+        "tribbler_rpc_duration_seconds",
+        "time until an RPC is answered",
+        &["server", "service", "method"]
+    )
+    .unwrap();
+    /// calls a bin client made to a replica which failed
+    pub static ref REPLICA_FAILURES: IntCounter = register_int_counter!(
+        "tribbler_replica_failures_total",
+        "calls to a bin replica which failed"
+    )
+    .unwrap();
+    /// 1 while the keeper leads the keepers, 0 otherwise
+    pub static ref KEEPER_LEADER: IntGaugeVec = register_int_gauge_vec!(
+        "tribbler_keeper_leader",
+        "whether the keeper leads",
+        &["keeper"]
+    )
+    .unwrap();
+    /// the newest membership epoch the keeper knows of
+    pub static ref KEEPER_EPOCH: IntGaugeVec = register_int_gauge_vec!(
+        "tribbler_keeper_epoch",
+        "newest membership epoch",
+        &["keeper"]
+    )
+    .unwrap();
+    /// the backends the leading keeper found alive in its last round
+    pub static ref KEEPER_BACKENDS_ALIVE: IntGaugeVec = register_int_gauge_vec!(
+        "tribbler_keeper_backends_alive",
+        "backends alive",
+        &["keeper"]
+    )
+    .unwrap();
+    /// data migrations run for a backend joining or leaving, by outcome
+    pub static ref KEEPER_MIGRATIONS: IntCounterVec = register_int_counter_vec!(
+        "tribbler_keeper_migrations_total",
+        "data migrations run",
+        &["keeper", "kind", "result"]
+    )
+    .unwrap();
+    /// front-end API requests, by endpoint and HTTP status
+    pub static ref FRONT_REQUESTS: IntCounterVec = register_int_counter_vec!(
+        "tribbler_front_requests_total",
+        "front-end API requests",
+        &["endpoint", "status"]
+    )
+    .unwrap();
+    /// time until a front-end API request is answered, by endpoint
+    pub static ref FRONT_SECONDS: HistogramVec = register_histogram_vec!(
+        "tribbler_front_duration_seconds",
+        "time until a front-end API request is answered",
+        &["endpoint"]
+    )
+    .unwrap();
+}
+
+/// `"ok"` or `"error"`, the `result` label of an outcome
+pub fn result_label<T, E>(r: &Result<T, E>) -> &'static str {
+    match r {
+        Ok(_) => "ok",
+        Err(_) => "error",
+    }
+}
+
+/// Every registered metric, in the Prometheus text format.
+pub fn render() -> String {
+    let mut buf = vec![];
+    // writing into a Vec cannot fail
+    let _ = TextEncoder::new().encode(&prometheus::gather(), &mut buf);
+    String::from_utf8_lossy(&buf).into_owned()
+}
+
+/// Serves the metrics on `http://<addr>/metrics` until the listener fails.
+pub async fn serve(addr: String) -> TribResult<()> {
+    let sock = addr
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| TribblerError::Unknown(format!("bad metrics address {}", addr)))?;
+    let make = make_service_fn(|_| async { Ok::<_, Infallible>(service_fn(respond)) });
+    hyper::Server::try_bind(&sock)?.serve(make).await?;
+    Ok(())
+}
+
+async fn respond(req: Request<Body>) -> Result<Response<Body>, Infallible> {
+    let resp = match req.uri().path() {
+        "/metrics" => Response::builder()
+            .header(CONTENT_TYPE, TextEncoder::new().format_type())
+            .body(Body::from(render())),
+        _ => Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .body(Body::empty()),
+    };
+    Ok(resp.unwrap())
+}
+
+/// A [Layer] for a tonic server which counts and times every RPC it serves
+/// in [RPC_REQUESTS] and [RPC_SECONDS], labelled with `server`.
+///
+/// A streaming RPC is timed until its first response, and counted with the
+/// code it started with.
+#[derive(Debug, Clone)]
+pub struct RpcMetrics {
+    server: Arc<str>,
+}
+
+impl RpcMetrics {
+    pub fn new(server: &str) -> RpcMetrics {
+        RpcMetrics {
+            server: server.into(),
+        }
+    }
+}
+
+impl<S> Layer<S> for RpcMetrics {
+    type Service = Metered<S>;
+
+    fn layer(&self, inner: S) -> Metered<S> {
+        Metered {
+            inner,
+            server: self.server.clone(),
+        }
+    }
+}
+
+/// The service [RpcMetrics] wraps a server's services in.
+#[derive(Debug, Clone)]
+pub struct Metered<S> {
+    inner: S,
+    server: Arc<str>,
+}
+
+impl<S, B, R> Service<http::Request<B>> for Metered<S>
+where
+    S: Service<http::Request<B>, Response = http::Response<R>>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<S::Response, S::Error>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), S::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: http::Request<B>) -> Self::Future {
+        // the path of an RPC is /<package>.<service>/<method>
+        let (service, method) = match req.uri().path()[1..].split_once('/') {
+            Some((service, method)) => (service.to_string(), method.to_string()),
+            None => (String::new(), req.uri().path().to_string()),
+        };
+        let server = self.server.clone();
+        let started = Instant::now();
+        let answer = self.inner.call(req);
+        Box::pin(async move {
+            let resp = answer.await;
+            // a failed call answers with its status in the headers; one
+            // which got through has it in the trailers, after the body
+            let code = match &resp {
+                Ok(r) => match r.headers().get("grpc-status") {
+                    Some(code) => Code::from_bytes(code.as_bytes()),
+                    None => Code::Ok,
+                },
+                Err(_) => Code::Unknown,
+            };
+            let labels = [&*server, &service, &method];
+            RPC_SECONDS
+                .with_label_values(&labels)
+                .observe(started.elapsed().as_secs_f64());
+            RPC_REQUESTS
+                .with_label_values(&[&*server, &service, &method, &format!("{:?}", code)])
+                .inc();
+            resp
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+    use std::time::Duration;
+
+    use super::{render, serve, RPC_REQUESTS};
+    use crate::addr::rand::rand_port;
+    use crate::err::TribResult;
+
+    #[tokio::test]
+    async fn metrics_served() -> TribResult<()> {
+        RPC_REQUESTS
+            .with_label_values(&["here", "rpc.Test", "Ping", "Ok"])
+            .inc();
+        assert!(render().contains("method=\"Ping\""));
+
+        let addr = format!("127.0.0.1:{}", rand_port());
+        tokio::spawn(serve(addr.clone()));
+        let fetch = tokio::task::spawn_blocking(move || -> std::io::Result<String> {
+            let mut resp = String::new();
+            for _ in 0..50 {
+                if let Ok(mut conn) = TcpStream::connect(&addr) {
+                    conn.write_all(b"GET /metrics HTTP/1.0\r\n\r\n")?;
+                    conn.read_to_string(&mut resp)?;
+                    break;
+                }
+                std::thread::sleep(Duration::from_millis(20));
+            }
+            Ok(resp)
+        });
+        let resp = fetch.await??;
+        assert!(resp.starts_with("HTTP/1.0 200"));
+        assert!(resp.contains("tribbler_rpc_requests_total{"));
+        Ok(())
+    }
+}
@@ -5,6 +5,18 @@ pub struct KeyValue {
     #[prost(string, tag = "2")]
     pub value: ::prost::alloc::string::String,
 }
+/// A key/value pair, or a list element, that expires ttl_ms milliseconds
+/// after it is written. ttl_ms must be at least 1; 0 is refused rather than
+/// read as either "now" or "never".
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ExpiringKeyValue {
+    #[prost(string, tag = "1")]
+    pub key: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub value: ::prost::alloc::string::String,
+    #[prost(uint64, tag = "3")]
+    pub ttl_ms: u64,
+}
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct Pattern {
     #[prost(string, tag = "1")]
@@ -12,6 +24,25 @@ pub struct Pattern {
     #[prost(string, tag = "2")]
     pub suffix: ::prost::alloc::string::String,
 }
+/// Asks for at most limit keys matching pattern that sort after start_after;
+/// an empty start_after starts from the first key.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct KeysPageRequest {
+    #[prost(message, optional, tag = "1")]
+    pub pattern: ::core::option::Option<Pattern>,
+    #[prost(string, tag = "2")]
+    pub start_after: ::prost::alloc::string::String,
+    #[prost(uint32, tag = "3")]
+    pub limit: u32,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct KeysPage {
+    #[prost(string, repeated, tag = "1")]
+    pub keys: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// the start_after of the next page, unset after the last page
+    #[prost(string, optional, tag = "2")]
+    pub next: ::core::option::Option<::prost::alloc::string::String>,
+}
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct Bool {
     #[prost(bool, tag = "1")]
@@ -24,8 +55,9 @@ pub struct Key {
 }
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct Value {
-    #[prost(string, tag = "1")]
-    pub value: ::prost::alloc::string::String,
+    /// unset when the key does not exist
+    #[prost(string, optional, tag = "1")]
+    pub value: ::core::option::Option<::prost::alloc::string::String>,
 }
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct StringList {
@@ -33,15 +65,250 @@ pub struct StringList {
     pub list: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
 }
 #[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Keys {
+    #[prost(string, repeated, tag = "1")]
+    pub keys: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct MaybeValue {
+    #[prost(string, optional, tag = "1")]
+    pub value: ::core::option::Option<::prost::alloc::string::String>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Values {
+    #[prost(message, repeated, tag = "1")]
+    pub values: ::prost::alloc::vec::Vec<MaybeValue>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct StringLists {
+    #[prost(message, repeated, tag = "1")]
+    pub lists: ::prost::alloc::vec::Vec<StringList>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct KeyValues {
+    #[prost(string, tag = "1")]
+    pub key: ::prost::alloc::string::String,
+    #[prost(string, repeated, tag = "2")]
+    pub values: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+}
+/// A hybrid logical clock reading, see tribbler::hlc::Hlc.
+#[derive(Clone, PartialEq, ::prost::Message)]
 pub struct Clock {
+    /// milliseconds since the Unix epoch
     #[prost(uint64, tag = "1")]
-    pub timestamp: u64,
+    pub physical: u64,
+    #[prost(uint32, tag = "2")]
+    pub logical: u32,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CasRequest {
+    #[prost(string, tag = "1")]
+    pub key: ::prost::alloc::string::String,
+    /// unset when the key is expected to have no value
+    #[prost(string, optional, tag = "2")]
+    pub expected: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(string, tag = "3")]
+    pub value: ::prost::alloc::string::String,
 }
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct ListRemoveResponse {
     #[prost(uint32, tag = "1")]
     pub removed: u32,
 }
+/// Elements start to end of a list, both included; negative indices count back
+/// from the end of the list.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ListRangeRequest {
+    #[prost(string, tag = "1")]
+    pub key: ::prost::alloc::string::String,
+    #[prost(sint64, tag = "2")]
+    pub start: i64,
+    #[prost(sint64, tag = "3")]
+    pub end: i64,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct MultiListRangeRequest {
+    #[prost(string, repeated, tag = "1")]
+    pub keys: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    #[prost(sint64, tag = "2")]
+    pub start: i64,
+    #[prost(sint64, tag = "3")]
+    pub end: i64,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ListTrimRequest {
+    #[prost(string, tag = "1")]
+    pub key: ::prost::alloc::string::String,
+    #[prost(uint64, tag = "2")]
+    pub keep_last: u64,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ListLength {
+    #[prost(uint64, tag = "1")]
+    pub len: u64,
+}
+/// A single key/value pair or key/list pair, as streamed by scan and ingest.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Record {
+    #[prost(string, tag = "1")]
+    pub key: ::prost::alloc::string::String,
+    /// how long until the pair or list expires, in milliseconds; 0 if never
+    #[prost(uint64, tag = "4")]
+    pub ttl_ms: u64,
+    #[prost(oneof = "record::Entry", tags = "2, 3")]
+    pub entry: ::core::option::Option<record::Entry>,
+}
+/// Nested message and enum types in `Record`.
+pub mod record {
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum Entry {
+        #[prost(string, tag = "2")]
+        Value(::prost::alloc::string::String),
+        #[prost(message, tag = "3")]
+        List(super::StringList),
+    }
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct IngestResponse {
+    #[prost(uint64, tag = "1")]
+    pub ingested: u64,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ListLenCheck {
+    #[prost(string, tag = "1")]
+    pub key: ::prost::alloc::string::String,
+    #[prost(uint64, tag = "2")]
+    pub len: u64,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Precondition {
+    #[prost(oneof = "precondition::Check", tags = "1, 2")]
+    pub check: ::core::option::Option<precondition::Check>,
+}
+/// Nested message and enum types in `Precondition`.
+pub mod precondition {
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum Check {
+        /// the key holds the value, or is not set when the value is empty
+        #[prost(message, tag = "1")]
+        Value(super::KeyValue),
+        #[prost(message, tag = "2")]
+        ListLen(super::ListLenCheck),
+    }
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct TxnOp {
+    #[prost(oneof = "txn_op::Op", tags = "1, 2, 3, 4")]
+    pub op: ::core::option::Option<txn_op::Op>,
+}
+/// Nested message and enum types in `TxnOp`.
+pub mod txn_op {
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum Op {
+        #[prost(string, tag = "1")]
+        Get(::prost::alloc::string::String),
+        #[prost(message, tag = "2")]
+        Set(super::KeyValue),
+        #[prost(message, tag = "3")]
+        ListAppend(super::KeyValue),
+        #[prost(message, tag = "4")]
+        ListRemove(super::KeyValue),
+    }
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct TxnRequest {
+    #[prost(message, repeated, tag = "1")]
+    pub checks: ::prost::alloc::vec::Vec<Precondition>,
+    #[prost(message, repeated, tag = "2")]
+    pub ops: ::prost::alloc::vec::Vec<TxnOp>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct TxnResponse {
+    #[prost(bool, tag = "1")]
+    pub committed: bool,
+    #[prost(message, repeated, tag = "2")]
+    pub values: ::prost::alloc::vec::Vec<MaybeValue>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct WatchRequest {
+    #[prost(message, optional, tag = "1")]
+    pub pattern: ::core::option::Option<Pattern>,
+    /// the last change already seen, or 0 for only new changes
+    #[prost(uint64, tag = "2")]
+    pub since: u64,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Change {
+    #[prost(uint64, tag = "1")]
+    pub seq: u64,
+    #[prost(enumeration = "ChangeKind", tag = "2")]
+    pub kind: i32,
+    #[prost(string, tag = "3")]
+    pub key: ::prost::alloc::string::String,
+    #[prost(string, tag = "4")]
+    pub value: ::prost::alloc::string::String,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct HealthCheck {}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct HealthStatus {
+    #[prost(bool, tag = "1")]
+    pub serving: bool,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct StatsRequest {}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Stats {
+    #[prost(uint64, tag = "1")]
+    pub used_bytes: u64,
+    /// 0 when there is no limit
+    #[prost(uint64, tag = "2")]
+    pub limit_bytes: u64,
+    #[prost(uint64, tag = "3")]
+    pub keys: u64,
+    #[prost(uint64, tag = "4")]
+    pub lists: u64,
+    #[prost(uint64, tag = "5")]
+    pub evicted: u64,
+    #[prost(uint64, tag = "6")]
+    pub rejected: u64,
+}
+/// The bytes variants of Key, KeyValue, Value and StringList, for keys and
+/// values which need not be UTF-8.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct BytesKey {
+    #[prost(bytes = "vec", tag = "1")]
+    pub key: ::prost::alloc::vec::Vec<u8>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct BytesKeyValue {
+    #[prost(bytes = "vec", tag = "1")]
+    pub key: ::prost::alloc::vec::Vec<u8>,
+    #[prost(bytes = "vec", tag = "2")]
+    pub value: ::prost::alloc::vec::Vec<u8>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct BytesValue {
+    /// unset when the key does not exist
+    #[prost(bytes = "vec", optional, tag = "1")]
+    pub value: ::core::option::Option<::prost::alloc::vec::Vec<u8>>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct BytesList {
+    #[prost(bytes = "vec", repeated, tag = "1")]
+    pub list: ::prost::alloc::vec::Vec<::prost::alloc::vec::Vec<u8>>,
+}
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum ChangeKind {
+    Set = 0,
+    ListAppend = 1,
+    ListRemove = 2,
+    ListTrim = 3,
+    Expire = 4,
+    ListExpire = 5,
+    Evict = 6,
+}
 #[doc = r" Generated client implementations."]
 pub mod trib_storage_client {
     #![allow(unused_variables, dead_code, missing_docs, clippy::let_unit_value)]
@@ -130,6 +397,20 @@ pub mod trib_storage_client {
             let path = http::uri::PathAndQuery::from_static("/rpc.TribStorage/set");
             self.inner.unary(request.into_request(), path, codec).await
         }
+        pub async fn set_with_ttl(
+            &mut self,
+            request: impl tonic::IntoRequest<super::ExpiringKeyValue>,
+        ) -> Result<tonic::Response<super::Bool>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/rpc.TribStorage/setWithTtl");
+            self.inner.unary(request.into_request(), path, codec).await
+        }
         pub async fn keys(
             &mut self,
             request: impl tonic::IntoRequest<super::Pattern>,
@@ -144,6 +425,48 @@ pub mod trib_storage_client {
             let path = http::uri::PathAndQuery::from_static("/rpc.TribStorage/keys");
             self.inner.unary(request.into_request(), path, codec).await
         }
+        pub async fn keys_page(
+            &mut self,
+            request: impl tonic::IntoRequest<super::KeysPageRequest>,
+        ) -> Result<tonic::Response<super::KeysPage>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/rpc.TribStorage/keysPage");
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        pub async fn multi_get(
+            &mut self,
+            request: impl tonic::IntoRequest<super::Keys>,
+        ) -> Result<tonic::Response<super::Values>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/rpc.TribStorage/multiGet");
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        pub async fn cas(
+            &mut self,
+            request: impl tonic::IntoRequest<super::CasRequest>,
+        ) -> Result<tonic::Response<super::Bool>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/rpc.TribStorage/cas");
+            self.inner.unary(request.into_request(), path, codec).await
+        }
         pub async fn list_get(
             &mut self,
             request: impl tonic::IntoRequest<super::Key>,
@@ -172,6 +495,20 @@ pub mod trib_storage_client {
             let path = http::uri::PathAndQuery::from_static("/rpc.TribStorage/listAppend");
             self.inner.unary(request.into_request(), path, codec).await
         }
+        pub async fn list_append_with_ttl(
+            &mut self,
+            request: impl tonic::IntoRequest<super::ExpiringKeyValue>,
+        ) -> Result<tonic::Response<super::Bool>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/rpc.TribStorage/listAppendWithTtl");
+            self.inner.unary(request.into_request(), path, codec).await
+        }
         pub async fn list_remove(
             &mut self,
             request: impl tonic::IntoRequest<super::KeyValue>,
@@ -186,6 +523,62 @@ pub mod trib_storage_client {
             let path = http::uri::PathAndQuery::from_static("/rpc.TribStorage/listRemove");
             self.inner.unary(request.into_request(), path, codec).await
         }
+        pub async fn list_trim(
+            &mut self,
+            request: impl tonic::IntoRequest<super::ListTrimRequest>,
+        ) -> Result<tonic::Response<super::ListRemoveResponse>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/rpc.TribStorage/listTrim");
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        pub async fn list_len(
+            &mut self,
+            request: impl tonic::IntoRequest<super::Key>,
+        ) -> Result<tonic::Response<super::ListLength>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/rpc.TribStorage/listLen");
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        pub async fn list_range(
+            &mut self,
+            request: impl tonic::IntoRequest<super::ListRangeRequest>,
+        ) -> Result<tonic::Response<super::StringList>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/rpc.TribStorage/listRange");
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        pub async fn multi_list_range(
+            &mut self,
+            request: impl tonic::IntoRequest<super::MultiListRangeRequest>,
+        ) -> Result<tonic::Response<super::StringLists>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/rpc.TribStorage/multiListRange");
+            self.inner.unary(request.into_request(), path, codec).await
+        }
         pub async fn list_keys(
             &mut self,
             request: impl tonic::IntoRequest<super::Pattern>,
@@ -200,6 +593,48 @@ pub mod trib_storage_client {
             let path = http::uri::PathAndQuery::from_static("/rpc.TribStorage/listKeys");
             self.inner.unary(request.into_request(), path, codec).await
         }
+        pub async fn list_keys_page(
+            &mut self,
+            request: impl tonic::IntoRequest<super::KeysPageRequest>,
+        ) -> Result<tonic::Response<super::KeysPage>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/rpc.TribStorage/listKeysPage");
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        pub async fn multi_list_get(
+            &mut self,
+            request: impl tonic::IntoRequest<super::Keys>,
+        ) -> Result<tonic::Response<super::StringLists>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/rpc.TribStorage/multiListGet");
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        pub async fn list_append_many(
+            &mut self,
+            request: impl tonic::IntoRequest<super::KeyValues>,
+        ) -> Result<tonic::Response<super::Bool>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/rpc.TribStorage/listAppendMany");
+            self.inner.unary(request.into_request(), path, codec).await
+        }
         pub async fn clock(
             &mut self,
             request: impl tonic::IntoRequest<super::Clock>,
@@ -214,47 +649,349 @@ pub mod trib_storage_client {
             let path = http::uri::PathAndQuery::from_static("/rpc.TribStorage/clock");
             self.inner.unary(request.into_request(), path, codec).await
         }
-    }
-}
-#[doc = r" Generated server implementations."]
-pub mod trib_storage_server {
-    #![allow(unused_variables, dead_code, missing_docs, clippy::let_unit_value)]
-    use tonic::codegen::*;
-    #[doc = "Generated trait containing gRPC methods that should be implemented for use with TribStorageServer."]
-    #[async_trait]
-    pub trait TribStorage: Send + Sync + 'static {
-        async fn get(
-            &self,
-            request: tonic::Request<super::Key>,
-        ) -> Result<tonic::Response<super::Value>, tonic::Status>;
-        async fn set(
-            &self,
-            request: tonic::Request<super::KeyValue>,
-        ) -> Result<tonic::Response<super::Bool>, tonic::Status>;
-        async fn keys(
-            &self,
-            request: tonic::Request<super::Pattern>,
-        ) -> Result<tonic::Response<super::StringList>, tonic::Status>;
-        async fn list_get(
-            &self,
-            request: tonic::Request<super::Key>,
-        ) -> Result<tonic::Response<super::StringList>, tonic::Status>;
-        async fn list_append(
-            &self,
-            request: tonic::Request<super::KeyValue>,
-        ) -> Result<tonic::Response<super::Bool>, tonic::Status>;
-        async fn list_remove(
-            &self,
-            request: tonic::Request<super::KeyValue>,
-        ) -> Result<tonic::Response<super::ListRemoveResponse>, tonic::Status>;
+        pub async fn txn(
+            &mut self,
+            request: impl tonic::IntoRequest<super::TxnRequest>,
+        ) -> Result<tonic::Response<super::TxnResponse>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/rpc.TribStorage/txn");
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        pub async fn health(
+            &mut self,
+            request: impl tonic::IntoRequest<super::HealthCheck>,
+        ) -> Result<tonic::Response<super::HealthStatus>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/rpc.TribStorage/health");
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        pub async fn stats(
+            &mut self,
+            request: impl tonic::IntoRequest<super::StatsRequest>,
+        ) -> Result<tonic::Response<super::Stats>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/rpc.TribStorage/stats");
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        pub async fn get_bytes(
+            &mut self,
+            request: impl tonic::IntoRequest<super::BytesKey>,
+        ) -> Result<tonic::Response<super::BytesValue>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/rpc.TribStorage/getBytes");
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        pub async fn set_bytes(
+            &mut self,
+            request: impl tonic::IntoRequest<super::BytesKeyValue>,
+        ) -> Result<tonic::Response<super::Bool>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/rpc.TribStorage/setBytes");
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        #[doc = " the keys starting with the given prefix"]
+        pub async fn keys_bytes(
+            &mut self,
+            request: impl tonic::IntoRequest<super::BytesKey>,
+        ) -> Result<tonic::Response<super::BytesList>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/rpc.TribStorage/keysBytes");
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        pub async fn list_get_bytes(
+            &mut self,
+            request: impl tonic::IntoRequest<super::BytesKey>,
+        ) -> Result<tonic::Response<super::BytesList>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/rpc.TribStorage/listGetBytes");
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        pub async fn list_append_bytes(
+            &mut self,
+            request: impl tonic::IntoRequest<super::BytesKeyValue>,
+        ) -> Result<tonic::Response<super::Bool>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/rpc.TribStorage/listAppendBytes");
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        pub async fn list_remove_bytes(
+            &mut self,
+            request: impl tonic::IntoRequest<super::BytesKeyValue>,
+        ) -> Result<tonic::Response<super::ListRemoveResponse>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/rpc.TribStorage/listRemoveBytes");
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        #[doc = " the list names starting with the given prefix"]
+        pub async fn list_keys_bytes(
+            &mut self,
+            request: impl tonic::IntoRequest<super::BytesKey>,
+        ) -> Result<tonic::Response<super::BytesList>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/rpc.TribStorage/listKeysBytes");
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        pub async fn scan(
+            &mut self,
+            request: impl tonic::IntoRequest<super::Pattern>,
+        ) -> Result<tonic::Response<tonic::codec::Streaming<super::Record>>, tonic::Status>
+        {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/rpc.TribStorage/Scan");
+            self.inner
+                .server_streaming(request.into_request(), path, codec)
+                .await
+        }
+        pub async fn ingest(
+            &mut self,
+            request: impl tonic::IntoStreamingRequest<Message = super::Record>,
+        ) -> Result<tonic::Response<super::IngestResponse>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/rpc.TribStorage/Ingest");
+            self.inner
+                .client_streaming(request.into_streaming_request(), path, codec)
+                .await
+        }
+        pub async fn watch(
+            &mut self,
+            request: impl tonic::IntoRequest<super::WatchRequest>,
+        ) -> Result<tonic::Response<tonic::codec::Streaming<super::Change>>, tonic::Status>
+        {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/rpc.TribStorage/Watch");
+            self.inner
+                .server_streaming(request.into_request(), path, codec)
+                .await
+        }
+    }
+}
+#[doc = r" Generated server implementations."]
+pub mod trib_storage_server {
+    #![allow(unused_variables, dead_code, missing_docs, clippy::let_unit_value)]
+    use tonic::codegen::*;
+    #[doc = "Generated trait containing gRPC methods that should be implemented for use with TribStorageServer."]
+    #[async_trait]
+    pub trait TribStorage: Send + Sync + 'static {
+        async fn get(
+            &self,
+            request: tonic::Request<super::Key>,
+        ) -> Result<tonic::Response<super::Value>, tonic::Status>;
+        async fn set(
+            &self,
+            request: tonic::Request<super::KeyValue>,
+        ) -> Result<tonic::Response<super::Bool>, tonic::Status>;
+        async fn set_with_ttl(
+            &self,
+            request: tonic::Request<super::ExpiringKeyValue>,
+        ) -> Result<tonic::Response<super::Bool>, tonic::Status>;
+        async fn keys(
+            &self,
+            request: tonic::Request<super::Pattern>,
+        ) -> Result<tonic::Response<super::StringList>, tonic::Status>;
+        async fn keys_page(
+            &self,
+            request: tonic::Request<super::KeysPageRequest>,
+        ) -> Result<tonic::Response<super::KeysPage>, tonic::Status>;
+        async fn multi_get(
+            &self,
+            request: tonic::Request<super::Keys>,
+        ) -> Result<tonic::Response<super::Values>, tonic::Status>;
+        async fn cas(
+            &self,
+            request: tonic::Request<super::CasRequest>,
+        ) -> Result<tonic::Response<super::Bool>, tonic::Status>;
+        async fn list_get(
+            &self,
+            request: tonic::Request<super::Key>,
+        ) -> Result<tonic::Response<super::StringList>, tonic::Status>;
+        async fn list_append(
+            &self,
+            request: tonic::Request<super::KeyValue>,
+        ) -> Result<tonic::Response<super::Bool>, tonic::Status>;
+        async fn list_append_with_ttl(
+            &self,
+            request: tonic::Request<super::ExpiringKeyValue>,
+        ) -> Result<tonic::Response<super::Bool>, tonic::Status>;
+        async fn list_remove(
+            &self,
+            request: tonic::Request<super::KeyValue>,
+        ) -> Result<tonic::Response<super::ListRemoveResponse>, tonic::Status>;
+        async fn list_trim(
+            &self,
+            request: tonic::Request<super::ListTrimRequest>,
+        ) -> Result<tonic::Response<super::ListRemoveResponse>, tonic::Status>;
+        async fn list_len(
+            &self,
+            request: tonic::Request<super::Key>,
+        ) -> Result<tonic::Response<super::ListLength>, tonic::Status>;
+        async fn list_range(
+            &self,
+            request: tonic::Request<super::ListRangeRequest>,
+        ) -> Result<tonic::Response<super::StringList>, tonic::Status>;
+        async fn multi_list_range(
+            &self,
+            request: tonic::Request<super::MultiListRangeRequest>,
+        ) -> Result<tonic::Response<super::StringLists>, tonic::Status>;
         async fn list_keys(
             &self,
             request: tonic::Request<super::Pattern>,
         ) -> Result<tonic::Response<super::StringList>, tonic::Status>;
+        async fn list_keys_page(
+            &self,
+            request: tonic::Request<super::KeysPageRequest>,
+        ) -> Result<tonic::Response<super::KeysPage>, tonic::Status>;
+        async fn multi_list_get(
+            &self,
+            request: tonic::Request<super::Keys>,
+        ) -> Result<tonic::Response<super::StringLists>, tonic::Status>;
+        async fn list_append_many(
+            &self,
+            request: tonic::Request<super::KeyValues>,
+        ) -> Result<tonic::Response<super::Bool>, tonic::Status>;
         async fn clock(
             &self,
             request: tonic::Request<super::Clock>,
         ) -> Result<tonic::Response<super::Clock>, tonic::Status>;
+        async fn txn(
+            &self,
+            request: tonic::Request<super::TxnRequest>,
+        ) -> Result<tonic::Response<super::TxnResponse>, tonic::Status>;
+        async fn health(
+            &self,
+            request: tonic::Request<super::HealthCheck>,
+        ) -> Result<tonic::Response<super::HealthStatus>, tonic::Status>;
+        async fn stats(
+            &self,
+            request: tonic::Request<super::StatsRequest>,
+        ) -> Result<tonic::Response<super::Stats>, tonic::Status>;
+        async fn get_bytes(
+            &self,
+            request: tonic::Request<super::BytesKey>,
+        ) -> Result<tonic::Response<super::BytesValue>, tonic::Status>;
+        async fn set_bytes(
+            &self,
+            request: tonic::Request<super::BytesKeyValue>,
+        ) -> Result<tonic::Response<super::Bool>, tonic::Status>;
+        #[doc = " the keys starting with the given prefix"]
+        async fn keys_bytes(
+            &self,
+            request: tonic::Request<super::BytesKey>,
+        ) -> Result<tonic::Response<super::BytesList>, tonic::Status>;
+        async fn list_get_bytes(
+            &self,
+            request: tonic::Request<super::BytesKey>,
+        ) -> Result<tonic::Response<super::BytesList>, tonic::Status>;
+        async fn list_append_bytes(
+            &self,
+            request: tonic::Request<super::BytesKeyValue>,
+        ) -> Result<tonic::Response<super::Bool>, tonic::Status>;
+        async fn list_remove_bytes(
+            &self,
+            request: tonic::Request<super::BytesKeyValue>,
+        ) -> Result<tonic::Response<super::ListRemoveResponse>, tonic::Status>;
+        #[doc = " the list names starting with the given prefix"]
+        async fn list_keys_bytes(
+            &self,
+            request: tonic::Request<super::BytesKey>,
+        ) -> Result<tonic::Response<super::BytesList>, tonic::Status>;
+        #[doc = "Server streaming response type for the Scan method."]
+        type ScanStream: futures_core::Stream<Item = Result<super::Record, tonic::Status>>
+            + Send
+            + 'static;
+        async fn scan(
+            &self,
+            request: tonic::Request<super::Pattern>,
+        ) -> Result<tonic::Response<Self::ScanStream>, tonic::Status>;
+        async fn ingest(
+            &self,
+            request: tonic::Request<tonic::Streaming<super::Record>>,
+        ) -> Result<tonic::Response<super::IngestResponse>, tonic::Status>;
+        #[doc = "Server streaming response type for the Watch method."]
+        type WatchStream: futures_core::Stream<Item = Result<super::Change, tonic::Status>>
+            + Send
+            + 'static;
+        async fn watch(
+            &self,
+            request: tonic::Request<super::WatchRequest>,
+        ) -> Result<tonic::Response<Self::WatchStream>, tonic::Status>;
     }
     #[derive(Debug)]
     pub struct TribStorageServer<T: TribStorage> {
@@ -354,18 +1091,18 @@ pub mod trib_storage_server {
                     };
                     Box::pin(fut)
                 }
-                "/rpc.TribStorage/keys" => {
+                "/rpc.TribStorage/setWithTtl" => {
                     #[allow(non_camel_case_types)]
-                    struct keysSvc<T: TribStorage>(pub Arc<T>);
-                    impl<T: TribStorage> tonic::server::UnaryService<super::Pattern> for keysSvc<T> {
-                        type Response = super::StringList;
+                    struct setWithTtlSvc<T: TribStorage>(pub Arc<T>);
+                    impl<T: TribStorage> tonic::server::UnaryService<super::ExpiringKeyValue> for setWithTtlSvc<T> {
+                        type Response = super::Bool;
                         type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::Pattern>,
+                            request: tonic::Request<super::ExpiringKeyValue>,
                         ) -> Self::Future {
                             let inner = self.0.clone();
-                            let fut = async move { (*inner).keys(request).await };
+                            let fut = async move { (*inner).set_with_ttl(request).await };
                             Box::pin(fut)
                         }
                     }
@@ -374,7 +1111,7 @@ pub mod trib_storage_server {
                     let inner = self.inner.clone();
                     let fut = async move {
                         let inner = inner.0;
-                        let method = keysSvc(inner);
+                        let method = setWithTtlSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
                             accept_compression_encodings,
@@ -385,15 +1122,18 @@ pub mod trib_storage_server {
                     };
                     Box::pin(fut)
                 }
-                "/rpc.TribStorage/listGet" => {
+                "/rpc.TribStorage/keys" => {
                     #[allow(non_camel_case_types)]
-                    struct listGetSvc<T: TribStorage>(pub Arc<T>);
-                    impl<T: TribStorage> tonic::server::UnaryService<super::Key> for listGetSvc<T> {
+                    struct keysSvc<T: TribStorage>(pub Arc<T>);
+                    impl<T: TribStorage> tonic::server::UnaryService<super::Pattern> for keysSvc<T> {
                         type Response = super::StringList;
                         type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
-                        fn call(&mut self, request: tonic::Request<super::Key>) -> Self::Future {
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::Pattern>,
+                        ) -> Self::Future {
                             let inner = self.0.clone();
-                            let fut = async move { (*inner).list_get(request).await };
+                            let fut = async move { (*inner).keys(request).await };
                             Box::pin(fut)
                         }
                     }
@@ -402,7 +1142,7 @@ pub mod trib_storage_server {
                     let inner = self.inner.clone();
                     let fut = async move {
                         let inner = inner.0;
-                        let method = listGetSvc(inner);
+                        let method = keysSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
                             accept_compression_encodings,
@@ -413,18 +1153,18 @@ pub mod trib_storage_server {
                     };
                     Box::pin(fut)
                 }
-                "/rpc.TribStorage/listAppend" => {
+                "/rpc.TribStorage/keysPage" => {
                     #[allow(non_camel_case_types)]
-                    struct listAppendSvc<T: TribStorage>(pub Arc<T>);
-                    impl<T: TribStorage> tonic::server::UnaryService<super::KeyValue> for listAppendSvc<T> {
-                        type Response = super::Bool;
+                    struct keysPageSvc<T: TribStorage>(pub Arc<T>);
+                    impl<T: TribStorage> tonic::server::UnaryService<super::KeysPageRequest> for keysPageSvc<T> {
+                        type Response = super::KeysPage;
                         type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::KeyValue>,
+                            request: tonic::Request<super::KeysPageRequest>,
                         ) -> Self::Future {
                             let inner = self.0.clone();
-                            let fut = async move { (*inner).list_append(request).await };
+                            let fut = async move { (*inner).keys_page(request).await };
                             Box::pin(fut)
                         }
                     }
@@ -433,7 +1173,7 @@ pub mod trib_storage_server {
                     let inner = self.inner.clone();
                     let fut = async move {
                         let inner = inner.0;
-                        let method = listAppendSvc(inner);
+                        let method = keysPageSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
                             accept_compression_encodings,
@@ -444,18 +1184,15 @@ pub mod trib_storage_server {
                     };
                     Box::pin(fut)
                 }
-                "/rpc.TribStorage/listRemove" => {
+                "/rpc.TribStorage/multiGet" => {
                     #[allow(non_camel_case_types)]
-                    struct listRemoveSvc<T: TribStorage>(pub Arc<T>);
-                    impl<T: TribStorage> tonic::server::UnaryService<super::KeyValue> for listRemoveSvc<T> {
-                        type Response = super::ListRemoveResponse;
+                    struct multiGetSvc<T: TribStorage>(pub Arc<T>);
+                    impl<T: TribStorage> tonic::server::UnaryService<super::Keys> for multiGetSvc<T> {
+                        type Response = super::Values;
                         type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
-                        fn call(
-                            &mut self,
-                            request: tonic::Request<super::KeyValue>,
-                        ) -> Self::Future {
+                        fn call(&mut self, request: tonic::Request<super::Keys>) -> Self::Future {
                             let inner = self.0.clone();
-                            let fut = async move { (*inner).list_remove(request).await };
+                            let fut = async move { (*inner).multi_get(request).await };
                             Box::pin(fut)
                         }
                     }
@@ -464,7 +1201,7 @@ pub mod trib_storage_server {
                     let inner = self.inner.clone();
                     let fut = async move {
                         let inner = inner.0;
-                        let method = listRemoveSvc(inner);
+                        let method = multiGetSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
                             accept_compression_encodings,
@@ -475,18 +1212,18 @@ pub mod trib_storage_server {
                     };
                     Box::pin(fut)
                 }
-                "/rpc.TribStorage/listKeys" => {
+                "/rpc.TribStorage/cas" => {
                     #[allow(non_camel_case_types)]
-                    struct listKeysSvc<T: TribStorage>(pub Arc<T>);
-                    impl<T: TribStorage> tonic::server::UnaryService<super::Pattern> for listKeysSvc<T> {
-                        type Response = super::StringList;
+                    struct casSvc<T: TribStorage>(pub Arc<T>);
+                    impl<T: TribStorage> tonic::server::UnaryService<super::CasRequest> for casSvc<T> {
+                        type Response = super::Bool;
                         type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::Pattern>,
+                            request: tonic::Request<super::CasRequest>,
                         ) -> Self::Future {
                             let inner = self.0.clone();
-                            let fut = async move { (*inner).list_keys(request).await };
+                            let fut = async move { (*inner).cas(request).await };
                             Box::pin(fut)
                         }
                     }
@@ -495,7 +1232,7 @@ pub mod trib_storage_server {
                     let inner = self.inner.clone();
                     let fut = async move {
                         let inner = inner.0;
-                        let method = listKeysSvc(inner);
+                        let method = casSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
                             accept_compression_encodings,
@@ -506,15 +1243,475 @@ pub mod trib_storage_server {
                     };
                     Box::pin(fut)
                 }
-                "/rpc.TribStorage/clock" => {
+                "/rpc.TribStorage/listGet" => {
                     #[allow(non_camel_case_types)]
-                    struct clockSvc<T: TribStorage>(pub Arc<T>);
-                    impl<T: TribStorage> tonic::server::UnaryService<super::Clock> for clockSvc<T> {
-                        type Response = super::Clock;
-                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                    struct listGetSvc<T: TribStorage>(pub Arc<T>);
+                    impl<T: TribStorage> tonic::server::UnaryService<super::Key> for listGetSvc<T> {
+                        type Response = super::StringList;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(&mut self, request: tonic::Request<super::Key>) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).list_get(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = listGetSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
+                            accept_compression_encodings,
+                            send_compression_encodings,
+                        );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/rpc.TribStorage/listAppend" => {
+                    #[allow(non_camel_case_types)]
+                    struct listAppendSvc<T: TribStorage>(pub Arc<T>);
+                    impl<T: TribStorage> tonic::server::UnaryService<super::KeyValue> for listAppendSvc<T> {
+                        type Response = super::Bool;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::KeyValue>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).list_append(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = listAppendSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
+                            accept_compression_encodings,
+                            send_compression_encodings,
+                        );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/rpc.TribStorage/listAppendWithTtl" => {
+                    #[allow(non_camel_case_types)]
+                    struct listAppendWithTtlSvc<T: TribStorage>(pub Arc<T>);
+                    impl<T: TribStorage> tonic::server::UnaryService<super::ExpiringKeyValue>
+                        for listAppendWithTtlSvc<T>
+                    {
+                        type Response = super::Bool;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::ExpiringKeyValue>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).list_append_with_ttl(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = listAppendWithTtlSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
+                            accept_compression_encodings,
+                            send_compression_encodings,
+                        );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/rpc.TribStorage/listRemove" => {
+                    #[allow(non_camel_case_types)]
+                    struct listRemoveSvc<T: TribStorage>(pub Arc<T>);
+                    impl<T: TribStorage> tonic::server::UnaryService<super::KeyValue> for listRemoveSvc<T> {
+                        type Response = super::ListRemoveResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::KeyValue>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).list_remove(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = listRemoveSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
+                            accept_compression_encodings,
+                            send_compression_encodings,
+                        );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/rpc.TribStorage/listTrim" => {
+                    #[allow(non_camel_case_types)]
+                    struct listTrimSvc<T: TribStorage>(pub Arc<T>);
+                    impl<T: TribStorage> tonic::server::UnaryService<super::ListTrimRequest> for listTrimSvc<T> {
+                        type Response = super::ListRemoveResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::ListTrimRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).list_trim(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = listTrimSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
+                            accept_compression_encodings,
+                            send_compression_encodings,
+                        );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/rpc.TribStorage/listLen" => {
+                    #[allow(non_camel_case_types)]
+                    struct listLenSvc<T: TribStorage>(pub Arc<T>);
+                    impl<T: TribStorage> tonic::server::UnaryService<super::Key> for listLenSvc<T> {
+                        type Response = super::ListLength;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(&mut self, request: tonic::Request<super::Key>) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).list_len(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = listLenSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
+                            accept_compression_encodings,
+                            send_compression_encodings,
+                        );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/rpc.TribStorage/listRange" => {
+                    #[allow(non_camel_case_types)]
+                    struct listRangeSvc<T: TribStorage>(pub Arc<T>);
+                    impl<T: TribStorage> tonic::server::UnaryService<super::ListRangeRequest> for listRangeSvc<T> {
+                        type Response = super::StringList;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::ListRangeRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).list_range(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = listRangeSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
+                            accept_compression_encodings,
+                            send_compression_encodings,
+                        );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/rpc.TribStorage/multiListRange" => {
+                    #[allow(non_camel_case_types)]
+                    struct multiListRangeSvc<T: TribStorage>(pub Arc<T>);
+                    impl<T: TribStorage> tonic::server::UnaryService<super::MultiListRangeRequest>
+                        for multiListRangeSvc<T>
+                    {
+                        type Response = super::StringLists;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::MultiListRangeRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).multi_list_range(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = multiListRangeSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
+                            accept_compression_encodings,
+                            send_compression_encodings,
+                        );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/rpc.TribStorage/listKeys" => {
+                    #[allow(non_camel_case_types)]
+                    struct listKeysSvc<T: TribStorage>(pub Arc<T>);
+                    impl<T: TribStorage> tonic::server::UnaryService<super::Pattern> for listKeysSvc<T> {
+                        type Response = super::StringList;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::Pattern>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).list_keys(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = listKeysSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
+                            accept_compression_encodings,
+                            send_compression_encodings,
+                        );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/rpc.TribStorage/listKeysPage" => {
+                    #[allow(non_camel_case_types)]
+                    struct listKeysPageSvc<T: TribStorage>(pub Arc<T>);
+                    impl<T: TribStorage> tonic::server::UnaryService<super::KeysPageRequest> for listKeysPageSvc<T> {
+                        type Response = super::KeysPage;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::KeysPageRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).list_keys_page(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = listKeysPageSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
+                            accept_compression_encodings,
+                            send_compression_encodings,
+                        );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/rpc.TribStorage/multiListGet" => {
+                    #[allow(non_camel_case_types)]
+                    struct multiListGetSvc<T: TribStorage>(pub Arc<T>);
+                    impl<T: TribStorage> tonic::server::UnaryService<super::Keys> for multiListGetSvc<T> {
+                        type Response = super::StringLists;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(&mut self, request: tonic::Request<super::Keys>) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).multi_list_get(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = multiListGetSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
+                            accept_compression_encodings,
+                            send_compression_encodings,
+                        );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/rpc.TribStorage/listAppendMany" => {
+                    #[allow(non_camel_case_types)]
+                    struct listAppendManySvc<T: TribStorage>(pub Arc<T>);
+                    impl<T: TribStorage> tonic::server::UnaryService<super::KeyValues> for listAppendManySvc<T> {
+                        type Response = super::Bool;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::KeyValues>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).list_append_many(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = listAppendManySvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
+                            accept_compression_encodings,
+                            send_compression_encodings,
+                        );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/rpc.TribStorage/clock" => {
+                    #[allow(non_camel_case_types)]
+                    struct clockSvc<T: TribStorage>(pub Arc<T>);
+                    impl<T: TribStorage> tonic::server::UnaryService<super::Clock> for clockSvc<T> {
+                        type Response = super::Clock;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
                         fn call(&mut self, request: tonic::Request<super::Clock>) -> Self::Future {
                             let inner = self.0.clone();
-                            let fut = async move { (*inner).clock(request).await };
+                            let fut = async move { (*inner).clock(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = clockSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
+                            accept_compression_encodings,
+                            send_compression_encodings,
+                        );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/rpc.TribStorage/txn" => {
+                    #[allow(non_camel_case_types)]
+                    struct txnSvc<T: TribStorage>(pub Arc<T>);
+                    impl<T: TribStorage> tonic::server::UnaryService<super::TxnRequest> for txnSvc<T> {
+                        type Response = super::TxnResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::TxnRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).txn(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = txnSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
+                            accept_compression_encodings,
+                            send_compression_encodings,
+                        );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/rpc.TribStorage/health" => {
+                    #[allow(non_camel_case_types)]
+                    struct healthSvc<T: TribStorage>(pub Arc<T>);
+                    impl<T: TribStorage> tonic::server::UnaryService<super::HealthCheck> for healthSvc<T> {
+                        type Response = super::HealthStatus;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::HealthCheck>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).health(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = healthSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
+                            accept_compression_encodings,
+                            send_compression_encodings,
+                        );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/rpc.TribStorage/stats" => {
+                    #[allow(non_camel_case_types)]
+                    struct statsSvc<T: TribStorage>(pub Arc<T>);
+                    impl<T: TribStorage> tonic::server::UnaryService<super::StatsRequest> for statsSvc<T> {
+                        type Response = super::Stats;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::StatsRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).stats(request).await };
                             Box::pin(fut)
                         }
                     }
@@ -523,7 +1720,224 @@ pub mod trib_storage_server {
                     let inner = self.inner.clone();
                     let fut = async move {
                         let inner = inner.0;
-                        let method = clockSvc(inner);
+                        let method = statsSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
+                            accept_compression_encodings,
+                            send_compression_encodings,
+                        );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/rpc.TribStorage/getBytes" => {
+                    #[allow(non_camel_case_types)]
+                    struct getBytesSvc<T: TribStorage>(pub Arc<T>);
+                    impl<T: TribStorage> tonic::server::UnaryService<super::BytesKey> for getBytesSvc<T> {
+                        type Response = super::BytesValue;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::BytesKey>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).get_bytes(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = getBytesSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
+                            accept_compression_encodings,
+                            send_compression_encodings,
+                        );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/rpc.TribStorage/setBytes" => {
+                    #[allow(non_camel_case_types)]
+                    struct setBytesSvc<T: TribStorage>(pub Arc<T>);
+                    impl<T: TribStorage> tonic::server::UnaryService<super::BytesKeyValue> for setBytesSvc<T> {
+                        type Response = super::Bool;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::BytesKeyValue>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).set_bytes(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = setBytesSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
+                            accept_compression_encodings,
+                            send_compression_encodings,
+                        );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/rpc.TribStorage/keysBytes" => {
+                    #[allow(non_camel_case_types)]
+                    struct keysBytesSvc<T: TribStorage>(pub Arc<T>);
+                    impl<T: TribStorage> tonic::server::UnaryService<super::BytesKey> for keysBytesSvc<T> {
+                        type Response = super::BytesList;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::BytesKey>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).keys_bytes(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = keysBytesSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
+                            accept_compression_encodings,
+                            send_compression_encodings,
+                        );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/rpc.TribStorage/listGetBytes" => {
+                    #[allow(non_camel_case_types)]
+                    struct listGetBytesSvc<T: TribStorage>(pub Arc<T>);
+                    impl<T: TribStorage> tonic::server::UnaryService<super::BytesKey> for listGetBytesSvc<T> {
+                        type Response = super::BytesList;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::BytesKey>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).list_get_bytes(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = listGetBytesSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
+                            accept_compression_encodings,
+                            send_compression_encodings,
+                        );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/rpc.TribStorage/listAppendBytes" => {
+                    #[allow(non_camel_case_types)]
+                    struct listAppendBytesSvc<T: TribStorage>(pub Arc<T>);
+                    impl<T: TribStorage> tonic::server::UnaryService<super::BytesKeyValue> for listAppendBytesSvc<T> {
+                        type Response = super::Bool;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::BytesKeyValue>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).list_append_bytes(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = listAppendBytesSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
+                            accept_compression_encodings,
+                            send_compression_encodings,
+                        );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/rpc.TribStorage/listRemoveBytes" => {
+                    #[allow(non_camel_case_types)]
+                    struct listRemoveBytesSvc<T: TribStorage>(pub Arc<T>);
+                    impl<T: TribStorage> tonic::server::UnaryService<super::BytesKeyValue> for listRemoveBytesSvc<T> {
+                        type Response = super::ListRemoveResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::BytesKeyValue>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).list_remove_bytes(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = listRemoveBytesSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
+                            accept_compression_encodings,
+                            send_compression_encodings,
+                        );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/rpc.TribStorage/listKeysBytes" => {
+                    #[allow(non_camel_case_types)]
+                    struct listKeysBytesSvc<T: TribStorage>(pub Arc<T>);
+                    impl<T: TribStorage> tonic::server::UnaryService<super::BytesKey> for listKeysBytesSvc<T> {
+                        type Response = super::BytesList;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::BytesKey>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).list_keys_bytes(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = listKeysBytesSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
                             accept_compression_encodings,
@@ -534,6 +1948,103 @@ pub mod trib_storage_server {
                     };
                     Box::pin(fut)
                 }
+                "/rpc.TribStorage/Scan" => {
+                    #[allow(non_camel_case_types)]
+                    struct ScanSvc<T: TribStorage>(pub Arc<T>);
+                    impl<T: TribStorage> tonic::server::ServerStreamingService<super::Pattern> for ScanSvc<T> {
+                        type Response = super::Record;
+                        type ResponseStream = T::ScanStream;
+                        type Future =
+                            BoxFuture<tonic::Response<Self::ResponseStream>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::Pattern>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).scan(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = ScanSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
+                            accept_compression_encodings,
+                            send_compression_encodings,
+                        );
+                        let res = grpc.server_streaming(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/rpc.TribStorage/Ingest" => {
+                    #[allow(non_camel_case_types)]
+                    struct IngestSvc<T: TribStorage>(pub Arc<T>);
+                    impl<T: TribStorage> tonic::server::ClientStreamingService<super::Record> for IngestSvc<T> {
+                        type Response = super::IngestResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<tonic::Streaming<super::Record>>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).ingest(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = IngestSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
+                            accept_compression_encodings,
+                            send_compression_encodings,
+                        );
+                        let res = grpc.client_streaming(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/rpc.TribStorage/Watch" => {
+                    #[allow(non_camel_case_types)]
+                    struct WatchSvc<T: TribStorage>(pub Arc<T>);
+                    impl<T: TribStorage> tonic::server::ServerStreamingService<super::WatchRequest> for WatchSvc<T> {
+                        type Response = super::Change;
+                        type ResponseStream = T::WatchStream;
+                        type Future =
+                            BoxFuture<tonic::Response<Self::ResponseStream>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::WatchRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).watch(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = WatchSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec).apply_compression_config(
+                            accept_compression_encodings,
+                            send_compression_encodings,
+                        );
+                        let res = grpc.server_streaming(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
                 _ => Box::pin(async move {
                     Ok(http::Response::builder()
                         .status(200)
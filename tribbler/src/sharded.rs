@@ -0,0 +1,812 @@
+//! module containing an in-memory implementation of the [Storage] trait
+//! which spreads its keys over independently locked shards.
+//!
+//! [MemStorage](crate::storage::MemStorage) keeps every key behind the same
+//! few locks, so a single writer holds up every reader. A [ShardedStorage]
+//! hashes each key and list name to one of its shards, and an operation only
+//! locks the shard its key lives in; only the numbering of changes for
+//! [Watch]ers is shared, and only by writes.
+//!
+//! A [MemoryLimit] applies to the whole storage. A write which needs room
+//! first drops the least recently used keys of its own shard; if that is not
+//! enough, it lets go of its shard, drops the least recently used key of the
+//! other shards and tries again. Writes to different shards at the same time
+//! may take the storage slightly over the limit.
+//!
+//! No lock is ever held across an `.await`: every operation does its work on
+//! the maps in one go, so the plain [std::sync] locks never hold up the
+//! async runtime for longer than a map lookup or update.
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex, PoisonError, RwLock, RwLockWriteGuard,
+    },
+    time::Duration,
+};
+
+use async_trait::async_trait;
+
+use crate::{
+    err::TribResult,
+    hlc::Hlc,
+    storage::{
+        apply_txn, deadline, expired, matching, now_ms, range_of, refused, stats_of, text,
+        text_list, time_left, watch_changes, ByteStorage, ChangeLog, ChangeStream, Expiry,
+        KeyBytes, KeyList, KeyPage, KeyString, KeyValue, KvMap, List, ListBytes, ListMap, Maps,
+        MemoryLimit, Pattern, Quota, Storage, StorageStats, Txn, TxnMaps, TxnResult, Usage, Watch,
+        SWEEP_INTERVAL,
+    },
+};
+
+/// number of shards of a [ShardedStorage] built by [ShardedStorage::new]
+pub const DEFAULT_SHARDS: usize = 64;
+
+/// The key-value pairs and lists whose names hash to one shard, with their
+/// expiry deadlines
+#[derive(Debug, Default)]
+struct Shard {
+    kvs: KvMap,
+    kv_list: ListMap,
+    expiry: Expiry,
+    // a mutex of its own, so that reads holding the shard's read lock can
+    // still mark keys as used
+    quota: Mutex<Quota>,
+}
+
+impl Shard {
+    fn maps(&mut self) -> Maps<'_> {
+        Maps {
+            kvs: &mut self.kvs,
+            kvl: &mut self.kv_list,
+            expiry: &mut self.expiry,
+            quota: self.quota.get_mut().unwrap_or_else(PoisonError::into_inner),
+        }
+    }
+}
+
+/// The shards locked by a transaction, in shard order
+struct Locked<'a> {
+    storage: &'a ShardedStorage,
+    shards: Vec<(usize, RwLockWriteGuard<'a, Shard>)>,
+}
+
+impl TxnMaps for Locked<'_> {
+    fn maps(&mut self, key: &str) -> Maps<'_> {
+        let i = self.storage.shard_index(key.as_bytes());
+        // every key of the transaction had its shard locked up front
+        let pos = self
+            .shards
+            .binary_search_by_key(&i, |(j, _)| *j)
+            .expect("shard of a transaction key is locked");
+        self.shards[pos].1.maps()
+    }
+}
+
+/// An in-memory storage with one lock per shard of keys; see the [module
+/// documentation](self).
+///
+/// It behaves like [MemStorage](crate::storage::MemStorage), except that
+/// reads of several keys ([KeyString::multi_get], [KeyString::keys] and the
+/// like) look at one shard at a time instead of at a single point in time.
+/// [Storage::transaction] still locks every shard it touches for the whole
+/// transaction.
+#[derive(Debug)]
+pub struct ShardedStorage {
+    shards: Vec<RwLock<Shard>>,
+    // shared by the quotas of every shard
+    usage: Arc<Usage>,
+    // whether reads have to touch the quotas, see Quota::evicting
+    evicting: AtomicBool,
+    // always locked after the shards, while the change is being made
+    changes: Mutex<ChangeLog>,
+    last_sweep: AtomicU64,
+    // the smallest reading that may be handed out next
+    clock: AtomicU64,
+}
+
+impl Default for ShardedStorage {
+    fn default() -> ShardedStorage {
+        ShardedStorage::new()
+    }
+}
+
+impl ShardedStorage {
+    /// Creates a new instance of [ShardedStorage] with [DEFAULT_SHARDS]
+    /// shards
+    pub fn new() -> ShardedStorage {
+        ShardedStorage::with_shards(DEFAULT_SHARDS)
+    }
+
+    /// Creates a new instance of [ShardedStorage] with `n` (at least one)
+    /// shards
+    pub fn with_shards(n: usize) -> ShardedStorage {
+        let usage = Arc::new(Usage::default());
+        let shard = || Shard {
+            quota: Mutex::new(Quota::new(usage.clone())),
+            ..Shard::default()
+        };
+        ShardedStorage {
+            shards: (0..n.max(1)).map(|_| RwLock::new(shard())).collect(),
+            usage,
+            evicting: AtomicBool::new(false),
+            changes: Mutex::default(),
+            last_sweep: AtomicU64::new(0),
+            clock: AtomicU64::new(0),
+        }
+    }
+
+    // keys are hashed as bytes, so that a key lands in the same shard
+    // whether it is given as text or as bytes
+    fn shard_index(&self, key: &[u8]) -> usize {
+        let mut h = DefaultHasher::new();
+        key.hash(&mut h);
+        (h.finish() % self.shards.len() as u64) as usize
+    }
+
+    fn shard(&self, key: &[u8]) -> &RwLock<Shard> {
+        &self.shards[self.shard_index(key)]
+    }
+
+    /// Drops every key and list whose deadline has passed, and returns how
+    /// many were dropped. Shards are swept one after the other.
+    pub fn sweep(&self) -> TribResult<usize> {
+        let now = now_ms();
+        self.last_sweep.store(now, Ordering::Relaxed);
+        let mut dropped = 0;
+        for shard in self.shards.iter() {
+            let mut shard = shard.write().map_err(|e| e.to_string())?;
+            let mut changes = self.changes.lock().map_err(|e| e.to_string())?;
+            dropped += shard.maps().sweep(now, &mut changes);
+        }
+        Ok(dropped)
+    }
+
+    fn sweep_if_due(&self) -> TribResult<()> {
+        let last = self.last_sweep.load(Ordering::Relaxed);
+        let now = now_ms();
+        if now.saturating_sub(last) < SWEEP_INTERVAL.as_millis() as u64 {
+            return Ok(());
+        }
+        // only the writer that wins the race sweeps
+        if self
+            .last_sweep
+            .compare_exchange(last, now, Ordering::Relaxed, Ordering::Relaxed)
+            .is_ok()
+        {
+            self.sweep()?;
+        }
+        Ok(())
+    }
+
+    /// Runs `f` with the shard of `key` locked for writing, sweeping first
+    /// if one is due. When `f` finds no room in the shard, it is run again
+    /// after every key evicted from the other shards.
+    fn write<T>(
+        &self,
+        key: &[u8],
+        mut f: impl FnMut(&mut Maps, &mut ChangeLog) -> TribResult<T>,
+    ) -> TribResult<T> {
+        self.sweep_if_due()?;
+        let i = self.shard_index(key);
+        loop {
+            let r = {
+                let mut shard = self.shards[i].write().map_err(|e| e.to_string())?;
+                let mut changes = self.changes.lock().map_err(|e| e.to_string())?;
+                f(&mut shard.maps(), &mut changes)
+            };
+            if !refused(&self.usage, &r) || !self.evict_elsewhere(i)? {
+                return r;
+            }
+            // only the final refusal counts
+            self.usage.rejected.fetch_sub(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Evicts the least recently used key of the shards other than `except`,
+    /// and returns whether there was one.
+    fn evict_elsewhere(&self, except: usize) -> TribResult<bool> {
+        if !self.evicting.load(Ordering::Relaxed) {
+            return Ok(false);
+        }
+        let mut oldest: Option<(u64, usize)> = None;
+        for (i, shard) in self.shards.iter().enumerate() {
+            if i == except {
+                continue;
+            }
+            let shard = shard.read().map_err(|e| e.to_string())?;
+            let quota = shard.quota.lock().map_err(|e| e.to_string())?;
+            match (quota.oldest(), oldest) {
+                (Some(stamp), Some((best, _))) if stamp >= best => {}
+                (Some(stamp), _) => oldest = Some((stamp, i)),
+                (None, _) => {}
+            }
+        }
+        let i = match oldest {
+            Some((_, i)) => i,
+            None => return Ok(false),
+        };
+        let mut shard = self.shards[i].write().map_err(|e| e.to_string())?;
+        let mut changes = self.changes.lock().map_err(|e| e.to_string())?;
+        // the key may have gone in the meantime, but then another took its
+        // place or the room it held is free
+        shard.maps().evict_oldest(&mut changes);
+        Ok(true)
+    }
+
+    /// Marks `key`, which lives in `shard`, as just read, when evicting.
+    fn touch(&self, shard: &Shard, key: &[u8]) -> TribResult<()> {
+        if self.evicting.load(Ordering::Relaxed) {
+            let mut quota = shard.quota.lock().map_err(|e| e.to_string())?;
+            quota.touch(key);
+        }
+        Ok(())
+    }
+
+    fn set_until(&self, key: &[u8], value: &[u8], deadline: Option<u64>) -> TribResult<bool> {
+        self.write(key, |maps, changes| {
+            maps.set(key, value, deadline, changes)?;
+            Ok(true)
+        })
+    }
+
+    fn list_push<V: AsRef<[u8]>>(
+        &self,
+        key: &[u8],
+        values: &[V],
+        at: u64,
+        deadline: Option<u64>,
+    ) -> TribResult<bool> {
+        self.write(key, |maps, changes| {
+            maps.push(key, values, at, deadline, changes)?;
+            Ok(true)
+        })
+    }
+
+    /// Reads the list `key` through `f`, as an empty list if it has expired.
+    fn with_list<T>(
+        &self,
+        key: &[u8],
+        now: u64,
+        f: impl FnOnce(&[Vec<u8>]) -> TribResult<T>,
+    ) -> TribResult<T> {
+        let shard = self.shard(key).read().map_err(|e| e.to_string())?;
+        match (
+            expired(&shard.expiry.lists, key, now),
+            shard.kv_list.get(key),
+        ) {
+            (false, Some(list)) => f(list),
+            _ => f(&[]),
+        }
+    }
+
+    /// The keys (or, with `lists`, the list names) of every shard that start
+    /// with `prefix`, end with `suffix`, sort after `start_after` and, with
+    /// `text`, are UTF-8, at most `limit` of them from each shard, in order.
+    fn matching_keys(
+        &self,
+        (prefix, suffix): (&[u8], &[u8]),
+        start_after: &[u8],
+        limit: usize,
+        lists: bool,
+        text: bool,
+    ) -> TribResult<Vec<Vec<u8>>> {
+        let now = now_ms();
+        let wanted = |k: &&Vec<u8>| !text || std::str::from_utf8(k).is_ok();
+        let mut keys = vec![];
+        for shard in self.shards.iter() {
+            let shard = shard.read().map_err(|e| e.to_string())?;
+            match lists {
+                true => keys.extend(
+                    matching(&shard.kv_list, prefix, suffix, start_after)
+                        .filter(|k| !expired(&shard.expiry.lists, k, now))
+                        .filter(wanted)
+                        .take(limit)
+                        .cloned(),
+                ),
+                false => keys.extend(
+                    matching(&shard.kvs, prefix, suffix, start_after)
+                        .filter(|k| !expired(&shard.expiry.kvs, k, now))
+                        .filter(wanted)
+                        .take(limit)
+                        .cloned(),
+                ),
+            }
+        }
+        keys.sort();
+        Ok(keys)
+    }
+
+    /// Same as [ShardedStorage::matching_keys] for the text keys matching
+    /// `p`.
+    fn text_keys(
+        &self,
+        p: &Pattern,
+        start_after: &str,
+        limit: usize,
+        lists: bool,
+    ) -> TribResult<Vec<String>> {
+        let affixes = (p.prefix.as_bytes(), p.suffix.as_bytes());
+        self.matching_keys(affixes, start_after.as_bytes(), limit, lists, true)?
+            .into_iter()
+            .map(text)
+            .collect()
+    }
+}
+
+#[async_trait]
+impl KeyString for ShardedStorage {
+    async fn get(&self, key: &str) -> TribResult<Option<String>> {
+        self.get_bytes(key.as_bytes()).await?.map(text).transpose()
+    }
+
+    async fn set(&self, kv: &KeyValue) -> TribResult<bool> {
+        self.set_until(kv.key.as_bytes(), kv.value.as_bytes(), None)
+    }
+
+    async fn set_with_ttl(&self, kv: &KeyValue, ttl: Duration) -> TribResult<bool> {
+        let deadline = Some(deadline(now_ms(), ttl));
+        self.set_until(kv.key.as_bytes(), kv.value.as_bytes(), deadline)
+    }
+
+    async fn ttl(&self, key: &str) -> TribResult<Option<Duration>> {
+        let key = key.as_bytes();
+        let shard = self.shard(key).read().map_err(|e| e.to_string())?;
+        Ok(time_left(&shard.expiry.kvs, key, now_ms()))
+    }
+
+    async fn cas(&self, key: &str, expected: Option<String>, new: &str) -> TribResult<bool> {
+        let expected = expected.unwrap_or_default();
+        let key = key.as_bytes();
+        self.write(key, |maps, changes| {
+            maps.cas(key, expected.as_bytes(), new.as_bytes(), now_ms(), changes)
+        })
+    }
+
+    async fn multi_get(&self, keys: &[String]) -> TribResult<Vec<Option<String>>> {
+        let mut values = Vec::with_capacity(keys.len());
+        for key in keys {
+            values.push(self.get(key).await?);
+        }
+        Ok(values)
+    }
+
+    async fn keys(&self, p: &Pattern) -> TribResult<List> {
+        Ok(List(self.text_keys(p, "", usize::MAX, false)?))
+    }
+
+    async fn keys_page(&self, p: &Pattern, start_after: &str, limit: usize) -> TribResult<KeyPage> {
+        // one more than a page from every shard tells whether there is a next
+        let per_shard = limit.max(1) + 1;
+        let keys = self.text_keys(p, start_after, per_shard, false)?;
+        Ok(KeyPage::from_sorted(keys.into_iter(), limit))
+    }
+}
+
+#[async_trait]
+impl KeyList for ShardedStorage {
+    async fn list_get(&self, key: &str) -> TribResult<List> {
+        text_list(self.list_get_bytes(key.as_bytes()).await?)
+    }
+
+    async fn list_append(&self, kv: &KeyValue) -> TribResult<bool> {
+        self.list_push(
+            kv.key.as_bytes(),
+            std::slice::from_ref(&kv.value),
+            now_ms(),
+            None,
+        )
+    }
+
+    async fn list_append_with_ttl(&self, kv: &KeyValue, ttl: Duration) -> TribResult<bool> {
+        let now = now_ms();
+        self.list_push(
+            kv.key.as_bytes(),
+            std::slice::from_ref(&kv.value),
+            now,
+            Some(deadline(now, ttl)),
+        )
+    }
+
+    async fn list_ttl(&self, key: &str) -> TribResult<Option<Duration>> {
+        let key = key.as_bytes();
+        let shard = self.shard(key).read().map_err(|e| e.to_string())?;
+        Ok(time_left(&shard.expiry.lists, key, now_ms()))
+    }
+
+    async fn list_remove(&self, kv: &KeyValue) -> TribResult<u32> {
+        self.list_remove_bytes(kv.key.as_bytes(), kv.value.as_bytes())
+            .await
+    }
+
+    async fn list_trim(&self, key: &str, keep_last: u64) -> TribResult<u32> {
+        let key = key.as_bytes();
+        self.write(key, |maps, changes| {
+            Ok(maps.trim(key, keep_last, now_ms(), changes) as u32)
+        })
+    }
+
+    async fn list_len(&self, key: &str) -> TribResult<u64> {
+        self.with_list(key.as_bytes(), now_ms(), |l| Ok(l.len() as u64))
+    }
+
+    async fn list_range(&self, key: &str, start: i64, end: i64) -> TribResult<List> {
+        self.with_list(key.as_bytes(), now_ms(), |l| {
+            text_list(range_of(l, start, end))
+        })
+    }
+
+    async fn multi_list_range(
+        &self,
+        keys: &[String],
+        start: i64,
+        end: i64,
+    ) -> TribResult<Vec<List>> {
+        let now = now_ms();
+        keys.iter()
+            .map(|k| self.with_list(k.as_bytes(), now, |l| text_list(range_of(l, start, end))))
+            .collect()
+    }
+
+    async fn list_keys(&self, p: &Pattern) -> TribResult<List> {
+        Ok(List(self.text_keys(p, "", usize::MAX, true)?))
+    }
+
+    async fn list_keys_page(
+        &self,
+        p: &Pattern,
+        start_after: &str,
+        limit: usize,
+    ) -> TribResult<KeyPage> {
+        let per_shard = limit.max(1) + 1;
+        let keys = self.text_keys(p, start_after, per_shard, true)?;
+        Ok(KeyPage::from_sorted(keys.into_iter(), limit))
+    }
+
+    async fn multi_list_get(&self, keys: &[String]) -> TribResult<Vec<List>> {
+        let now = now_ms();
+        keys.iter()
+            .map(|k| self.with_list(k.as_bytes(), now, |l| text_list(l.to_vec())))
+            .collect()
+    }
+
+    async fn list_append_many(&self, key: &str, values: &[String]) -> TribResult<bool> {
+        self.list_push(key.as_bytes(), values, now_ms(), None)
+    }
+
+    async fn list_append_many_with_ttl(
+        &self,
+        key: &str,
+        values: &[String],
+        ttl: Duration,
+    ) -> TribResult<bool> {
+        let now = now_ms();
+        self.list_push(key.as_bytes(), values, now, Some(deadline(now, ttl)))
+    }
+}
+
+#[async_trait]
+impl KeyBytes for ShardedStorage {
+    async fn get_bytes(&self, key: &[u8]) -> TribResult<Option<Vec<u8>>> {
+        let shard = self.shard(key).read().map_err(|e| e.to_string())?;
+        if expired(&shard.expiry.kvs, key, now_ms()) {
+            return Ok(None);
+        }
+        let value = shard.kvs.get(key).cloned();
+        if value.is_some() {
+            self.touch(&shard, key)?;
+        }
+        Ok(value)
+    }
+
+    async fn set_bytes(&self, key: &[u8], value: &[u8]) -> TribResult<bool> {
+        self.set_until(key, value, None)
+    }
+
+    async fn keys_bytes(&self, prefix: &[u8]) -> TribResult<Vec<Vec<u8>>> {
+        self.matching_keys((prefix, b""), b"", usize::MAX, false, false)
+    }
+}
+
+#[async_trait]
+impl ListBytes for ShardedStorage {
+    async fn list_get_bytes(&self, key: &[u8]) -> TribResult<Vec<Vec<u8>>> {
+        self.with_list(key, now_ms(), |l| Ok(l.to_vec()))
+    }
+
+    async fn list_append_bytes(&self, key: &[u8], value: &[u8]) -> TribResult<bool> {
+        self.list_push(key, &[value], now_ms(), None)
+    }
+
+    async fn list_remove_bytes(&self, key: &[u8], value: &[u8]) -> TribResult<u32> {
+        self.write(key, |maps, changes| {
+            Ok(maps.remove(key, value, now_ms(), changes) as u32)
+        })
+    }
+
+    async fn list_keys_bytes(&self, prefix: &[u8]) -> TribResult<Vec<Vec<u8>>> {
+        self.matching_keys((prefix, b""), b"", usize::MAX, true, false)
+    }
+}
+
+#[async_trait]
+impl Storage for ShardedStorage {
+    async fn clock(&self, at_least: u64) -> TribResult<u64> {
+        let mut ret = 0;
+        // the closure always succeeds, it is only retried on a lost race
+        let _ = self
+            .clock
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |next| {
+                let reading = Hlc::tick(Hlc::from_u64(next), Hlc::from_u64(at_least));
+                ret = reading.to_u64();
+                Some(reading.successor().to_u64())
+            });
+        Ok(ret)
+    }
+
+    async fn transaction(&self, txn: &Txn) -> TribResult<TxnResult> {
+        self.sweep_if_due()?;
+        let mut indices = txn
+            .keys()
+            .map(|k| self.shard_index(k.as_bytes()))
+            .collect::<Vec<_>>();
+        indices.sort_unstable();
+        indices.dedup();
+        // locking in shard order keeps concurrent transactions from deadlocking
+        let mut shards = Vec::with_capacity(indices.len());
+        for i in indices {
+            shards.push((i, self.shards[i].write().map_err(|e| e.to_string())?));
+        }
+        let mut locked = Locked {
+            storage: self,
+            shards,
+        };
+        let mut changes = self.changes.lock().map_err(|e| e.to_string())?;
+        let r = apply_txn(&mut locked, &mut changes, txn, now_ms());
+        refused(&self.usage, &r);
+        r
+    }
+
+    fn watcher(&self) -> Option<&dyn Watch> {
+        Some(self)
+    }
+
+    fn bytes(&self) -> Option<&dyn ByteStorage> {
+        Some(self)
+    }
+
+    fn set_memory_limit(&self, limit: MemoryLimit) -> TribResult<()> {
+        for shard in self.shards.iter() {
+            let mut shard = shard.write().map_err(|e| e.to_string())?;
+            let maps = shard.maps();
+            maps.quota.set_limit(Some(limit), maps.kvs);
+            self.evicting
+                .store(maps.quota.evicting(), Ordering::Relaxed);
+        }
+        Ok(())
+    }
+
+    async fn stats(&self) -> TribResult<StorageStats> {
+        let (mut keys, mut lists) = (0, 0);
+        for shard in self.shards.iter() {
+            let shard = shard.read().map_err(|e| e.to_string())?;
+            keys += shard.kvs.len();
+            lists += shard.kv_list.len();
+        }
+        let shard = self.shards[0].read().map_err(|e| e.to_string())?;
+        let quota = shard.quota.lock().map_err(|e| e.to_string())?;
+        Ok(stats_of(&quota, keys, lists))
+    }
+}
+
+#[async_trait]
+impl Watch for ShardedStorage {
+    async fn watch(&self, p: &Pattern, since: u64) -> TribResult<ChangeStream> {
+        watch_changes(&self.changes, p, since)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{sync::Arc, time::Duration};
+
+    use crate::{
+        err::TribResult,
+        storage::{
+            ChangeKind, EvictionPolicy, KeyBytes, KeyList, KeyString, KeyValue, ListBytes,
+            MemoryLimit, Pattern, Precondition, Storage, Txn, TxnOp, Watch,
+        },
+    };
+
+    use super::ShardedStorage;
+
+    fn pattern(prefix: &str, suffix: &str) -> Pattern {
+        Pattern {
+            prefix: prefix.to_string(),
+            suffix: suffix.to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn sharded_keys_in_order() -> TribResult<()> {
+        let s = ShardedStorage::with_shards(4);
+        for i in (0..20).rev() {
+            s.set(&KeyValue::new(&format!("k{:02}", i), "v")).await?;
+            s.list_append(&KeyValue::new(&format!("l{:02}", i), "v"))
+                .await?;
+        }
+        let want = (0..20).map(|i| format!("k{:02}", i)).collect::<Vec<_>>();
+        assert_eq!(want, s.keys(&pattern("k", "")).await?.0);
+
+        let mut paged = vec![];
+        let mut start_after = String::new();
+        loop {
+            let page = s.list_keys_page(&pattern("l", ""), &start_after, 3).await?;
+            paged.extend(page.keys.0);
+            match page.next {
+                Some(next) => start_after = next,
+                None => break,
+            }
+        }
+        let want = (0..20).map(|i| format!("l{:02}", i)).collect::<Vec<_>>();
+        assert_eq!(want, paged);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn sharded_list_remove_and_ttl() -> TribResult<()> {
+        let s = ShardedStorage::with_shards(4);
+        for v in ["a", "b", "a", "c"] {
+            s.list_append(&KeyValue::new("l", v)).await?;
+        }
+        assert_eq!(2, s.list_remove(&KeyValue::new("l", "a")).await?);
+        assert_eq!(vec!["b", "c"], s.list_get("l").await?.0);
+        assert_eq!(1, s.list_remove(&KeyValue::new("l", "b")).await?);
+        s.list_remove(&KeyValue::new("l", "c")).await?;
+        assert!(s.list_keys(&pattern("", "")).await?.0.is_empty());
+
+        s.set_with_ttl(&KeyValue::new("t", "v"), Duration::from_millis(50))
+            .await?;
+        assert_eq!(Some("v".to_string()), s.get("t").await?);
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(None, s.get("t").await?);
+        assert_eq!(1, s.sweep()?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn sharded_transaction_across_shards() -> TribResult<()> {
+        let s = ShardedStorage::with_shards(8);
+        s.set(&KeyValue::new("a", "1")).await?;
+        let txn = Txn {
+            checks: vec![
+                Precondition::Value {
+                    key: "a".to_string(),
+                    value: "1".to_string(),
+                },
+                Precondition::ListLen {
+                    key: "l".to_string(),
+                    len: 0,
+                },
+            ],
+            ops: (0..16)
+                .map(|i| TxnOp::Set(KeyValue::new(&format!("k{}", i), "x")))
+                .chain([
+                    TxnOp::ListAppend(KeyValue::new("l", "x")),
+                    TxnOp::Get("k3".to_string()),
+                ])
+                .collect(),
+        };
+        let res = s.transaction(&txn).await?;
+        assert!(res.committed);
+        assert_eq!(vec![Some("x".to_string())], res.values);
+        assert_eq!(16, s.keys(&pattern("k", "")).await?.0.len());
+        // the list is no longer empty
+        assert!(!s.transaction(&txn).await?.committed);
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn sharded_concurrent_appends() -> TribResult<()> {
+        let s = Arc::new(ShardedStorage::with_shards(4));
+        let mut watch = s.watch(&pattern("", ""), 0).await?;
+        let tasks = (0..8)
+            .map(|t| {
+                let s = s.clone();
+                tokio::spawn(async move {
+                    for i in 0..50 {
+                        let kv = KeyValue::new(&format!("l{}", i % 5), &format!("{}-{}", t, i));
+                        s.list_append(&kv).await?;
+                        s.clock(0).await?;
+                    }
+                    TribResult::Ok(())
+                })
+            })
+            .collect::<Vec<_>>();
+        for t in tasks {
+            t.await??;
+        }
+        let mut total = 0;
+        for i in 0..5 {
+            total += s.list_len(&format!("l{}", i)).await?;
+        }
+        assert_eq!(400, total);
+
+        let mut last = 0;
+        for _ in 0..400 {
+            let change = watch.recv().await.unwrap()?;
+            assert_eq!(ChangeKind::ListAppend, change.kind);
+            assert!(change.seq > last);
+            last = change.seq;
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn sharded_memory_limit() -> TribResult<()> {
+        let s = ShardedStorage::with_shards(4);
+        s.set_memory_limit(MemoryLimit {
+            bytes: 100,
+            policy: EvictionPolicy::EvictLru,
+        })?;
+        // every key takes 9 bytes, and a shard which runs out of its own
+        // keys to evict takes them from the others
+        for i in 0..100 {
+            s.set(&KeyValue::new(&format!("k{:02}", i), "123456"))
+                .await?;
+        }
+        let stats = s.stats().await?;
+        assert!(stats.used_bytes <= 100);
+        assert_eq!(100, stats.keys + stats.evicted);
+        assert_eq!(0, stats.rejected);
+        assert_eq!(None, s.get("k00").await?);
+        assert_eq!(Some("123456".to_string()), s.get("k99").await?);
+
+        s.set_memory_limit(MemoryLimit {
+            bytes: 100,
+            policy: EvictionPolicy::Reject,
+        })?;
+        let mut refused = 0;
+        for i in 0..20 {
+            if s.set(&KeyValue::new(&format!("r{:02}", i), "123456"))
+                .await
+                .is_err()
+            {
+                refused += 1;
+            }
+        }
+        assert!(refused > 0);
+        assert_eq!(refused, s.stats().await?.rejected);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn sharded_bytes() -> TribResult<()> {
+        let s = ShardedStorage::with_shards(4);
+        s.set_bytes(&[b'k', 0xff], &[0xfe]).await?;
+        s.set(&KeyValue::new("k1", "v")).await?;
+        s.set_bytes(b"k0", b"v").await?;
+        assert_eq!(Some(vec![0xfe]), s.get_bytes(&[b'k', 0xff]).await?);
+        assert_eq!(Some(b"v".to_vec()), s.get_bytes(b"k1").await?);
+        assert_eq!(vec!["k0", "k1"], s.keys(&pattern("k", "")).await?.0);
+        let want = vec![b"k0".to_vec(), b"k1".to_vec(), vec![b'k', 0xff]];
+        assert_eq!(want, s.keys_bytes(b"k").await?);
+
+        s.list_append_bytes(b"l", &[0xff]).await?;
+        assert!(s.list_get("l").await.is_err());
+        assert_eq!(1, s.list_remove_bytes(b"l", &[0xff]).await?);
+        assert!(s.list_keys_bytes(b"").await?.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn sharded_clock() -> TribResult<()> {
+        let s = ShardedStorage::new();
+        let a = s.clock(0).await?;
+        let b = s.clock(0).await?;
+        assert!(b > a);
+        assert!(s.clock(b + 1000).await? >= b + 1000);
+        Ok(())
+    }
+}
@@ -1,9 +1,21 @@
 #![allow(dead_code)]
 //! module containing Tribbler storage-related structs and implementations
 use async_trait::async_trait;
-use std::{collections::HashMap, sync::RwLock};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    ops::Bound,
+    str::Utf8Error,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex, RwLock,
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tokio::sync::{broadcast, mpsc};
 
-use crate::err::TribResult;
+use crate::err::{TribResult, TribblerError};
+use crate::hlc::Hlc;
 
 #[derive(Debug, Clone)]
 
@@ -46,6 +58,112 @@ impl Pattern {
 /// A wrapper type around a [Vec<String>]
 pub struct List(pub Vec<String>);
 
+impl List {
+    /// Returns the elements from index `start` to index `end`, both included.
+    /// Negative indices count back from the end, so `-1` is the last element.
+    /// Indices past either end are clamped to the list.
+    pub fn range(&self, start: i64, end: i64) -> List {
+        List(range_of(&self.0, start, end))
+    }
+}
+
+/// The elements `start` to `end` of `items`, see [List::range].
+pub(crate) fn range_of<T: Clone>(items: &[T], start: i64, end: i64) -> Vec<T> {
+    let len = items.len() as i64;
+    let resolve = |i: i64| if i < 0 { len + i } else { i };
+    let start = resolve(start).max(0);
+    let end = resolve(end).min(len - 1);
+    if start > end {
+        return vec![];
+    }
+    items[start as usize..=end as usize].to_vec()
+}
+
+#[derive(Debug, Clone)]
+/// One page of keys, as returned by [KeyString::keys_page] and
+/// [KeyList::list_keys_page]
+pub struct KeyPage {
+    /// the keys on this page, in order
+    pub keys: List,
+    /// the `start_after` that fetches the next page, or [None] when this is
+    /// the last one
+    pub next: Option<String>,
+}
+
+impl KeyPage {
+    /// Builds a page from the first `limit` (at least one) of `keys`, which
+    /// must already be in order.
+    pub fn from_sorted<I: Iterator<Item = String>>(mut keys: I, limit: usize) -> KeyPage {
+        let page = keys.by_ref().take(limit.max(1)).collect::<Vec<String>>();
+        let next = match keys.next() {
+            Some(_) => page.last().cloned(),
+            None => None,
+        };
+        KeyPage {
+            keys: List(page),
+            next,
+        }
+    }
+}
+
+/// The keys of `map` that start with `prefix`, end with `suffix` and sort
+/// after `start_after` (if it is not empty), in order. Only the range of keys
+/// sharing the prefix is visited.
+pub(crate) fn matching<'a, V>(
+    map: &'a BTreeMap<Vec<u8>, V>,
+    prefix: &'a [u8],
+    suffix: &'a [u8],
+    start_after: &[u8],
+) -> impl Iterator<Item = &'a Vec<u8>> + 'a {
+    let start = if start_after.is_empty() || start_after < prefix {
+        Bound::Included(prefix)
+    } else {
+        Bound::Excluded(start_after)
+    };
+    map.range::<[u8], _>((start, Bound::Unbounded))
+        .map(|(k, _)| k)
+        .take_while(move |k| k.starts_with(prefix))
+        .filter(move |k| k.ends_with(suffix))
+}
+
+/// The keys of `map` that match `p` and sort after `start_after`, as
+/// [matching], leaving out the keys which are not UTF-8.
+pub(crate) fn matching_text<'a, V>(
+    map: &'a BTreeMap<Vec<u8>, V>,
+    p: &'a Pattern,
+    start_after: &str,
+) -> impl Iterator<Item = (&'a Vec<u8>, String)> + 'a {
+    matching(
+        map,
+        p.prefix.as_bytes(),
+        p.suffix.as_bytes(),
+        start_after.as_bytes(),
+    )
+    .filter_map(|k| Some((k, std::str::from_utf8(k).ok()?.to_string())))
+}
+
+fn not_text(e: Utf8Error) -> Box<TribblerError> {
+    Box::new(TribblerError::Unknown(format!(
+        "value is not UTF-8, read it as bytes: {}",
+        e
+    )))
+}
+
+/// `bytes` as a [str], failing when they are not UTF-8.
+pub(crate) fn as_text(bytes: &[u8]) -> TribResult<&str> {
+    Ok(std::str::from_utf8(bytes).map_err(not_text)?)
+}
+
+/// `bytes` as a [String], failing when they are not UTF-8.
+pub(crate) fn text(bytes: Vec<u8>) -> TribResult<String> {
+    Ok(String::from_utf8(bytes).map_err(|e| not_text(e.utf8_error()))?)
+}
+
+/// Every entry of `list` as a [String], failing when one is not UTF-8.
+pub(crate) fn text_list(list: Vec<Vec<u8>>) -> TribResult<List> {
+    Ok(List(list.into_iter().map(text).collect::<TribResult<_>>()?))
+}
+
 #[async_trait]
 /// Key-value pair interfaces
 /// Default value for all keys is empty string
@@ -56,9 +174,48 @@ pub trait KeyString {
     /// Set kv.key to kv.value. return true when no error.
     async fn set(&self, kv: &KeyValue) -> TribResult<bool>;
 
+    /// Like [KeyString::set], but the pair disappears on its own once `ttl`
+    /// has passed. A plain [KeyString::set] or [KeyString::cas] of the same
+    /// key makes it permanent again.
+    async fn set_with_ttl(&self, kv: &KeyValue, ttl: Duration) -> TribResult<bool>;
+
+    /// How long until the pair `key` expires, or [None] when it never does.
+    /// Storages which keep no deadlines always answer [None].
+    async fn ttl(&self, _key: &str) -> TribResult<Option<Duration>> {
+        Ok(None)
+    }
+
+    /// Atomically sets `key` to `new`, but only if its current value is
+    /// `expected`. An `expected` of [None] (or an empty string) means the key
+    /// must currently be unset. Returns true if the value was swapped.
+    async fn cas(&self, key: &str, expected: Option<String>, new: &str) -> TribResult<bool>;
+
     /// List all the keys of non-empty pairs where the key matches
     /// the given pattern.
     async fn keys(&self, p: &Pattern) -> TribResult<List>;
+
+    /// Like [KeyString::keys], but returns them in order and at most `limit`
+    /// at a time, starting after the key `start_after`. Pass an empty
+    /// `start_after` for the first page and [KeyPage::next] for the ones after.
+    async fn keys_page(&self, p: &Pattern, start_after: &str, limit: usize) -> TribResult<KeyPage> {
+        let mut keys = self.keys(p).await?.0;
+        keys.sort();
+        Ok(KeyPage::from_sorted(
+            keys.into_iter()
+                .filter(|k| start_after.is_empty() || k.as_str() > start_after),
+            limit,
+        ))
+    }
+
+    /// Gets the values of many keys at once. The result has one entry per
+    /// key, in the same order, which is [None] for keys with no value.
+    async fn multi_get(&self, keys: &[String]) -> TribResult<Vec<Option<String>>> {
+        let mut values = Vec::with_capacity(keys.len());
+        for key in keys {
+            values.push(self.get(key).await?);
+        }
+        Ok(values)
+    }
 }
 
 #[async_trait]
@@ -70,15 +227,159 @@ pub trait KeyList {
     /// Append a string to the list. return true when no error.
     async fn list_append(&self, kv: &KeyValue) -> TribResult<bool>;
 
+    /// Like [KeyList::list_append], but the whole list disappears once `ttl`
+    /// has passed. Later appends of either kind leave that deadline alone,
+    /// unless they come with a new `ttl`.
+    async fn list_append_with_ttl(&self, kv: &KeyValue, ttl: Duration) -> TribResult<bool>;
+
+    /// The [KeyString::ttl] of the list `key`.
+    async fn list_ttl(&self, _key: &str) -> TribResult<Option<Duration>> {
+        Ok(None)
+    }
+
     /// Removes all elements that are equal to `kv.value` in list `kv.key`
     /// returns the number of elements removed.
     async fn list_remove(&self, kv: &KeyValue) -> TribResult<u32>;
 
+    /// Drops all but the last `keep_last` elements of the list and returns
+    /// the number of elements dropped.
+    async fn list_trim(&self, key: &str, keep_last: u64) -> TribResult<u32>;
+
+    /// Get the length of the list. 0 if not set.
+    async fn list_len(&self, key: &str) -> TribResult<u64> {
+        Ok(self.list_get(key).await?.0.len() as u64)
+    }
+
+    /// Get the elements of the list from index `start` to index `end`, both
+    /// included. See [List::range] for how indices are counted.
+    async fn list_range(&self, key: &str, start: i64, end: i64) -> TribResult<List> {
+        Ok(self.list_get(key).await?.range(start, end))
+    }
+
+    /// Gets the same range of many lists at once. The result has one list
+    /// per key, in the same order.
+    async fn multi_list_range(
+        &self,
+        keys: &[String],
+        start: i64,
+        end: i64,
+    ) -> TribResult<Vec<List>> {
+        let mut lists = Vec::with_capacity(keys.len());
+        for key in keys {
+            lists.push(self.list_range(key, start, end).await?);
+        }
+        Ok(lists)
+    }
+
     /// List all the keys of non-empty lists, where the key matches
     /// the given pattern.
     async fn list_keys(&self, p: &Pattern) -> TribResult<List>;
+
+    /// The paginated form of [KeyList::list_keys]; see [KeyString::keys_page].
+    async fn list_keys_page(
+        &self,
+        p: &Pattern,
+        start_after: &str,
+        limit: usize,
+    ) -> TribResult<KeyPage> {
+        let mut keys = self.list_keys(p).await?.0;
+        keys.sort();
+        Ok(KeyPage::from_sorted(
+            keys.into_iter()
+                .filter(|k| start_after.is_empty() || k.as_str() > start_after),
+            limit,
+        ))
+    }
+
+    /// Gets many lists at once. The result has one list per key, in the same
+    /// order, which is empty for keys that are not set.
+    async fn multi_list_get(&self, keys: &[String]) -> TribResult<Vec<List>> {
+        let mut lists = Vec::with_capacity(keys.len());
+        for key in keys {
+            lists.push(self.list_get(key).await?);
+        }
+        Ok(lists)
+    }
+
+    /// Appends all of `values`, in order, to the list `key`. return true when
+    /// no error.
+    async fn list_append_many(&self, key: &str, values: &[String]) -> TribResult<bool> {
+        let mut ok = true;
+        for value in values {
+            ok &= self
+                .list_append(&KeyValue {
+                    key: key.to_string(),
+                    value: value.to_string(),
+                })
+                .await?;
+        }
+        Ok(ok)
+    }
+
+    /// Like [KeyList::list_append_many], but gives the list a new deadline
+    /// as in [KeyList::list_append_with_ttl].
+    async fn list_append_many_with_ttl(
+        &self,
+        key: &str,
+        values: &[String],
+        ttl: Duration,
+    ) -> TribResult<bool> {
+        let (first, rest) = match values.split_first() {
+            Some(split) => split,
+            None => return Ok(true),
+        };
+        let ok = self
+            .list_append_with_ttl(&KeyValue::new(key, first), ttl)
+            .await?;
+        Ok(self.list_append_many(key, rest).await? && ok)
+    }
+}
+
+#[async_trait]
+/// Key-value pairs whose keys and values are arbitrary bytes. Keys and
+/// values written through [KeyString] are their UTF-8 bytes here, and bytes
+/// which are valid UTF-8 read back through [KeyString] as text; keys which
+/// are not are left out of [KeyString::keys].
+///
+/// A storage which holds bytes, like [MemStorage], keeps a single set of
+/// byte maps and serves [KeyString] and [KeyList] as a UTF-8 view of them.
+/// It is reached through [Storage::bytes] rather than being a bound of
+/// [Storage], so that storages which can only hold text, such as a bin
+/// stamping every value with a clock, do not have to implement it.
+pub trait KeyBytes {
+    /// Gets a value. If no value set, return [None]
+    async fn get_bytes(&self, key: &[u8]) -> TribResult<Option<Vec<u8>>>;
+
+    /// Sets `key` to `value`. An empty value clears the key.
+    async fn set_bytes(&self, key: &[u8], value: &[u8]) -> TribResult<bool>;
+
+    /// Lists the keys that start with `prefix`, in order.
+    async fn keys_bytes(&self, prefix: &[u8]) -> TribResult<Vec<Vec<u8>>>;
 }
 
+#[async_trait]
+/// Lists whose names and entries are arbitrary bytes, see [KeyBytes]
+pub trait ListBytes {
+    /// Gets the list. Empty if not set.
+    async fn list_get_bytes(&self, key: &[u8]) -> TribResult<Vec<Vec<u8>>>;
+
+    /// Appends `value` to the list `key`.
+    async fn list_append_bytes(&self, key: &[u8], value: &[u8]) -> TribResult<bool>;
+
+    /// Removes every copy of `value` from the list `key`, and returns how
+    /// many were removed.
+    async fn list_remove_bytes(&self, key: &[u8], value: &[u8]) -> TribResult<u32>;
+
+    /// Lists the names of the non-empty lists that start with `prefix`, in
+    /// order.
+    async fn list_keys_bytes(&self, prefix: &[u8]) -> TribResult<Vec<Vec<u8>>>;
+}
+
+/// A storage which holds arbitrary bytes, as returned by [Storage::bytes]
+pub trait ByteStorage: KeyBytes + ListBytes + Send + Sync {}
+
+impl<T: KeyBytes + ListBytes + Send + Sync> ByteStorage for T {}
+
 #[async_trait]
 /// A trait representing a storage interface
 /// The trait bounds for [KeyString] and [KeyList] respectively represent
@@ -88,17 +389,945 @@ pub trait Storage: KeyString + KeyList + Send + Sync {
     /// Returns an auto-incrementing clock. The returned value of each call will
     /// be unique, no smaller than `at_least`, and strictly larger than the
     /// value returned last time, unless it was [u64::MAX]
+    ///
+    /// [MemStorage] hands out packed [hybrid logical clock](crate::hlc)
+    /// readings, which also stay close to the wall clock.
     async fn clock(&self, at_least: u64) -> TribResult<u64>;
+
+    /// Runs `txn` atomically: when all of its checks hold, its steps are
+    /// carried out in order with no other change in between; otherwise
+    /// nothing changes.
+    async fn transaction(&self, txn: &Txn) -> TribResult<TxnResult>;
+
+    /// Returns this storage as a [Watch], if it can be watched.
+    fn watcher(&self) -> Option<&dyn Watch> {
+        None
+    }
+
+    /// Returns this storage as a [ByteStorage], if it can hold keys and
+    /// values which are not UTF-8.
+    fn bytes(&self) -> Option<&dyn ByteStorage> {
+        None
+    }
+
+    /// Caps the memory this storage may use from now on. Storages which
+    /// cannot enforce a limit refuse.
+    fn set_memory_limit(&self, _limit: MemoryLimit) -> TribResult<()> {
+        Err(Box::new(TribblerError::Unsupported(
+            "this storage does not support memory limits".to_string(),
+        )))
+    }
+
+    /// Reports how much this storage holds.
+    async fn stats(&self) -> TribResult<StorageStats> {
+        Err(Box::new(TribblerError::Unsupported(
+            "this storage does not report stats".to_string(),
+        )))
+    }
+}
+
+/// A condition a [Txn] checks before making any of its changes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Precondition {
+    /// the key holds `value`; an empty value means the key is not set
+    Value {
+        /// the key to look at
+        key: String,
+        /// the value it must hold
+        value: String,
+    },
+    /// the list has exactly `len` entries
+    ListLen {
+        /// the list to look at
+        key: String,
+        /// the length it must have
+        len: u64,
+    },
+}
+
+/// One step of a [Txn], with the same meaning as the method of the same
+/// name
+#[derive(Debug, Clone)]
+pub enum TxnOp {
+    /// reads the key, seeing every step before it
+    Get(String),
+    /// see [KeyString::set]
+    Set(KeyValue),
+    /// see [KeyList::list_append]
+    ListAppend(KeyValue),
+    /// see [KeyList::list_remove]
+    ListRemove(KeyValue),
+}
+
+/// A batch of steps carried out together by [Storage::transaction]
+#[derive(Debug, Clone, Default)]
+pub struct Txn {
+    /// every one of these must hold for the steps to be carried out
+    pub checks: Vec<Precondition>,
+    /// the steps, in order
+    pub ops: Vec<TxnOp>,
+}
+
+impl Txn {
+    /// Every key and list this transaction looks at or changes.
+    pub(crate) fn keys(&self) -> impl Iterator<Item = &str> {
+        let checked = self.checks.iter().map(|c| match c {
+            Precondition::Value { key, .. } | Precondition::ListLen { key, .. } => key.as_str(),
+        });
+        let changed = self.ops.iter().map(|op| match op {
+            TxnOp::Get(key) => key.as_str(),
+            TxnOp::Set(kv) | TxnOp::ListAppend(kv) | TxnOp::ListRemove(kv) => kv.key.as_str(),
+        });
+        checked.chain(changed)
+    }
+}
+
+/// The outcome of a [Txn]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TxnResult {
+    /// whether the checks held and the steps were carried out
+    pub committed: bool,
+    /// what every [TxnOp::Get] read, in order; empty when not committed
+    pub values: Vec<Option<String>>,
+}
+
+/// What a storage does with a write that would take it over its
+/// [MemoryLimit]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EvictionPolicy {
+    /// the write fails with [TribblerError::QuotaExceeded]
+    Reject,
+    /// the least recently used plain keys are dropped until the write fits.
+    /// Lists are never dropped, so a write which still does not fit fails
+    /// as with [EvictionPolicy::Reject].
+    EvictLru,
+}
+
+/// A cap on the bytes of keys, values, list names and list entries held by
+/// a storage
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MemoryLimit {
+    /// most bytes the storage may hold
+    pub bytes: u64,
+    /// what happens to the writes beyond that
+    pub policy: EvictionPolicy,
+}
+
+/// How much a storage holds, as reported by [Storage::stats]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StorageStats {
+    /// bytes of the keys, values, list names and list entries held
+    pub used_bytes: u64,
+    /// the [MemoryLimit::bytes] in force, if any
+    pub limit_bytes: Option<u64>,
+    /// number of keys set
+    pub keys: u64,
+    /// number of non-empty lists
+    pub lists: u64,
+    /// keys dropped to make room since the storage started
+    pub evicted: u64,
+    /// writes refused for lack of room since the storage started
+    pub rejected: u64,
+}
+
+/// What happened to a key or list, as reported by [Watch::watch]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    /// the key was set to [Change::value], or cleared when it is empty
+    Set,
+    /// [Change::value] was appended to the list
+    ListAppend,
+    /// every copy of [Change::value] was removed from the list
+    ListRemove,
+    /// the list was trimmed to its last [Change::value] entries
+    ListTrim,
+    /// the key expired
+    Expire,
+    /// the list expired
+    ListExpire,
+    /// the key was dropped to make room, see [EvictionPolicy::EvictLru]
+    Evict,
+}
+
+/// One change made to a storage
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Change {
+    /// position of this change among all changes made to the storage
+    pub seq: u64,
+    /// what happened
+    pub kind: ChangeKind,
+    /// the key or list that changed
+    pub key: String,
+    /// see [ChangeKind]; empty for expiries
+    pub value: String,
+}
+
+/// The changes a watcher receives, in order. An error is always the last
+/// item: the watcher fell too far behind and has to read the storage again.
+pub type ChangeStream = mpsc::Receiver<TribResult<Change>>;
+
+/// How many changes a [MemStorage] keeps around for watchers resuming from
+/// an earlier sequence number, and how far a watcher may fall behind.
+pub const WATCH_HISTORY: usize = 1024;
+
+#[async_trait]
+/// A storage which can report its changes as they happen
+pub trait Watch: Send + Sync {
+    /// Streams every change to a key or list matching `p`. With `since` set
+    /// to 0 only the changes made from now on are sent; otherwise the stream
+    /// resumes right after the change numbered `since`, which fails when
+    /// the storage no longer remembers what came after it.
+    async fn watch(&self, p: &Pattern, since: u64) -> TribResult<ChangeStream>;
+}
+
+/// How often the writes to a [MemStorage] also sweep out expired entries.
+pub const SWEEP_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Milliseconds since the Unix epoch. Expiry deadlines are wall-clock times so
+/// that they mean the same thing after a [crate::disk::DiskStorage] restarts.
+pub(crate) fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// The deadline, in milliseconds since the Unix epoch, of something that
+/// expires `ttl` after `at`.
+pub(crate) fn deadline(at: u64, ttl: Duration) -> u64 {
+    at.saturating_add(ttl.as_millis() as u64)
+}
+
+/// The expiry deadlines of the keys and lists of a [MemStorage], in
+/// milliseconds since the Unix epoch. Keys without one never expire.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct Expiry {
+    pub(crate) kvs: HashMap<Vec<u8>, u64>,
+    pub(crate) lists: HashMap<Vec<u8>, u64>,
+}
+
+pub(crate) fn expired(deadlines: &HashMap<Vec<u8>, u64>, key: &[u8], now: u64) -> bool {
+    matches!(deadlines.get(key), Some(d) if *d <= now)
+}
+
+/// How long `key` has left as of `now`, or [None] when it has no deadline.
+/// A deadline which has passed counts as a millisecond away, so that it is
+/// not taken for none.
+pub(crate) fn time_left(
+    deadlines: &HashMap<Vec<u8>, u64>,
+    key: &[u8],
+    now: u64,
+) -> Option<Duration> {
+    deadlines
+        .get(key)
+        .map(|d| Duration::from_millis(d.saturating_sub(now).max(1)))
+}
+
+/// The recent changes made to a [MemStorage], and the channel announcing new
+/// ones to its watchers
+#[derive(Debug)]
+pub(crate) struct ChangeLog {
+    // the sequence number of the last change
+    last: u64,
+    history: VecDeque<Change>,
+    tx: broadcast::Sender<Change>,
+}
+
+impl Default for ChangeLog {
+    fn default() -> ChangeLog {
+        // counting from the creation time in microseconds keeps the sequence
+        // numbers of a restarted storage above any it handed out before, so
+        // resuming against it fails instead of skipping changes
+        let last = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_micros() as u64)
+            .unwrap_or(0);
+        ChangeLog {
+            last,
+            history: VecDeque::with_capacity(WATCH_HISTORY),
+            tx: broadcast::channel(WATCH_HISTORY).0,
+        }
+    }
+}
+
+impl ChangeLog {
+    /// Numbers and announces a change. Keys and values which are not UTF-8
+    /// reach the watchers with the offending bytes replaced.
+    pub(crate) fn record(&mut self, kind: ChangeKind, key: &[u8], value: &[u8]) {
+        self.last += 1;
+        let change = Change {
+            seq: self.last,
+            kind,
+            key: String::from_utf8_lossy(key).into_owned(),
+            value: String::from_utf8_lossy(value).into_owned(),
+        };
+        if self.history.len() == WATCH_HISTORY {
+            self.history.pop_front();
+        }
+        self.history.push_back(change.clone());
+        // nobody watching is not an error
+        let _ = self.tx.send(change);
+    }
+}
+
+/// The key-value pairs of a storage, by key
+pub(crate) type KvMap = BTreeMap<Vec<u8>, Vec<u8>>;
+
+/// The lists of a storage, by name
+pub(crate) type ListMap = BTreeMap<Vec<u8>, Vec<Vec<u8>>>;
+
+/// Bytes, evictions and refusals counted across every [Quota] of a storage
+#[derive(Debug, Default)]
+pub(crate) struct Usage {
+    pub(crate) used: AtomicU64,
+    pub(crate) evicted: AtomicU64,
+    pub(crate) rejected: AtomicU64,
+    // orders the uses of keys across quotas
+    tick: AtomicU64,
+}
+
+/// The memory limit of a [MemStorage] (or of one shard of a
+/// [ShardedStorage](crate::sharded::ShardedStorage)), and the recency of its
+/// plain keys when it evicts them
+#[derive(Debug, Default)]
+pub(crate) struct Quota {
+    pub(crate) usage: Arc<Usage>,
+    pub(crate) limit: Option<MemoryLimit>,
+    // bytes of the plain keys, which could all be evicted
+    plain: u64,
+    // only kept under EvictionPolicy::EvictLru
+    stamps: HashMap<Vec<u8>, u64>,
+    lru: BTreeMap<u64, Vec<u8>>,
+}
+
+/// Bytes accounted for the key `key` holding `value`.
+fn kv_bytes(key: &[u8], value: &[u8]) -> u64 {
+    (key.len() + value.len()) as u64
+}
+
+/// Bytes accounted for the list `key`.
+fn list_bytes(key: &[u8], list: &[Vec<u8>]) -> u64 {
+    key.len() as u64 + list.iter().map(|v| v.len() as u64).sum::<u64>()
+}
+
+impl Quota {
+    /// A quota with no limit, counting into `usage`.
+    pub(crate) fn new(usage: Arc<Usage>) -> Quota {
+        Quota {
+            usage,
+            ..Quota::default()
+        }
+    }
+
+    /// Bytes held by `kvs` and `kvl`.
+    pub(crate) fn measure(kvs: &KvMap, kvl: &ListMap) -> u64 {
+        let kv = kvs.iter().map(|(k, v)| kv_bytes(k, v)).sum::<u64>();
+        kv + kvl.iter().map(|(k, l)| list_bytes(k, l)).sum::<u64>()
+    }
+
+    /// Puts `limit` in force for the keys in `kvs`, which start out as
+    /// recently used as each other.
+    pub(crate) fn set_limit(&mut self, limit: Option<MemoryLimit>, kvs: &KvMap) {
+        self.limit = limit;
+        self.plain = kvs.iter().map(|(k, v)| kv_bytes(k, v)).sum();
+        self.stamps.clear();
+        self.lru.clear();
+        for key in kvs.keys() {
+            self.touch(key);
+        }
+    }
+
+    /// Whether reads have to [Quota::touch] the keys they look at.
+    pub(crate) fn evicting(&self) -> bool {
+        matches!(
+            self.limit,
+            Some(MemoryLimit {
+                policy: EvictionPolicy::EvictLru,
+                ..
+            })
+        )
+    }
+
+    /// Marks the key `key` as just used.
+    pub(crate) fn touch(&mut self, key: &[u8]) {
+        if !self.evicting() {
+            return;
+        }
+        let tick = self.usage.tick.fetch_add(1, Ordering::Relaxed);
+        if let Some(old) = self.stamps.insert(key.to_vec(), tick) {
+            self.lru.remove(&old);
+        }
+        self.lru.insert(tick, key.to_vec());
+    }
+
+    /// When the least recently used key was last used, if there is one.
+    pub(crate) fn oldest(&self) -> Option<u64> {
+        self.lru.keys().next().copied()
+    }
+
+    fn forget(&mut self, key: &[u8]) {
+        if let Some(old) = self.stamps.remove(key) {
+            self.lru.remove(&old);
+        }
+    }
+
+    fn grow(&self, n: u64) {
+        self.usage.used.fetch_add(n, Ordering::Relaxed);
+    }
+
+    fn shrink(&self, n: u64) {
+        let _ = self
+            .usage
+            .used
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |u| {
+                Some(u.saturating_sub(n))
+            });
+    }
+}
+
+/// The key-value pairs, lists, expiry deadlines and quota a key lives in,
+/// all locked by the caller. Every change to them goes through here so that
+/// the bytes they hold stay accounted for.
+pub(crate) struct Maps<'a> {
+    pub(crate) kvs: &'a mut KvMap,
+    pub(crate) kvl: &'a mut ListMap,
+    pub(crate) expiry: &'a mut Expiry,
+    pub(crate) quota: &'a mut Quota,
+}
+
+impl Maps<'_> {
+    /// Drops the key `key`, and returns whether it was set.
+    fn drop_kv(&mut self, key: &[u8]) -> bool {
+        self.expiry.kvs.remove(key);
+        self.quota.forget(key);
+        match self.kvs.remove(key) {
+            Some(v) => {
+                self.quota.shrink(kv_bytes(key, &v));
+                self.quota.plain = self.quota.plain.saturating_sub(kv_bytes(key, &v));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Drops the list `key`, and returns whether it had any entries.
+    fn drop_list(&mut self, key: &[u8]) -> bool {
+        self.expiry.lists.remove(key);
+        match self.kvl.remove(key) {
+            Some(l) => {
+                self.quota.shrink(list_bytes(key, &l));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Drops the key and the list `key` if they have expired by `now`.
+    pub(crate) fn purge(&mut self, key: &[u8], now: u64, changes: &mut ChangeLog) {
+        if expired(&self.expiry.kvs, key, now) && self.drop_kv(key) {
+            changes.record(ChangeKind::Expire, key, b"");
+        }
+        if expired(&self.expiry.lists, key, now) && self.drop_list(key) {
+            changes.record(ChangeKind::ListExpire, key, b"");
+        }
+    }
+
+    /// Drops every key and list whose deadline has passed by `now`, and
+    /// returns how many were dropped.
+    pub(crate) fn sweep(&mut self, now: u64, changes: &mut ChangeLog) -> usize {
+        let due = |deadlines: &HashMap<Vec<u8>, u64>| {
+            deadlines
+                .iter()
+                .filter(|(_, d)| **d <= now)
+                .map(|(k, _)| k.clone())
+                .collect::<Vec<Vec<u8>>>()
+        };
+        let (kvs, lists) = (due(&self.expiry.kvs), due(&self.expiry.lists));
+        let mut dropped = 0;
+        for key in kvs {
+            if self.drop_kv(&key) {
+                changes.record(ChangeKind::Expire, &key, b"");
+                dropped += 1;
+            }
+        }
+        for key in lists {
+            if self.drop_list(&key) {
+                changes.record(ChangeKind::ListExpire, &key, b"");
+                dropped += 1;
+            }
+        }
+        dropped
+    }
+
+    /// Makes room under the memory limit for `grow` more bytes, evicting
+    /// plain keys other than `keep` if the policy allows it.
+    fn make_room(&mut self, grow: u64, keep: &[&[u8]], changes: &mut ChangeLog) -> TribResult<()> {
+        let limit = match self.quota.limit {
+            Some(limit) if grow > 0 => limit,
+            _ => return Ok(()),
+        };
+        // refuse up front rather than evict keys for a write that will not
+        // fit anyway
+        let mut evictable = match limit.policy {
+            EvictionPolicy::Reject => 0,
+            EvictionPolicy::EvictLru => self.quota.plain,
+        };
+        for key in keep {
+            let kept = self.kvs.get(*key).map_or(0, |v| kv_bytes(key, v));
+            evictable = evictable.saturating_sub(kept);
+        }
+        let used = self.quota.usage.used.load(Ordering::Relaxed);
+        if used + grow > limit.bytes + evictable {
+            return full(&limit);
+        }
+        while self.quota.usage.used.load(Ordering::Relaxed) + grow > limit.bytes {
+            let victim = match limit.policy {
+                EvictionPolicy::Reject => None,
+                EvictionPolicy::EvictLru => self
+                    .quota
+                    .lru
+                    .values()
+                    .find(|k| !keep.contains(&k.as_slice()))
+                    .cloned(),
+            };
+            match victim {
+                Some(key) => self.evict(&key, changes),
+                None => return full(&limit),
+            }
+        }
+        Ok(())
+    }
+
+    fn evict(&mut self, key: &[u8], changes: &mut ChangeLog) {
+        self.drop_kv(key);
+        self.quota.usage.evicted.fetch_add(1, Ordering::Relaxed);
+        changes.record(ChangeKind::Evict, key, b"");
+    }
+
+    /// Evicts the least recently used key, and returns whether there was
+    /// one.
+    pub(crate) fn evict_oldest(&mut self, changes: &mut ChangeLog) -> bool {
+        match self.quota.lru.values().next().cloned() {
+            Some(key) => {
+                self.evict(&key, changes);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Reads the key `key` as of time `now`, marking it as used.
+    pub(crate) fn get(&mut self, key: &[u8], now: u64) -> Option<Vec<u8>> {
+        if expired(&self.expiry.kvs, key, now) {
+            return None;
+        }
+        let value = self.kvs.get(key).cloned();
+        if value.is_some() {
+            self.quota.touch(key);
+        }
+        value
+    }
+
+    /// Sets the key `key` to `value`, or clears it when `value` is empty. A
+    /// `deadline` replaces the key's current one.
+    pub(crate) fn set(
+        &mut self,
+        key: &[u8],
+        value: &[u8],
+        deadline: Option<u64>,
+        changes: &mut ChangeLog,
+    ) -> TribResult<()> {
+        let old = self.kvs.get(key).map_or(0, |v| kv_bytes(key, v));
+        let new = match value.is_empty() {
+            true => 0,
+            false => kv_bytes(key, value),
+        };
+        self.make_room(new.saturating_sub(old), &[key], changes)?;
+        self.put(key, value, deadline, changes);
+        Ok(())
+    }
+
+    fn put(&mut self, key: &[u8], value: &[u8], deadline: Option<u64>, changes: &mut ChangeLog) {
+        self.drop_kv(key);
+        if !value.is_empty() {
+            self.kvs.insert(key.to_vec(), value.to_vec());
+            self.quota.grow(kv_bytes(key, value));
+            self.quota.plain += kv_bytes(key, value);
+            self.quota.touch(key);
+            if let Some(d) = deadline {
+                self.expiry.kvs.insert(key.to_vec(), d);
+            }
+        }
+        changes.record(ChangeKind::Set, key, value);
+    }
+
+    /// Sets the key `key` to `new` if it holds `expected` as of time `now`.
+    pub(crate) fn cas(
+        &mut self,
+        key: &[u8],
+        expected: &[u8],
+        new: &[u8],
+        now: u64,
+        changes: &mut ChangeLog,
+    ) -> TribResult<bool> {
+        let current = match expired(&self.expiry.kvs, key, now) {
+            true => &[][..],
+            false => self.kvs.get(key).map_or(&[][..], Vec::as_slice),
+        };
+        if current != expected {
+            return Ok(false);
+        }
+        self.set(key, new, None, changes)?;
+        Ok(true)
+    }
+
+    /// Appends `values` to the list `key` as of time `at`: a list that had
+    /// expired by then is replaced rather than extended. A `deadline`
+    /// replaces the list's current one.
+    pub(crate) fn push<V: AsRef<[u8]>>(
+        &mut self,
+        key: &[u8],
+        values: &[V],
+        at: u64,
+        deadline: Option<u64>,
+        changes: &mut ChangeLog,
+    ) -> TribResult<()> {
+        if expired(&self.expiry.lists, key, at) && self.drop_list(key) {
+            changes.record(ChangeKind::ListExpire, key, b"");
+        }
+        if values.is_empty() {
+            return Ok(());
+        }
+        let mut grow = values.iter().map(|v| v.as_ref().len() as u64).sum::<u64>();
+        if !self.kvl.contains_key(key) {
+            grow += key.len() as u64;
+        }
+        self.make_room(grow, &[], changes)?;
+        self.extend(key, values, deadline, changes);
+        Ok(())
+    }
+
+    fn extend<V: AsRef<[u8]>>(
+        &mut self,
+        key: &[u8],
+        values: &[V],
+        deadline: Option<u64>,
+        changes: &mut ChangeLog,
+    ) {
+        let list = match self.kvl.get_mut(key) {
+            Some(list) => list,
+            None => {
+                self.quota.grow(key.len() as u64);
+                self.kvl.entry(key.to_vec()).or_default()
+            }
+        };
+        list.extend(values.iter().map(|v| v.as_ref().to_vec()));
+        self.quota
+            .grow(values.iter().map(|v| v.as_ref().len() as u64).sum::<u64>());
+        if let Some(d) = deadline {
+            self.expiry.lists.insert(key.to_vec(), d);
+        }
+        for value in values {
+            changes.record(ChangeKind::ListAppend, key, value.as_ref());
+        }
+    }
+
+    /// Removes every copy of `value` from the list `key` in place as of time
+    /// `now`, dropping the list once it is empty, and returns how many were
+    /// removed.
+    pub(crate) fn remove(
+        &mut self,
+        key: &[u8],
+        value: &[u8],
+        now: u64,
+        changes: &mut ChangeLog,
+    ) -> usize {
+        self.purge(key, now, changes);
+        let (removed, empty) = match self.kvl.get_mut(key) {
+            Some(list) => {
+                let before = list.len();
+                list.retain(|x| x != value);
+                (before - list.len(), list.is_empty())
+            }
+            None => (0, false),
+        };
+        self.quota.shrink((removed * value.len()) as u64);
+        if empty {
+            self.drop_list(key);
+        }
+        if removed > 0 {
+            changes.record(ChangeKind::ListRemove, key, value);
+        }
+        removed
+    }
+
+    /// Drops all but the last `keep_last` entries of the list `key` as of
+    /// time `now`, and returns how many were dropped.
+    pub(crate) fn trim(
+        &mut self,
+        key: &[u8],
+        keep_last: u64,
+        now: u64,
+        changes: &mut ChangeLog,
+    ) -> usize {
+        self.purge(key, now, changes);
+        let list = match self.kvl.get_mut(key) {
+            Some(list) => list,
+            None => return 0,
+        };
+        let drop = list.len().saturating_sub(keep_last as usize);
+        let freed = list.drain(..drop).map(|v| v.len() as u64).sum::<u64>();
+        let empty = list.is_empty();
+        self.quota.shrink(freed);
+        if empty {
+            self.drop_list(key);
+        }
+        if drop > 0 {
+            changes.record(ChangeKind::ListTrim, key, keep_last.to_string().as_bytes());
+        }
+        drop
+    }
+}
+
+fn full(limit: &MemoryLimit) -> TribResult<()> {
+    Err(Box::new(TribblerError::QuotaExceeded(format!(
+        "storage memory limit of {} bytes reached",
+        limit.bytes
+    ))))
+}
+
+/// Whether `r` failed for lack of room, counting it as refused if so.
+pub(crate) fn refused<T>(usage: &Usage, r: &TribResult<T>) -> bool {
+    let full = matches!(r, Err(e) if matches!(
+        e.downcast_ref::<TribblerError>(),
+        Some(TribblerError::QuotaExceeded(_))
+    ));
+    if full {
+        usage.rejected.fetch_add(1, Ordering::Relaxed);
+    }
+    full
+}
+
+/// The [StorageStats] of a storage holding `keys` keys and `lists` lists
+/// and whose (first) quota is `quota`.
+pub(crate) fn stats_of(quota: &Quota, keys: usize, lists: usize) -> StorageStats {
+    StorageStats {
+        used_bytes: quota.usage.used.load(Ordering::Relaxed),
+        limit_bytes: quota.limit.map(|l| l.bytes),
+        keys: keys as u64,
+        lists: lists as u64,
+        evicted: quota.usage.evicted.load(Ordering::Relaxed),
+        rejected: quota.usage.rejected.load(Ordering::Relaxed),
+    }
+}
+
+/// Finds the maps holding a key, all of which stay locked for the whole of a
+/// transaction
+pub(crate) trait TxnMaps {
+    fn maps(&mut self, key: &str) -> Maps<'_>;
+}
+
+impl TxnMaps for Maps<'_> {
+    fn maps(&mut self, _key: &str) -> Maps<'_> {
+        Maps {
+            kvs: &mut *self.kvs,
+            kvl: &mut *self.kvl,
+            expiry: &mut *self.expiry,
+            quota: &mut *self.quota,
+        }
+    }
+}
+
+/// Runs `txn` as of time `now` against `maps`, see [Storage::transaction].
+pub(crate) fn apply_txn<M: TxnMaps>(
+    maps: &mut M,
+    changes: &mut ChangeLog,
+    txn: &Txn,
+    now: u64,
+) -> TribResult<TxnResult> {
+    for key in txn.keys() {
+        maps.maps(key).purge(key.as_bytes(), now, changes);
+    }
+
+    let holds = txn.checks.iter().all(|c| match c {
+        Precondition::Value { key, value } => {
+            maps.maps(key)
+                .kvs
+                .get(key.as_bytes())
+                .map_or(&[][..], Vec::as_slice)
+                == value.as_bytes()
+        }
+        Precondition::ListLen { key, len } => {
+            maps.maps(key)
+                .kvl
+                .get(key.as_bytes())
+                .map_or(0, |l| l.len() as u64)
+                == *len
+        }
+    });
+    if !holds {
+        return Ok(TxnResult {
+            committed: false,
+            values: vec![],
+        });
+    }
+
+    // a value written as bytes cannot be read back as text; finding out
+    // before any step is carried out keeps the transaction all or nothing
+    for op in txn.ops.iter() {
+        if let TxnOp::Get(key) = op {
+            if let Some(value) = maps.maps(key).kvs.get(key.as_bytes()) {
+                as_text(value)?;
+            }
+        }
+    }
+
+    // room for everything the steps could add is made up front, so that
+    // either all of them happen or none do
+    let keep = txn.keys().map(str::as_bytes).collect::<Vec<&[u8]>>();
+    let mut grow = BTreeMap::<&str, u64>::new();
+    for op in txn.ops.iter() {
+        if let TxnOp::Set(kv) | TxnOp::ListAppend(kv) = op {
+            *grow.entry(&kv.key).or_default() += kv_bytes(kv.key.as_bytes(), kv.value.as_bytes());
+        }
+    }
+    let mut reserved = vec![];
+    let mut result = Ok(());
+    for (key, n) in grow {
+        let mut m = maps.maps(key);
+        result = m.make_room(n, &keep, changes);
+        if result.is_err() {
+            break;
+        }
+        m.quota.grow(n);
+        reserved.push((key, n));
+    }
+    for (key, n) in reserved {
+        maps.maps(key).quota.shrink(n);
+    }
+    result?;
+
+    let mut values = vec![];
+    for op in txn.ops.iter() {
+        match op {
+            TxnOp::Get(key) => match maps.maps(key).get(key.as_bytes(), now) {
+                Some(value) => values.push(Some(text(value)?)),
+                None => values.push(None),
+            },
+            TxnOp::Set(kv) => {
+                let (key, value) = (kv.key.as_bytes(), kv.value.as_bytes());
+                maps.maps(&kv.key).put(key, value, None, changes)
+            }
+            TxnOp::ListAppend(kv) => {
+                let (key, value) = (kv.key.as_bytes(), kv.value.as_bytes());
+                maps.maps(&kv.key).extend(key, &[value], None, changes)
+            }
+            TxnOp::ListRemove(kv) => {
+                let (key, value) = (kv.key.as_bytes(), kv.value.as_bytes());
+                maps.maps(&kv.key).remove(key, value, now, changes);
+            }
+        }
+    }
+    Ok(TxnResult {
+        committed: true,
+        values,
+    })
+}
+
+/// Streams the changes recorded in `changes`, see [Watch::watch].
+pub(crate) fn watch_changes(
+    changes: &Mutex<ChangeLog>,
+    p: &Pattern,
+    since: u64,
+) -> TribResult<ChangeStream> {
+    let (backlog, mut rx) = {
+        let changes = changes.lock().map_err(|e| e.to_string())?;
+        let oldest = changes.history.front().map_or(changes.last + 1, |c| c.seq);
+        if since > changes.last || (since != 0 && since + 1 < oldest) {
+            return Err(Box::new(TribblerError::OutOfRange(format!(
+                "changes after {} are not available",
+                since
+            ))));
+        }
+        let backlog = changes
+            .history
+            .iter()
+            .filter(|c| since != 0 && c.seq > since && p.matches(&c.key))
+            .cloned()
+            .collect::<Vec<Change>>();
+        // subscribing under the lock means no change can slip in between
+        (backlog, changes.tx.subscribe())
+    };
+    let p = p.clone();
+    let (tx, out) = mpsc::channel(WATCH_HISTORY);
+    tokio::spawn(async move {
+        for change in backlog {
+            if tx.send(Ok(change)).await.is_err() {
+                return;
+            }
+        }
+        loop {
+            let change = tokio::select! {
+                _ = tx.closed() => return,
+                change = rx.recv() => change,
+            };
+            let item = match change {
+                Ok(change) if p.matches(&change.key) => Ok(change),
+                Ok(_) => continue,
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    Err(format!("watcher fell {} changes behind", n).into())
+                }
+                Err(broadcast::error::RecvError::Closed) => return,
+            };
+            let last = item.is_err();
+            if tx.send(item).await.is_err() || last {
+                return;
+            }
+        }
+    });
+    Ok(out)
+}
+
+/// Everything held by a [MemStorage]: its key-value pairs, lists, expiry
+/// deadlines and next clock value
+pub(crate) struct MemDump {
+    pub(crate) kvs: KvMap,
+    pub(crate) kv_list: ListMap,
+    pub(crate) expiry: Expiry,
+    pub(crate) clock: u64,
 }
 
 /// This is a toy implementation of a backend storage service.
 /// The trait definition requires this to be safe to utilize across threads
 /// because mutating methods (e.g. [KeyString::set] take `&self` instead of
 /// `&mut self`)
+///
+/// Keys and lists are kept in order, so listing the keys with a prefix only
+/// visits those keys.
+///
+/// Expired keys and lists are hidden from every read as soon as their
+/// deadline passes. They are only dropped from memory by a sweep, which
+/// writes trigger at most once every [SWEEP_INTERVAL], or by
+/// [MemStorage::sweep].
+///
+/// Every change is numbered and announced to its [Watch]ers; the last
+/// [WATCH_HISTORY] changes are kept so that watchers can resume.
+///
+/// The bytes held are tracked as they change, and a [MemoryLimit] set with
+/// [Storage::set_memory_limit] is enforced on every write.
 #[derive(Debug, Default)]
 pub struct MemStorage {
-    kvs: RwLock<HashMap<String, String>>,
-    kv_list: RwLock<HashMap<String, List>>,
+    kvs: RwLock<KvMap>,
+    kv_list: RwLock<ListMap>,
+    // always locked after kvs and kv_list
+    expiry: RwLock<Expiry>,
+    // locked after expiry
+    quota: Mutex<Quota>,
+    // whether reads have to touch the quota, see Quota::evicting
+    evicting: AtomicBool,
+    // always locked last, while the change is being made
+    changes: Mutex<ChangeLog>,
+    last_sweep: AtomicU64,
     clock: RwLock<u64>,
 }
 
@@ -107,115 +1336,432 @@ impl MemStorage {
     pub fn new() -> MemStorage {
         MemStorage::default()
     }
+
+    /// Builds a [MemStorage] pre-populated with everything in `dump`.
+    pub(crate) fn restore(dump: MemDump) -> MemStorage {
+        let usage = Usage::default();
+        usage
+            .used
+            .store(Quota::measure(&dump.kvs, &dump.kv_list), Ordering::Relaxed);
+        MemStorage {
+            kvs: RwLock::new(dump.kvs),
+            kv_list: RwLock::new(dump.kv_list),
+            expiry: RwLock::new(dump.expiry),
+            quota: Mutex::new(Quota::new(Arc::new(usage))),
+            evicting: AtomicBool::new(false),
+            changes: Mutex::default(),
+            last_sweep: AtomicU64::new(0),
+            clock: RwLock::new(dump.clock),
+        }
+    }
+
+    /// Copies out every key-value pair, every list, every expiry deadline and
+    /// the next clock value held by this storage.
+    pub(crate) fn dump(&self) -> TribResult<MemDump> {
+        let kvs = self.kvs.read().map_err(|e| e.to_string())?.clone();
+        let kv_list = self.kv_list.read().map_err(|e| e.to_string())?.clone();
+        let expiry = self.expiry.read().map_err(|e| e.to_string())?.clone();
+        let clock = *self.clock.read().map_err(|e| e.to_string())?;
+        Ok(MemDump {
+            kvs,
+            kv_list,
+            expiry,
+            clock,
+        })
+    }
+
+    /// Runs `f` with everything locked for writing.
+    fn locked<T>(
+        &self,
+        f: impl FnOnce(&mut Maps, &mut ChangeLog) -> TribResult<T>,
+    ) -> TribResult<T> {
+        let mut kvs = self.kvs.write().map_err(|e| e.to_string())?;
+        let mut kvl = self.kv_list.write().map_err(|e| e.to_string())?;
+        let mut expiry = self.expiry.write().map_err(|e| e.to_string())?;
+        let mut quota = self.quota.lock().map_err(|e| e.to_string())?;
+        let mut changes = self.changes.lock().map_err(|e| e.to_string())?;
+        let mut maps = Maps {
+            kvs: &mut kvs,
+            kvl: &mut kvl,
+            expiry: &mut expiry,
+            quota: &mut quota,
+        };
+        f(&mut maps, &mut changes)
+    }
+
+    /// Same as [MemStorage::locked], but sweeps first if one is due.
+    fn write<T>(
+        &self,
+        f: impl FnOnce(&mut Maps, &mut ChangeLog) -> TribResult<T>,
+    ) -> TribResult<T> {
+        self.sweep_if_due()?;
+        let r = self.locked(f);
+        if r.is_err() {
+            let quota = self.quota.lock().map_err(|e| e.to_string())?;
+            refused(&quota.usage, &r);
+        }
+        r
+    }
+
+    /// Marks `keys` as just read, when evicting.
+    fn touch<'a>(&self, keys: impl Iterator<Item = &'a [u8]>) -> TribResult<()> {
+        if self.evicting.load(Ordering::Relaxed) {
+            let mut quota = self.quota.lock().map_err(|e| e.to_string())?;
+            keys.for_each(|k| quota.touch(k));
+        }
+        Ok(())
+    }
+
+    /// Drops every key and list whose deadline has passed, and returns how
+    /// many were dropped.
+    pub fn sweep(&self) -> TribResult<usize> {
+        let now = now_ms();
+        self.last_sweep.store(now, Ordering::Relaxed);
+        self.locked(|maps, changes| Ok(maps.sweep(now, changes)))
+    }
+
+    fn sweep_if_due(&self) -> TribResult<()> {
+        let last = self.last_sweep.load(Ordering::Relaxed);
+        if now_ms().saturating_sub(last) >= SWEEP_INTERVAL.as_millis() as u64 {
+            self.sweep()?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn set_until(
+        &self,
+        key: &[u8],
+        value: &[u8],
+        deadline: Option<u64>,
+    ) -> TribResult<bool> {
+        self.write(|maps, changes| {
+            maps.set(key, value, deadline, changes)?;
+            Ok(true)
+        })
+    }
+
+    /// Appends `values` to the list `key` as of time `at`: a list that had
+    /// expired by then is replaced rather than extended. A `deadline` replaces
+    /// the list's current one.
+    pub(crate) fn list_push<V: AsRef<[u8]>>(
+        &self,
+        key: &[u8],
+        values: &[V],
+        at: u64,
+        deadline: Option<u64>,
+    ) -> TribResult<bool> {
+        self.write(|maps, changes| {
+            maps.push(key, values, at, deadline, changes)?;
+            Ok(true)
+        })
+    }
+
+    /// Reads the list `key` through `f`, as an empty list if it has expired
+    /// by `now`.
+    fn with_list<T>(
+        &self,
+        key: &[u8],
+        now: u64,
+        f: impl FnOnce(&[Vec<u8>]) -> TribResult<T>,
+    ) -> TribResult<T> {
+        let kvl = self.kv_list.read().map_err(|e| e.to_string())?;
+        let expiry = self.expiry.read().map_err(|e| e.to_string())?;
+        match (expired(&expiry.lists, key, now), kvl.get(key)) {
+            (false, Some(list)) => f(list),
+            _ => f(&[]),
+        }
+    }
+
+    /// Runs `txn` as of time `now`, see [Storage::transaction].
+    pub(crate) fn transaction_at(&self, txn: &Txn, now: u64) -> TribResult<TxnResult> {
+        self.write(|maps, changes| apply_txn(maps, changes, txn, now))
+    }
 }
 
 #[async_trait]
 impl KeyString for MemStorage {
     async fn get(&self, key: &str) -> TribResult<Option<String>> {
-        match self.kvs.read().map_err(|e| e.to_string())?.get(key) {
-            Some(v) => Ok(Some(v.to_string())),
-            None => Ok(None),
-        }
+        self.get_bytes(key.as_bytes()).await?.map(text).transpose()
     }
 
     async fn set(&self, kv: &KeyValue) -> TribResult<bool> {
-        let mut entry = self.kvs.write().map_err(|e| e.to_string())?;
-        if kv.value.is_empty() {
-            entry.remove(&kv.key);
-        } else {
-            entry.insert(kv.key.clone(), kv.value.clone());
-        }
-        Ok(true)
+        self.set_until(kv.key.as_bytes(), kv.value.as_bytes(), None)
+    }
+
+    async fn set_with_ttl(&self, kv: &KeyValue, ttl: Duration) -> TribResult<bool> {
+        let deadline = deadline(now_ms(), ttl);
+        self.set_until(kv.key.as_bytes(), kv.value.as_bytes(), Some(deadline))
+    }
+
+    async fn ttl(&self, key: &str) -> TribResult<Option<Duration>> {
+        let expiry = self.expiry.read().map_err(|e| e.to_string())?;
+        Ok(time_left(&expiry.kvs, key.as_bytes(), now_ms()))
+    }
+
+    async fn cas(&self, key: &str, expected: Option<String>, new: &str) -> TribResult<bool> {
+        let expected = expected.unwrap_or_default();
+        self.write(|maps, changes| {
+            maps.cas(
+                key.as_bytes(),
+                expected.as_bytes(),
+                new.as_bytes(),
+                now_ms(),
+                changes,
+            )
+        })
+    }
+
+    async fn multi_get(&self, keys: &[String]) -> TribResult<Vec<Option<String>>> {
+        let values = {
+            let kvs = self.kvs.read().map_err(|e| e.to_string())?;
+            let expiry = self.expiry.read().map_err(|e| e.to_string())?;
+            let now = now_ms();
+            self.touch(
+                keys.iter()
+                    .map(String::as_bytes)
+                    .filter(|k| kvs.contains_key(*k)),
+            )?;
+            keys.iter()
+                .map(|k| match expired(&expiry.kvs, k.as_bytes(), now) {
+                    true => None,
+                    false => kvs.get(k.as_bytes()).cloned(),
+                })
+                .collect::<Vec<_>>()
+        };
+        values
+            .into_iter()
+            .map(|v| v.map(text).transpose())
+            .collect()
     }
 
     async fn keys(&self, p: &Pattern) -> TribResult<List> {
-        let result = self
-            .kvs
-            .read()
-            .map_err(|e| e.to_string())?
-            .iter()
-            .filter(|(k, _)| p.matches(*k))
-            .map(|(k, _)| k.to_string())
+        let kvs = self.kvs.read().map_err(|e| e.to_string())?;
+        let expiry = self.expiry.read().map_err(|e| e.to_string())?;
+        let now = now_ms();
+        let result = matching_text(&kvs, p, "")
+            .filter(|(k, _)| !expired(&expiry.kvs, k, now))
+            .map(|(_, k)| k)
             .collect::<Vec<String>>();
         Ok(List(result))
     }
+
+    async fn keys_page(&self, p: &Pattern, start_after: &str, limit: usize) -> TribResult<KeyPage> {
+        let kvs = self.kvs.read().map_err(|e| e.to_string())?;
+        let expiry = self.expiry.read().map_err(|e| e.to_string())?;
+        let now = now_ms();
+        let keys = matching_text(&kvs, p, start_after)
+            .filter(|(k, _)| !expired(&expiry.kvs, k, now))
+            .map(|(_, k)| k);
+        Ok(KeyPage::from_sorted(keys, limit))
+    }
 }
 
 #[async_trait]
 impl KeyList for MemStorage {
     async fn list_get(&self, key: &str) -> TribResult<List> {
-        match self.kv_list.read().map_err(|e| e.to_string())?.get(key) {
-            Some(l) => Ok(l.clone()),
-            None => Ok(List(vec![])),
-        }
+        text_list(self.list_get_bytes(key.as_bytes()).await?)
     }
 
     async fn list_append(&self, kv: &KeyValue) -> TribResult<bool> {
-        let mut kvl = self.kv_list.write().map_err(|e| e.to_string())?;
-        match kvl.get_mut(&kv.key) {
-            Some(list) => {
-                list.0.push(kv.value.clone());
-                Ok(true)
-            }
-            None => {
-                let list = vec![kv.value.clone()];
-                kvl.insert(kv.key.clone(), List(list));
-                Ok(true)
-            }
-        }
+        self.list_push(kv.key.as_bytes(), &[&kv.value], now_ms(), None)
+    }
+
+    async fn list_append_with_ttl(&self, kv: &KeyValue, ttl: Duration) -> TribResult<bool> {
+        let now = now_ms();
+        self.list_push(
+            kv.key.as_bytes(),
+            &[&kv.value],
+            now,
+            Some(deadline(now, ttl)),
+        )
+    }
+
+    async fn list_ttl(&self, key: &str) -> TribResult<Option<Duration>> {
+        let expiry = self.expiry.read().map_err(|e| e.to_string())?;
+        Ok(time_left(&expiry.lists, key.as_bytes(), now_ms()))
     }
 
     async fn list_remove(&self, kv: &KeyValue) -> TribResult<u32> {
-        let mut removed = 0;
-        let mut kvl = self.kv_list.write().map_err(|e| e.to_string())?;
-        kvl.entry(kv.key.clone()).and_modify(|list| {
-            let begin_size = list.0.len();
-            *list = List(
-                list.0
-                    .iter()
-                    .filter(|val| **val != kv.value)
-                    .map(String::from)
-                    .collect::<Vec<String>>(),
-            );
-            let end_size = list.0.len();
-            removed = begin_size - end_size;
-        });
-        if let Some(x) = kvl.get(&kv.key) {
-            if x.0.is_empty() {
-                kvl.remove(&kv.key);
-            }
-        };
+        self.list_remove_bytes(kv.key.as_bytes(), kv.value.as_bytes())
+            .await
+    }
+
+    async fn list_trim(&self, key: &str, keep_last: u64) -> TribResult<u32> {
+        self.write(|maps, changes| {
+            Ok(maps.trim(key.as_bytes(), keep_last, now_ms(), changes) as u32)
+        })
+    }
+
+    async fn list_len(&self, key: &str) -> TribResult<u64> {
+        self.with_list(key.as_bytes(), now_ms(), |l| Ok(l.len() as u64))
+    }
+
+    async fn list_range(&self, key: &str, start: i64, end: i64) -> TribResult<List> {
+        self.with_list(key.as_bytes(), now_ms(), |l| {
+            text_list(range_of(l, start, end))
+        })
+    }
 
-        Ok(removed as u32)
+    async fn multi_list_range(
+        &self,
+        keys: &[String],
+        start: i64,
+        end: i64,
+    ) -> TribResult<Vec<List>> {
+        let now = now_ms();
+        keys.iter()
+            .map(|k| self.with_list(k.as_bytes(), now, |l| text_list(range_of(l, start, end))))
+            .collect()
     }
 
     async fn list_keys(&self, p: &Pattern) -> TribResult<List> {
-        let mut result = vec![];
-        self.kv_list
-            .read()
-            .map_err(|e| e.to_string())?
-            .iter()
-            .filter(|(k, _)| p.matches(*k))
-            .for_each(|(v, _)| result.push((*v).clone()));
-        result.sort();
+        let kvl = self.kv_list.read().map_err(|e| e.to_string())?;
+        let expiry = self.expiry.read().map_err(|e| e.to_string())?;
+        let now = now_ms();
+        let result = matching_text(&kvl, p, "")
+            .filter(|(k, _)| !expired(&expiry.lists, k, now))
+            .map(|(_, k)| k)
+            .collect::<Vec<String>>();
         Ok(List(result))
     }
+
+    async fn list_keys_page(
+        &self,
+        p: &Pattern,
+        start_after: &str,
+        limit: usize,
+    ) -> TribResult<KeyPage> {
+        let kvl = self.kv_list.read().map_err(|e| e.to_string())?;
+        let expiry = self.expiry.read().map_err(|e| e.to_string())?;
+        let now = now_ms();
+        let keys = matching_text(&kvl, p, start_after)
+            .filter(|(k, _)| !expired(&expiry.lists, k, now))
+            .map(|(_, k)| k);
+        Ok(KeyPage::from_sorted(keys, limit))
+    }
+
+    async fn multi_list_get(&self, keys: &[String]) -> TribResult<Vec<List>> {
+        let now = now_ms();
+        keys.iter()
+            .map(|k| self.with_list(k.as_bytes(), now, |l| text_list(l.to_vec())))
+            .collect()
+    }
+
+    async fn list_append_many(&self, key: &str, values: &[String]) -> TribResult<bool> {
+        self.list_push(key.as_bytes(), values, now_ms(), None)
+    }
+
+    async fn list_append_many_with_ttl(
+        &self,
+        key: &str,
+        values: &[String],
+        ttl: Duration,
+    ) -> TribResult<bool> {
+        let now = now_ms();
+        self.list_push(key.as_bytes(), values, now, Some(deadline(now, ttl)))
+    }
+}
+
+#[async_trait]
+impl KeyBytes for MemStorage {
+    async fn get_bytes(&self, key: &[u8]) -> TribResult<Option<Vec<u8>>> {
+        let kvs = self.kvs.read().map_err(|e| e.to_string())?;
+        let expiry = self.expiry.read().map_err(|e| e.to_string())?;
+        if expired(&expiry.kvs, key, now_ms()) {
+            return Ok(None);
+        }
+        match kvs.get(key) {
+            Some(v) => {
+                self.touch(std::iter::once(key))?;
+                Ok(Some(v.clone()))
+            }
+            None => Ok(None),
+        }
+    }
+
+    async fn set_bytes(&self, key: &[u8], value: &[u8]) -> TribResult<bool> {
+        self.set_until(key, value, None)
+    }
+
+    async fn keys_bytes(&self, prefix: &[u8]) -> TribResult<Vec<Vec<u8>>> {
+        let kvs = self.kvs.read().map_err(|e| e.to_string())?;
+        let expiry = self.expiry.read().map_err(|e| e.to_string())?;
+        let now = now_ms();
+        Ok(matching(&kvs, prefix, b"", b"")
+            .filter(|k| !expired(&expiry.kvs, k, now))
+            .cloned()
+            .collect())
+    }
+}
+
+#[async_trait]
+impl ListBytes for MemStorage {
+    async fn list_get_bytes(&self, key: &[u8]) -> TribResult<Vec<Vec<u8>>> {
+        self.with_list(key, now_ms(), |l| Ok(l.to_vec()))
+    }
+
+    async fn list_append_bytes(&self, key: &[u8], value: &[u8]) -> TribResult<bool> {
+        self.list_push(key, &[value], now_ms(), None)
+    }
+
+    async fn list_remove_bytes(&self, key: &[u8], value: &[u8]) -> TribResult<u32> {
+        self.write(|maps, changes| Ok(maps.remove(key, value, now_ms(), changes) as u32))
+    }
+
+    async fn list_keys_bytes(&self, prefix: &[u8]) -> TribResult<Vec<Vec<u8>>> {
+        let kvl = self.kv_list.read().map_err(|e| e.to_string())?;
+        let expiry = self.expiry.read().map_err(|e| e.to_string())?;
+        let now = now_ms();
+        Ok(matching(&kvl, prefix, b"", b"")
+            .filter(|k| !expired(&expiry.lists, k, now))
+            .cloned()
+            .collect())
+    }
 }
 
 #[async_trait]
 impl Storage for MemStorage {
     async fn clock(&self, at_least: u64) -> TribResult<u64> {
+        // the lock holds the smallest reading that may be handed out next
         let mut clk = self.clock.write().map_err(|e| e.to_string())?;
-        if *clk < at_least {
-            *clk = at_least
-        }
+        let ret = Hlc::tick(Hlc::from_u64(*clk), Hlc::from_u64(at_least));
+        *clk = ret.successor().to_u64();
+        Ok(ret.to_u64())
+    }
 
-        let ret = *clk;
+    async fn transaction(&self, txn: &Txn) -> TribResult<TxnResult> {
+        self.transaction_at(txn, now_ms())
+    }
 
-        if *clk < u64::MAX {
-            *clk += 1;
-        }
-        Ok(ret)
+    fn watcher(&self) -> Option<&dyn Watch> {
+        Some(self)
+    }
+
+    fn bytes(&self) -> Option<&dyn ByteStorage> {
+        Some(self)
+    }
+
+    fn set_memory_limit(&self, limit: MemoryLimit) -> TribResult<()> {
+        self.locked(|maps, _| {
+            maps.quota.set_limit(Some(limit), maps.kvs);
+            self.evicting
+                .store(maps.quota.evicting(), Ordering::Relaxed);
+            Ok(())
+        })
+    }
+
+    async fn stats(&self) -> TribResult<StorageStats> {
+        let kvs = self.kvs.read().map_err(|e| e.to_string())?;
+        let kvl = self.kv_list.read().map_err(|e| e.to_string())?;
+        let quota = self.quota.lock().map_err(|e| e.to_string())?;
+        Ok(stats_of(&quota, kvs.len(), kvl.len()))
+    }
+}
+
+#[async_trait]
+impl Watch for MemStorage {
+    async fn watch(&self, p: &Pattern, since: u64) -> TribResult<ChangeStream> {
+        watch_changes(&self.changes, p, since)
     }
 }
 
@@ -224,16 +1770,49 @@ impl Storage for MemStorage {
 pub trait BinStorage: Send + Sync {
     /// Fetch a [Storage] bin based on the given bin name.
     async fn bin(&self, name: &str) -> TribResult<Box<dyn Storage>>;
+
+    /// Gets the list `key` from every bin in `names`. The result has one list
+    /// per bin, in the same order.
+    async fn multi_bin_list_get(&self, names: &[String], key: &str) -> TribResult<Vec<List>> {
+        let mut lists = Vec::with_capacity(names.len());
+        for name in names {
+            lists.push(self.bin(name).await?.list_get(key).await?);
+        }
+        Ok(lists)
+    }
+
+    /// Gets the elements `start` to `end` of the list `key` from every bin in
+    /// `names`, see [List::range]. The result has one list per bin, in the
+    /// same order.
+    async fn multi_bin_list_range(
+        &self,
+        names: &[String],
+        key: &str,
+        start: i64,
+        end: i64,
+    ) -> TribResult<Vec<List>> {
+        let mut lists = Vec::with_capacity(names.len());
+        for name in names {
+            lists.push(self.bin(name).await?.list_range(key, start, end).await?);
+        }
+        Ok(lists)
+    }
 }
 
 #[cfg(test)]
 mod test {
+    use std::time::Duration;
+
     use crate::{
-        err::TribResult,
+        err::{TribResult, TribblerError},
+        hlc::Hlc,
         storage::{KeyValue, Pattern, Storage},
     };
 
-    use super::{KeyList, KeyString, MemStorage};
+    use super::{
+        ChangeKind, EvictionPolicy, KeyBytes, KeyList, KeyString, ListBytes, MemStorage,
+        MemoryLimit, Precondition, Txn, TxnOp, Watch, WATCH_HISTORY,
+    };
 
     async fn setup_test_storage() -> MemStorage {
         let storage = MemStorage::new();
@@ -277,6 +1856,46 @@ mod test {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn storage_cas() -> TribResult<()> {
+        let storage = setup_test_storage().await;
+        assert_eq!(false, storage.cas("test", None, "v1").await?);
+        assert_eq!(
+            false,
+            storage.cas("test", Some("nope".to_string()), "v1").await?
+        );
+        assert_eq!(
+            true,
+            storage
+                .cas("test", Some("test-value".to_string()), "v1")
+                .await?
+        );
+        assert_eq!(Some("v1".to_string()), storage.get("test").await?);
+        assert_eq!(true, storage.cas("new", None, "v2").await?);
+        assert_eq!(false, storage.cas("new", None, "v3").await?);
+        assert_eq!(true, storage.cas("new", Some("v2".to_string()), "").await?);
+        assert_eq!(None, storage.get("new").await?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn storage_multi() -> TribResult<()> {
+        let storage = setup_test_storage().await;
+        let keys = vec!["test".to_string(), "none".to_string()];
+        assert_eq!(
+            vec![Some("test-value".to_string()), None],
+            storage.multi_get(&keys).await?
+        );
+        let values = vec!["a".to_string(), "b".to_string()];
+        assert_eq!(true, storage.list_append_many("test", &values).await?);
+        assert_eq!(true, storage.list_append_many("empty", &[]).await?);
+        let lists = storage.multi_list_get(&keys).await?;
+        assert_eq!(vec!["test-value", "a", "b"], lists[0].0);
+        assert_eq!(0, lists[1].0.len());
+        assert_eq!(1, storage.list_keys(&Pattern::default()).await?.0.len());
+        Ok(())
+    }
+
     #[tokio::test]
     async fn storage_keys() {
         let storage = setup_test_storage().await;
@@ -397,10 +2016,394 @@ mod test {
         assert_eq!(1, storage.list_keys(&p5).await.unwrap().0.len());
     }
 
+    #[tokio::test]
+    async fn storage_ttl() -> TribResult<()> {
+        let storage = setup_test_storage().await;
+        let ttl = Duration::from_millis(50);
+        storage.set_with_ttl(&KeyValue::new("t", "v"), ttl).await?;
+        storage.set_with_ttl(&KeyValue::new("p", "v"), ttl).await?;
+        storage.set(&KeyValue::new("p", "w")).await?;
+        assert_eq!(Some("v".to_string()), storage.get("t").await?);
+        assert_eq!(3, storage.keys(&Pattern::default()).await?.0.len());
+        assert!(storage.ttl("t").await?.unwrap() <= ttl);
+        // a plain set makes the key permanent again
+        assert_eq!(None, storage.ttl("p").await?);
+
+        tokio::time::sleep(ttl * 2).await;
+        assert_eq!(None, storage.get("t").await?);
+        assert_eq!(Some("w".to_string()), storage.get("p").await?);
+        let keys = vec!["t".to_string(), "p".to_string()];
+        assert_eq!(
+            vec![None, Some("w".to_string())],
+            storage.multi_get(&keys).await?
+        );
+        assert_eq!(2, storage.keys(&Pattern::default()).await?.0.len());
+        assert_eq!(true, storage.cas("t", None, "again").await?);
+        assert_eq!(Some("again".to_string()), storage.get("t").await?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn storage_list_ttl() -> TribResult<()> {
+        let storage = setup_test_storage().await;
+        let ttl = Duration::from_millis(50);
+        storage
+            .list_append_with_ttl(&KeyValue::new("l", "a"), ttl)
+            .await?;
+        storage.list_append(&KeyValue::new("l", "b")).await?;
+        assert_eq!(vec!["a", "b"], storage.list_get("l").await?.0);
+        assert!(storage.list_ttl("l").await?.unwrap() <= ttl);
+
+        tokio::time::sleep(ttl * 2).await;
+        assert_eq!(0, storage.list_get("l").await?.0.len());
+        assert_eq!(
+            vec!["test".to_string()],
+            storage.list_keys(&Pattern::default()).await?.0
+        );
+        // an expired list is replaced, not extended
+        storage.list_append(&KeyValue::new("l", "c")).await?;
+        assert_eq!(vec!["c"], storage.list_get("l").await?.0);
+        assert_eq!(None, storage.list_ttl("l").await?);
+        tokio::time::sleep(ttl * 2).await;
+        assert_eq!(vec!["c"], storage.list_get("l").await?.0);
+
+        // a batch takes its deadline along in the same write
+        let batch = vec!["d".to_string(), "e".to_string()];
+        storage.list_append_many_with_ttl("m", &batch, ttl).await?;
+        assert_eq!(batch, storage.list_get("m").await?.0);
+        assert!(storage.list_ttl("m").await?.unwrap() <= ttl);
+        tokio::time::sleep(ttl * 2).await;
+        assert_eq!(0, storage.list_get("m").await?.0.len());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn storage_sweep() -> TribResult<()> {
+        let storage = setup_test_storage().await;
+        let ttl = Duration::from_millis(10);
+        storage.set_with_ttl(&KeyValue::new("t", "v"), ttl).await?;
+        storage
+            .list_append_with_ttl(&KeyValue::new("l", "a"), ttl)
+            .await?;
+        assert_eq!(0, storage.sweep()?);
+        tokio::time::sleep(ttl * 3).await;
+        assert_eq!(2, storage.sweep()?);
+        assert_eq!(0, storage.sweep()?);
+        assert_eq!(Some("test-value".to_string()), storage.get("test").await?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn storage_keys_page() -> TribResult<()> {
+        let storage = MemStorage::new();
+        for k in ["b2", "a", "b1", "b3", "c", "b0"] {
+            storage.set(&KeyValue::new(k, "v")).await?;
+            storage.list_append(&KeyValue::new(k, "v")).await?;
+        }
+        let p = Pattern {
+            prefix: "b".to_string(),
+            suffix: "".to_string(),
+        };
+        assert_eq!(vec!["b0", "b1", "b2", "b3"], storage.keys(&p).await?.0);
+
+        let page = storage.keys_page(&p, "", 3).await?;
+        assert_eq!(vec!["b0", "b1", "b2"], page.keys.0);
+        assert_eq!(Some("b2".to_string()), page.next);
+        let page = storage.keys_page(&p, "b2", 3).await?;
+        assert_eq!(vec!["b3"], page.keys.0);
+        assert_eq!(None, page.next);
+        // a cursor before the prefix starts at the prefix
+        let page = storage.list_keys_page(&p, "a", 4).await?;
+        assert_eq!(vec!["b0", "b1", "b2", "b3"], page.keys.0);
+        assert_eq!(None, page.next);
+        let all = storage
+            .list_keys_page(&Pattern::default(), "b3", 10)
+            .await?;
+        assert_eq!(vec!["c"], all.keys.0);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn storage_list_range_trim() -> TribResult<()> {
+        let storage = MemStorage::new();
+        for v in ["a", "b", "c", "d", "e"] {
+            storage.list_append(&KeyValue::new("l", v)).await?;
+        }
+        assert_eq!(5, storage.list_len("l").await?);
+        assert_eq!(0, storage.list_len("none").await?);
+        assert_eq!(vec!["b", "c"], storage.list_range("l", 1, 2).await?.0);
+        assert_eq!(vec!["d", "e"], storage.list_range("l", -2, -1).await?.0);
+        assert_eq!(
+            vec!["a", "b", "c", "d", "e"],
+            storage.list_range("l", -10, 10).await?.0
+        );
+        assert_eq!(0, storage.list_range("l", 3, 1).await?.0.len());
+        let lists = storage
+            .multi_list_range(&["l".to_string(), "none".to_string()], 0, 0)
+            .await?;
+        assert_eq!(vec!["a"], lists[0].0);
+        assert_eq!(0, lists[1].0.len());
+
+        assert_eq!(2, storage.list_trim("l", 3).await?);
+        assert_eq!(vec!["c", "d", "e"], storage.list_get("l").await?.0);
+        assert_eq!(0, storage.list_trim("l", 10).await?);
+        // trimming everything away removes the list
+        assert_eq!(3, storage.list_trim("l", 0).await?);
+        assert_eq!(0, storage.list_keys(&Pattern::default()).await?.0.len());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn storage_watch() -> TribResult<()> {
+        let storage = MemStorage::new();
+        let p = Pattern {
+            prefix: "w".to_string(),
+            suffix: "".to_string(),
+        };
+        let mut w = storage.watch(&p, 0).await?;
+        storage.set(&KeyValue::new("w1", "a")).await?;
+        storage.set(&KeyValue::new("other", "b")).await?;
+        storage.list_append(&KeyValue::new("w2", "c")).await?;
+        storage.list_trim("w2", 0).await?;
+
+        let first = w.recv().await.unwrap()?;
+        assert_eq!(
+            (ChangeKind::Set, "w1", "a"),
+            (first.kind, &*first.key, &*first.value)
+        );
+        let second = w.recv().await.unwrap()?;
+        assert_eq!(ChangeKind::ListAppend, second.kind);
+        let third = w.recv().await.unwrap()?;
+        assert_eq!((ChangeKind::ListTrim, "0"), (third.kind, &*third.value));
+        // "other" took the number between the first two
+        assert_eq!(first.seq + 2, second.seq);
+
+        // resuming replays what came after the given change
+        let mut again = storage.watch(&p, first.seq).await?;
+        assert_eq!(second, again.recv().await.unwrap()?);
+        assert_eq!(third, again.recv().await.unwrap()?);
+        assert!(storage.watch(&p, third.seq + 1).await.is_err());
+
+        for i in 0..WATCH_HISTORY {
+            storage.set(&KeyValue::new("w1", &i.to_string())).await?;
+        }
+        let gone = storage.watch(&p, first.seq).await.unwrap_err();
+        assert!(matches!(
+            gone.downcast_ref::<TribblerError>(),
+            Some(TribblerError::OutOfRange(_))
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn storage_transaction() -> TribResult<()> {
+        let storage = setup_test_storage().await;
+        let txn = Txn {
+            checks: vec![
+                Precondition::Value {
+                    key: "test".to_string(),
+                    value: "test-value".to_string(),
+                },
+                Precondition::ListLen {
+                    key: "l".to_string(),
+                    len: 0,
+                },
+            ],
+            ops: vec![
+                TxnOp::Get("k".to_string()),
+                TxnOp::Set(KeyValue::new("k", "v")),
+                TxnOp::Get("k".to_string()),
+                TxnOp::ListAppend(KeyValue::new("l", "a")),
+                TxnOp::ListRemove(KeyValue::new("test", "test-value")),
+            ],
+        };
+        let r = storage.transaction(&txn).await?;
+        assert!(r.committed);
+        assert_eq!(vec![None, Some("v".to_string())], r.values);
+        assert_eq!(vec!["a"], storage.list_get("l").await?.0);
+        assert_eq!(0, storage.list_len("test").await?);
+
+        // the list is no longer empty, so nothing happens the second time
+        let r = storage.transaction(&txn).await?;
+        assert!(!r.committed);
+        assert_eq!(0, r.values.len());
+        assert_eq!(vec!["a"], storage.list_get("l").await?.0);
+
+        // an expired key counts as unset
+        storage
+            .set_with_ttl(&KeyValue::new("t", "x"), Duration::from_millis(10))
+            .await?;
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        let unset = Txn {
+            checks: vec![Precondition::Value {
+                key: "t".to_string(),
+                value: "".to_string(),
+            }],
+            ops: vec![TxnOp::Set(KeyValue::new("t", "y"))],
+        };
+        assert!(storage.transaction(&unset).await?.committed);
+        assert_eq!(Some("y".to_string()), storage.get("t").await?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn storage_memory_accounting() -> TribResult<()> {
+        let storage = MemStorage::new();
+        storage.set(&KeyValue::new("key", "value")).await?;
+        assert_eq!(8, storage.stats().await?.used_bytes);
+        storage.set(&KeyValue::new("key", "v")).await?;
+        assert_eq!(4, storage.stats().await?.used_bytes);
+        storage.set(&KeyValue::new("key", "")).await?;
+        assert_eq!(0, storage.stats().await?.used_bytes);
+
+        for v in ["aa", "b", "aa", "ccc"] {
+            storage.list_append(&KeyValue::new("l", v)).await?;
+        }
+        assert_eq!(9, storage.stats().await?.used_bytes);
+        storage.list_remove(&KeyValue::new("l", "aa")).await?;
+        assert_eq!(5, storage.stats().await?.used_bytes);
+        storage.list_trim("l", 1).await?;
+        assert_eq!(4, storage.stats().await?.used_bytes);
+        storage.list_trim("l", 0).await?;
+        let stats = storage.stats().await?;
+        assert_eq!(0, stats.used_bytes);
+        assert_eq!((0, 0), (stats.keys, stats.lists));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn storage_memory_reject() -> TribResult<()> {
+        let storage = MemStorage::new();
+        storage.set_memory_limit(MemoryLimit {
+            bytes: 10,
+            policy: EvictionPolicy::Reject,
+        })?;
+        storage.set(&KeyValue::new("a", "1234")).await?;
+        storage.set(&KeyValue::new("b", "1234")).await?;
+        let err = storage.set(&KeyValue::new("c", "1234")).await.unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<TribblerError>(),
+            Some(TribblerError::QuotaExceeded(_))
+        ));
+        assert!(err.to_string().contains("10 bytes"));
+        assert!(storage.list_append(&KeyValue::new("l", "x")).await.is_err());
+        // writes which do not grow anything still go through
+        storage.set(&KeyValue::new("a", "12")).await?;
+        storage.set(&KeyValue::new("b", "")).await?;
+        storage.list_append(&KeyValue::new("l", "x")).await?;
+
+        // a transaction either fits as a whole or does not happen
+        let txn = Txn {
+            checks: vec![],
+            ops: vec![
+                TxnOp::Set(KeyValue::new("d", "1")),
+                TxnOp::Set(KeyValue::new("e", "1234")),
+            ],
+        };
+        assert!(storage.transaction(&txn).await.is_err());
+        assert_eq!(None, storage.get("d").await?);
+
+        let stats = storage.stats().await?;
+        assert_eq!(5, stats.used_bytes);
+        assert_eq!(Some(10), stats.limit_bytes);
+        assert_eq!(3, stats.rejected);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn storage_memory_evict_lru() -> TribResult<()> {
+        let storage = MemStorage::new();
+        storage.set(&KeyValue::new("a", "1234")).await?;
+        storage.set(&KeyValue::new("b", "1234")).await?;
+        storage.set_memory_limit(MemoryLimit {
+            bytes: 12,
+            policy: EvictionPolicy::EvictLru,
+        })?;
+        let mut changes = storage.watch(&Pattern::default(), 0).await?;
+        assert_eq!(Some("1234".to_string()), storage.get("a").await?);
+        // b has been used least recently, so it makes room for c
+        storage.set(&KeyValue::new("c", "1234")).await?;
+        assert_eq!(None, storage.get("b").await?);
+        assert_eq!(Some("1234".to_string()), storage.get("a").await?);
+        let change = changes.recv().await.unwrap()?;
+        assert_eq!((ChangeKind::Evict, "b"), (change.kind, change.key.as_str()));
+
+        // lists are counted but never evicted
+        storage.list_append(&KeyValue::new("l", "12345")).await?;
+        assert_eq!(None, storage.get("c").await?);
+        assert!(storage
+            .list_append(&KeyValue::new("l", "1234567"))
+            .await
+            .is_err());
+        let stats = storage.stats().await?;
+        assert_eq!((2, 1), (stats.evicted, stats.rejected));
+        assert!(stats.used_bytes <= 12);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn storage_bytes() -> TribResult<()> {
+        let storage = MemStorage::new();
+        let binary = [0xff, 0x00, 0xfe];
+        assert!(storage.set_bytes(b"raw", &binary).await?);
+        assert_eq!(Some(binary.to_vec()), storage.get_bytes(b"raw").await?);
+        assert!(storage.get("raw").await.is_err());
+        let txn = Txn {
+            checks: vec![],
+            ops: vec![
+                TxnOp::Set(KeyValue::new("a", "1")),
+                TxnOp::Get("raw".to_string()),
+            ],
+        };
+        assert!(storage.transaction(&txn).await.is_err());
+        assert_eq!(None, storage.get("a").await?);
+
+        // text written through either side reads back through the other
+        storage.set(&KeyValue::new("text", "v")).await?;
+        assert_eq!(Some(b"v".to_vec()), storage.get_bytes(b"text").await?);
+        storage.set_bytes(b"text", b"w").await?;
+        assert_eq!(Some("w".to_string()), storage.get("text").await?);
+
+        // keys which are not UTF-8 only show up as bytes
+        storage.set_bytes(&[b't', 0xff], b"v").await?;
+        let p = Pattern {
+            prefix: "t".to_string(),
+            suffix: String::new(),
+        };
+        assert_eq!(vec!["text"], storage.keys(&p).await?.0);
+        assert_eq!(
+            vec![b"text".to_vec(), vec![b't', 0xff]],
+            storage.keys_bytes(b"t").await?
+        );
+
+        storage.list_append_bytes(b"l", &binary).await?;
+        storage.list_append_bytes(b"l", b"x").await?;
+        assert!(storage.list_get("l").await.is_err());
+        assert_eq!(1, storage.list_remove_bytes(b"l", &binary).await?);
+        assert_eq!(vec!["x"], storage.list_get("l").await?.0);
+        assert_eq!(vec![b"l".to_vec()], storage.list_keys_bytes(b"").await?);
+        Ok(())
+    }
+
     #[tokio::test]
     async fn clock_at_least() {
         let storage = setup_test_storage().await;
-        assert_eq!(1234, storage.clock(1234).await.unwrap());
+        let ahead = Hlc {
+            physical: Hlc::now().physical + 60_000,
+            logical: 0,
+        }
+        .to_u64();
+        assert_eq!(ahead, storage.clock(ahead).await.unwrap());
+        assert_eq!(ahead + 1, storage.clock(1234).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn clock_follows_wall_time() {
+        let storage = setup_test_storage().await;
+        let before = Hlc::now();
+        let c = Hlc::from_u64(storage.clock(1234).await.unwrap());
+        assert!(c >= before);
+        assert!(c.physical <= Hlc::now().physical);
     }
 
     #[tokio::test]
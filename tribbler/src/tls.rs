@@ -0,0 +1,58 @@
+//! module which issues the certificates of a [TlsConfig]: a self-signed
+//! certificate authority, and certificates signed by it for every back-end
+//! and keeper and for their clients.
+//!
+//! The authority's private key is not kept, so no certificate can be added
+//! later; issue a new set for a new cluster instead.
+use std::fs;
+use std::path::Path;
+
+use rcgen::{BasicConstraints, Certificate, CertificateParams, DnType, IsCa};
+
+use crate::config::{CertFiles, TlsConfig, TLS_DOMAIN};
+use crate::err::TribResult;
+
+/// Issues the certificates for the back-ends and keepers at `nodes`, and
+/// one for their clients, writes them as PEM files into `dir`, and returns
+/// the [TlsConfig] pointing at them.
+pub fn generate(dir: &Path, nodes: &[String]) -> TribResult<TlsConfig> {
+    fs::create_dir_all(dir)?;
+    // the config may be read from anywhere
+    let dir = &fs::canonicalize(dir)?;
+    let mut params = CertificateParams::new(vec![]);
+    params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+    params
+        .distinguished_name
+        .push(DnType::CommonName, "tribbler authority");
+    let authority = Certificate::from_params(params)?;
+    let ca = dir.join("ca.pem");
+    fs::write(&ca, authority.serialize_pem()?)?;
+
+    let issue = |name: &str, file: &str| -> TribResult<CertFiles> {
+        let mut params = CertificateParams::new(vec![TLS_DOMAIN.to_string()]);
+        params.distinguished_name.push(DnType::CommonName, name);
+        let cert = Certificate::from_params(params)?;
+        let files = CertFiles {
+            cert: path_string(&dir.join(format!("{}.pem", file))),
+            key: path_string(&dir.join(format!("{}.key", file))),
+        };
+        fs::write(&files.cert, cert.serialize_pem_with_signer(&authority)?)?;
+        fs::write(&files.key, cert.serialize_private_key_pem())?;
+        Ok(files)
+    };
+    let mut tls = TlsConfig {
+        ca: path_string(&ca),
+        nodes: Default::default(),
+        client: issue("tribbler client", "client")?,
+    };
+    for addr in nodes {
+        // colons are not allowed in file names everywhere
+        let files = issue(addr, &addr.replace(':', "_"))?;
+        tls.nodes.insert(addr.to_string(), files);
+    }
+    Ok(tls)
+}
+
+fn path_string(p: &Path) -> String {
+    p.to_string_lossy().into_owned()
+}
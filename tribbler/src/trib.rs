@@ -35,9 +35,9 @@ pub struct Trib {
     pub user: String,
     /// the content of the trib
     pub message: String,
-    /// the physical timestamp when posted
+    /// the physical timestamp when posted, in seconds since the Unix epoch
     pub time: u64,
-    /// the logical timestamp when posted
+    /// the packed [hybrid logical clock](crate::hlc) reading when posted
     pub clock: u64,
 }
 
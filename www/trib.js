@@ -1,6 +1,6 @@
 // Generated by CoffeeScript 2.6.1
 (function() {
-  var _postRetrib, _postTrib, _showHome, _showUser, _updateFollow, _updateFollowing, addUser, appendError, countPostLength, follow, hoveringFollow, lclock, listTribs, listUsers, main, me, postDone, postTrib, seenClock, showHome, showUser, showing, signIn, signOut, unfollow, updateFollow, updateFollowing, updateUsers;
+  var _postRetrib, _postTrib, _showHome, _showUser, _updateFollow, _updateFollowing, addUser, appendError, countPostLength, credentials, follow, hoveringFollow, lclock, listTribs, listUsers, main, me, postDone, postTrib, seenClock, showHome, showUser, showing, signIn, signOut, signedIn, unfollow, updateFollow, updateFollowing, updateUsers;
 
   me = "";
 
@@ -141,18 +141,28 @@
     $("#users li").click(showUser);
   };
 
+  // the password may hold characters the form encoding would mangle
+  credentials = function(name, password) {
+    return encodeURIComponent(JSON.stringify({
+      user: name,
+      password: password
+    }));
+  };
+
   addUser = function() {
-    var name;
+    var name, password;
     name = $("form#adduser input#username").val();
-    if (name === "") {
+    password = $("form#adduser input#password").val();
+    if (name === "" || password === "") {
       return false;
     }
     $("form#adduser input#username").val("");
+    $("form#adduser input#password").val("");
     console.log("add user", name);
     $.ajax({
       url: "api/add-user",
       type: "POST",
-      data: name,
+      data: credentials(name, password),
       success: updateUsers,
       cache: false
     });
@@ -173,11 +183,30 @@
   };
 
   signIn = function(ev) {
+    var password;
     ev.preventDefault();
     if (showing === "" || showing === "!home") {
       return;
     }
     console.log("sign in as: " + showing);
+    password = $("input#signinpass").val();
+    $("input#signinpass").val("");
+    $.ajax({
+      url: "api/login",
+      type: "POST",
+      data: credentials(showing, password),
+      success: signedIn,
+      cache: false
+    });
+  };
+
+  signedIn = function(data) {
+    var ret;
+    ret = JSON.parse(data);
+    if (ret.err !== "") {
+      appendError(ret.err);
+      return;
+    }
     me = showing;
     $("div#who").show();
     $("div#who h3").html("Signed in as " + me);
@@ -192,6 +221,11 @@
   signOut = function(ev) {
     console.log("sign out");
     ev.preventDefault();
+    $.ajax({
+      url: "api/logout",
+      type: "POST",
+      cache: false
+    });
     me = "";
     $("div#who").hide();
     $("div#compose").hide();
@@ -403,6 +437,11 @@
   };
 
   main = function() {
+    $.ajaxSetup({
+      error: function(xhr) {
+        return appendError(xhr.responseText);
+      }
+    });
     $("form#adduser").submit(addUser);
     $("form#post").submit(postTrib);
     $("div#errors").hide();